@@ -0,0 +1,725 @@
+//! Parsing of command-line arguments.
+
+use crate::services::{parse_timestamp, ChannelFilter, UploadDate};
+use crate::types::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Usage information for the command-line interface.
+pub const USAGE: &str = "\
+Usage: ytdl [--json] <command> [options]
+
+Run without a command to open the application window.
+
+Commands:
+  download <url>              Download a video, playlist or channel
+  info <url>                  Show information about a video
+  formats <url>               List the streams available for a video
+  subs <url>                  List the subtitle languages available for a video
+  deps install                Install missing or unverified dependencies of the
+                              configured extractor
+  deps update                 Install missing or unverified dependencies and
+                              update youtube-dl or yt-dlp
+  deps status                 Show which dependency binaries are run, where
+                              they were found and their versions
+  config get [key]            Show the configuration, or a single value of it
+  config set <key> <value>    Change a configuration value
+  help                        Show this message
+
+Download options:
+  --type <type>               video, audio, thumbnail or subtitles
+  --output <directory>        The directory to save downloads to
+  --format <selector>         An explicit youtube-dl format selector
+  --max-height <pixels>       The maximum video height
+  --codec <codec>             any, av1, vp9 or h264
+  --audio-format <format>     mp3, m4a, opus, flac, wav or ogg
+  --audio-quality <quality>   A bitrate such as 192, or a VBR level such as v2
+  --template <template>       The output filename template
+  --on-collision <policy>     overwrite, skip or rename
+  --ignore-archive            Download even if recorded in the download archive
+  --start <time>              Keep the video from a time such as 1:30 or 1m30s
+  --end <time>                Keep the video up to a time
+  --accurate-clip             Re-encode to cut exactly at the start and end times
+  --subs <languages>          Comma-separated subtitle languages, such as en,fr
+  --auto-subs <languages>     Comma-separated automatic caption languages
+  --sub-format <format>       srt or vtt
+  --embed-subs                Embed subtitles into the video instead of saving
+                              them alongside it
+  --split-chapters            Save one file per chapter of the video, with any
+                              subtitles saved alongside the chapters
+
+Channel options:
+  --after <date>              Only download videos uploaded on or after a date
+                              such as 2024-01-31
+  --max-videos <count>        Only download this many of the newest videos
+  --skip-shorts               Exclude YouTube Shorts
+
+Global options:
+  --json                      Print machine-readable JSON output
+  -h, --help                  Show this message
+  --version                   Show the application version
+
+Options not given default to the saved configuration. Timestamps in a
+video URL are used as the start and end times unless given.";
+
+/// An error in the command-line arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsageError {
+    /// A description of the error.
+    message: String,
+}
+
+impl UsageError {
+    /// Creates a new usage error.
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// Options for the `download` command. Options that were not given are
+/// `None`, and are taken from the configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DownloadArgs {
+    /// The URL of the video, playlist or channel to download.
+    pub url: String,
+    /// The content type to download.
+    pub content_type: Option<ContentType>,
+    /// The directory to save downloads to.
+    pub output_directory: Option<PathBuf>,
+    /// An explicit youtube-dl format selector.
+    pub format_selector: Option<String>,
+    /// The maximum video height in pixels.
+    pub max_height: Option<u32>,
+    /// The preferred video codec.
+    pub codec: Option<VideoCodec>,
+    /// The audio output format.
+    pub audio_format: Option<AudioFormat>,
+    /// The audio output quality.
+    pub audio_quality: Option<AudioQuality>,
+    /// The output filename template.
+    pub filename_template: Option<String>,
+    /// How to handle existing files at output paths.
+    pub collision_policy: Option<CollisionPolicy>,
+    /// Whether to download even if recorded in the download archive.
+    pub ignore_archive: bool,
+    /// The time to begin the download at.
+    pub start: Option<Duration>,
+    /// The time to end the download at.
+    pub end: Option<Duration>,
+    /// Whether to re-encode clips so that they are cut exactly.
+    pub accurate_clip: bool,
+    /// The subtitle tracks to download.
+    pub subtitle_tracks: Vec<SubtitleTrack>,
+    /// The format to save subtitles in.
+    pub subtitle_format: Option<SubtitleFormat>,
+    /// Whether to embed subtitles into the video.
+    pub embed_subtitles: bool,
+    /// Whether to split audio or video into one file per chapter.
+    pub split_chapters: bool,
+    /// The filters to apply when downloading a channel's uploads.
+    pub channel_filter: ChannelFilter,
+}
+
+/// A `deps` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepsCommand {
+    /// Install missing dependencies.
+    Install,
+    /// Install missing dependencies and update youtube-dl.
+    Update,
+    /// Show whether the dependencies are installed.
+    Status,
+}
+
+/// A `config` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConfigCommand {
+    /// Show the configuration, or the value of a single key.
+    Get(Option<String>),
+    /// Change the value of a key.
+    Set(String, String),
+}
+
+/// A command given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Download a video, playlist or channel.
+    Download(DownloadArgs),
+    /// Show information about a video.
+    Info(String),
+    /// List the streams available for a video.
+    Formats(String),
+    /// List the subtitle tracks available for a video.
+    Subtitles(String),
+    /// Manage the dependency binaries.
+    Deps(DepsCommand),
+    /// View or change the configuration.
+    Config(ConfigCommand),
+    /// Show usage information.
+    Help,
+    /// Show the application version.
+    Version,
+}
+
+/// Parsed command-line arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Args {
+    /// Whether to print JSON output.
+    pub json: bool,
+    /// The command to run.
+    pub command: Command,
+}
+
+/// Options that take no value.
+const FLAG_OPTIONS: [&str; 8] = [
+    "json",
+    "help",
+    "version",
+    "ignore-archive",
+    "accurate-clip",
+    "embed-subs",
+    "split-chapters",
+    "skip-shorts",
+];
+
+/// Parses a value by finding the choice whose name matches it, ignoring
+/// case.
+fn parse_choice<T: Copy>(
+    option: &str,
+    value: &str,
+    choices: &[(&str, T)],
+) -> Result<T, UsageError> {
+    choices
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| {
+            let names = choices
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            UsageError::new(format!(
+                "invalid value for --{}: {} (expected one of {})",
+                option, value, names
+            ))
+        })
+}
+
+/// Parses an audio quality, either a bitrate in kilobits per second such as
+/// `192` or `192k`, or a VBR level such as `v2`.
+fn parse_audio_quality(value: &str) -> Result<AudioQuality, UsageError> {
+    let value = value.to_lowercase();
+    let quality = match value.strip_prefix('v') {
+        Some(level) => level.parse().ok().map(AudioQuality::Vbr),
+        None => value
+            .trim_end_matches('k')
+            .parse()
+            .ok()
+            .map(AudioQuality::Bitrate),
+    };
+
+    quality.ok_or_else(|| UsageError::new(format!("invalid audio quality: {}", value)))
+}
+
+/// Parses a time within a video.
+fn parse_time(option: &str, value: &str) -> Result<Duration, UsageError> {
+    parse_timestamp(value)
+        .ok_or_else(|| UsageError::new(format!("invalid value for --{}: {}", option, value)))
+}
+
+/// Parses a comma-separated list of subtitle languages into tracks.
+fn parse_subtitle_tracks(value: &str, automatic: bool) -> Vec<SubtitleTrack> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|language| !language.is_empty())
+        .map(|language| SubtitleTrack {
+            language: language.to_owned(),
+            automatic,
+        })
+        .collect()
+}
+
+/// Builds the options of the `download` command.
+fn download_args(
+    url: String,
+    mut options: HashMap<String, Option<String>>,
+) -> Result<DownloadArgs, UsageError> {
+    let ignore_archive = options.remove("ignore-archive").is_some();
+    let accurate_clip = options.remove("accurate-clip").is_some();
+    let embed_subtitles = options.remove("embed-subs").is_some();
+    let split_chapters = options.remove("split-chapters").is_some();
+    let skip_shorts = options.remove("skip-shorts").is_some();
+
+    let mut value = |name: &str| options.remove(name).flatten();
+    let content_types = [
+        ContentType::Video,
+        ContentType::Audio,
+        ContentType::Thumbnail,
+        ContentType::Subtitles,
+    ]
+    .map(|content_type| (content_type.type_name(), content_type));
+    let codecs = [
+        ("any", VideoCodec::Any),
+        ("av1", VideoCodec::Av1),
+        ("vp9", VideoCodec::Vp9),
+        ("h264", VideoCodec::H264),
+    ];
+    let audio_formats = AudioFormat::ALL.map(|format| (format.extension(), format));
+    let subtitle_formats = SubtitleFormat::ALL.map(|format| (format.extension(), format));
+    let collision_policies = [
+        ("overwrite", CollisionPolicy::Overwrite),
+        ("skip", CollisionPolicy::SkipIfSame),
+        ("rename", CollisionPolicy::Rename),
+    ];
+
+    let args = DownloadArgs {
+        url,
+        content_type: value("type")
+            .map(|value| parse_choice("type", &value, &content_types))
+            .transpose()?,
+        output_directory: value("output").map(PathBuf::from),
+        format_selector: value("format"),
+        max_height: value("max-height")
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| UsageError::new(format!("invalid maximum height: {}", value)))
+            })
+            .transpose()?,
+        codec: value("codec")
+            .map(|value| parse_choice("codec", &value, &codecs))
+            .transpose()?,
+        audio_format: value("audio-format")
+            .map(|value| parse_choice("audio-format", &value, &audio_formats))
+            .transpose()?,
+        audio_quality: value("audio-quality")
+            .map(|value| parse_audio_quality(&value))
+            .transpose()?,
+        filename_template: value("template"),
+        collision_policy: value("on-collision")
+            .map(|value| parse_choice("on-collision", &value, &collision_policies))
+            .transpose()?,
+        ignore_archive,
+        start: value("start")
+            .map(|value| parse_time("start", &value))
+            .transpose()?,
+        end: value("end")
+            .map(|value| parse_time("end", &value))
+            .transpose()?,
+        accurate_clip,
+        subtitle_tracks: value("subs")
+            .map(|value| parse_subtitle_tracks(&value, false))
+            .into_iter()
+            .chain(value("auto-subs").map(|value| parse_subtitle_tracks(&value, true)))
+            .flatten()
+            .collect(),
+        subtitle_format: value("sub-format")
+            .map(|value| parse_choice("sub-format", &value, &subtitle_formats))
+            .transpose()?,
+        embed_subtitles,
+        split_chapters,
+        channel_filter: ChannelFilter {
+            uploaded_after: value("after")
+                .map(|value| {
+                    value.parse::<UploadDate>().map_err(|err| {
+                        UsageError::new(format!("invalid value for --after: {}", err))
+                    })
+                })
+                .transpose()?,
+            max_videos: value("max-videos")
+                .map(|value| {
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|max_videos| *max_videos > 0)
+                        .ok_or_else(|| {
+                            UsageError::new(format!("invalid maximum video count: {}", value))
+                        })
+                })
+                .transpose()?,
+            skip_shorts,
+        },
+    };
+
+    match options.into_keys().next() {
+        Some(name) => Err(UsageError::new(format!("unknown option: --{}", name))),
+        None => Ok(args),
+    }
+}
+
+/// The names of the commands.
+const COMMAND_NAMES: [&str; 7] = [
+    "download", "info", "formats", "subs", "deps", "config", "help",
+];
+
+/// Arguments that run the command-line interface without a command.
+const STANDALONE_OPTIONS: [&str; 3] = ["-h", "--help", "--version"];
+
+/// Checks if the command-line arguments, excluding the program name, are
+/// meant for the command-line interface. Other arguments, such as those the
+/// operating system passes when launching the application or opening a file
+/// with it, open the application window instead.
+pub fn is_command_line<S: AsRef<str>>(args: &[S]) -> bool {
+    let mut args = args
+        .iter()
+        .map(AsRef::as_ref)
+        .skip_while(|arg| *arg == "--json");
+
+    args.next()
+        .is_some_and(|arg| COMMAND_NAMES.contains(&arg) || STANDALONE_OPTIONS.contains(&arg))
+}
+
+/// Parses the command-line arguments, excluding the program name.
+pub fn parse_args<I>(args: I) -> Result<Args, UsageError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let mut positional = Vec::new();
+    let mut options = HashMap::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" {
+            options.insert("help".to_owned(), None);
+        } else if let Some(option) = arg.strip_prefix("--") {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None if FLAG_OPTIONS.contains(&option) => (option.to_owned(), None),
+                None => {
+                    let value = args.next().ok_or_else(|| {
+                        UsageError::new(format!("missing value for --{}", option))
+                    })?;
+                    (option.to_owned(), Some(value))
+                }
+            };
+            options.insert(name, value);
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    let json = options.remove("json").is_some();
+
+    if options.remove("help").is_some() {
+        return Ok(Args {
+            json,
+            command: Command::Help,
+        });
+    }
+
+    if options.remove("version").is_some() {
+        return Ok(Args {
+            json,
+            command: Command::Version,
+        });
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("download") => {
+            let url = positional
+                .next()
+                .ok_or_else(|| UsageError::new("missing URL to download"))?;
+            Command::Download(download_args(url, std::mem::take(&mut options))?)
+        }
+        Some("info") => Command::Info(
+            positional
+                .next()
+                .ok_or_else(|| UsageError::new("missing video URL"))?,
+        ),
+        Some("formats") => Command::Formats(
+            positional
+                .next()
+                .ok_or_else(|| UsageError::new("missing video URL"))?,
+        ),
+        Some("subs") => Command::Subtitles(
+            positional
+                .next()
+                .ok_or_else(|| UsageError::new("missing video URL"))?,
+        ),
+        Some("deps") => Command::Deps(match positional.next().as_deref() {
+            Some("install") => DepsCommand::Install,
+            Some("update") => DepsCommand::Update,
+            Some("status") => DepsCommand::Status,
+            Some(other) => return Err(UsageError::new(format!("unknown deps command: {}", other))),
+            None => return Err(UsageError::new("missing deps command")),
+        }),
+        Some("config") => Command::Config(match positional.next().as_deref() {
+            Some("get") => ConfigCommand::Get(positional.next()),
+            Some("set") => match (positional.next(), positional.next()) {
+                (Some(key), Some(value)) => ConfigCommand::Set(key, value),
+                _ => return Err(UsageError::new("expected a key and a value to set")),
+            },
+            Some(other) => {
+                return Err(UsageError::new(format!(
+                    "unknown config command: {}",
+                    other
+                )))
+            }
+            None => return Err(UsageError::new("missing config command")),
+        }),
+        Some("help") => Command::Help,
+        Some(other) => return Err(UsageError::new(format!("unknown command: {}", other))),
+        None => return Err(UsageError::new("missing command")),
+    };
+
+    if let Some(arg) = positional.next() {
+        return Err(UsageError::new(format!("unexpected argument: {}", arg)));
+    }
+
+    if let Some(name) = options.into_keys().next() {
+        return Err(UsageError::new(format!("unknown option: --{}", name)));
+    }
+
+    Ok(Args { json, command })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses command-line arguments given as string slices.
+    fn parse(args: &[&str]) -> Result<Args, UsageError> {
+        parse_args(args.iter().map(|arg| (*arg).to_owned()))
+    }
+
+    /// Parses command-line arguments that are expected to be invalid,
+    /// returning the error message.
+    fn parse_error(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn commands() {
+        let cases = [
+            (&["info", "url"][..], Command::Info("url".to_owned())),
+            (&["formats", "url"], Command::Formats("url".to_owned())),
+            (&["subs", "url"], Command::Subtitles("url".to_owned())),
+            (&["deps", "install"], Command::Deps(DepsCommand::Install)),
+            (&["deps", "update"], Command::Deps(DepsCommand::Update)),
+            (&["deps", "status"], Command::Deps(DepsCommand::Status)),
+            (
+                &["config", "get"],
+                Command::Config(ConfigCommand::Get(None)),
+            ),
+            (
+                &["config", "get", "key"],
+                Command::Config(ConfigCommand::Get(Some("key".to_owned()))),
+            ),
+            (
+                &["config", "set", "key", "value"],
+                Command::Config(ConfigCommand::Set("key".to_owned(), "value".to_owned())),
+            ),
+            (&["help"], Command::Help),
+            (&["-h"], Command::Help),
+            (&["--help"], Command::Help),
+            (&["download", "url", "-h"], Command::Help),
+            (&["--version"], Command::Version),
+            (
+                &["download", "url"],
+                Command::Download(DownloadArgs {
+                    url: "url".to_owned(),
+                    ..DownloadArgs::default()
+                }),
+            ),
+        ];
+
+        for (args, command) in cases {
+            let expected = Args {
+                json: false,
+                command,
+            };
+            assert_eq!(parse(args).unwrap(), expected, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn json_placement() {
+        let cases = [
+            &["--json", "info", "url"][..],
+            &["info", "--json", "url"],
+            &["info", "url", "--json"],
+        ];
+
+        for args in cases {
+            let expected = Args {
+                json: true,
+                command: Command::Info("url".to_owned()),
+            };
+            assert_eq!(parse(args).unwrap(), expected, "{:?}", args);
+        }
+
+        assert!(parse(&["--json", "--version"]).unwrap().json);
+        assert_eq!(parse_error(&["--json"]), "missing command");
+    }
+
+    #[test]
+    fn download_options() {
+        let args = parse(&[
+            "download",
+            "url",
+            "--type",
+            "Audio",
+            "--output=downloads",
+            "--max-height",
+            "720",
+            "--codec",
+            "vp9",
+            "--audio-format",
+            "opus",
+            "--audio-quality",
+            "V2",
+            "--on-collision",
+            "rename",
+            "--start",
+            "1:30",
+            "--end=2m",
+            "--subs",
+            "en, fr",
+            "--auto-subs",
+            "de",
+            "--ignore-archive",
+            "--embed-subs",
+            "--split-chapters",
+            "--after",
+            "2024-01-31",
+            "--max-videos",
+            "10",
+            "--skip-shorts",
+        ])
+        .unwrap();
+        let expected = DownloadArgs {
+            url: "url".to_owned(),
+            content_type: Some(ContentType::Audio),
+            output_directory: Some(PathBuf::from("downloads")),
+            max_height: Some(720),
+            codec: Some(VideoCodec::Vp9),
+            audio_format: Some(AudioFormat::Opus),
+            audio_quality: Some(AudioQuality::Vbr(2)),
+            collision_policy: Some(CollisionPolicy::Rename),
+            ignore_archive: true,
+            start: Some(Duration::from_secs(90)),
+            end: Some(Duration::from_secs(120)),
+            subtitle_tracks: vec![
+                SubtitleTrack {
+                    language: "en".to_owned(),
+                    automatic: false,
+                },
+                SubtitleTrack {
+                    language: "fr".to_owned(),
+                    automatic: false,
+                },
+                SubtitleTrack {
+                    language: "de".to_owned(),
+                    automatic: true,
+                },
+            ],
+            embed_subtitles: true,
+            split_chapters: true,
+            channel_filter: ChannelFilter {
+                uploaded_after: UploadDate::new(2024, 1, 31),
+                max_videos: Some(10),
+                skip_shorts: true,
+            },
+            ..DownloadArgs::default()
+        };
+
+        assert_eq!(args.command, Command::Download(expected));
+    }
+
+    #[test]
+    fn invalid_args() {
+        let cases = [
+            (&[][..], "missing command"),
+            (&["fetch", "url"], "unknown command: fetch"),
+            (&["download"], "missing URL to download"),
+            (&["info"], "missing video URL"),
+            (&["info", "url", "other"], "unexpected argument: other"),
+            (
+                &["info", "url", "--type", "video"],
+                "unknown option: --type",
+            ),
+            (
+                &["download", "url", "--bogus", "value"],
+                "unknown option: --bogus",
+            ),
+            (
+                &["download", "url", "--bogus=value"],
+                "unknown option: --bogus",
+            ),
+            (&["download", "url", "--type"], "missing value for --type"),
+            (
+                &["download", "url", "--codec", "h265"],
+                "invalid value for --codec: h265 (expected one of any, av1, vp9, h264)",
+            ),
+            (
+                &["download", "url", "--max-height", "tall"],
+                "invalid maximum height: tall",
+            ),
+            (
+                &["download", "url", "--audio-quality", "loud"],
+                "invalid audio quality: loud",
+            ),
+            (
+                &["download", "url", "--start", "soon"],
+                "invalid value for --start: soon",
+            ),
+            (
+                &["download", "url", "--after", "31/01/2024"],
+                "invalid value for --after: expected a date in the form YYYY-MM-DD",
+            ),
+            (
+                &["download", "url", "--max-videos", "0"],
+                "invalid maximum video count: 0",
+            ),
+            (&["deps"], "missing deps command"),
+            (&["deps", "remove"], "unknown deps command: remove"),
+            (
+                &["config", "set", "key"],
+                "expected a key and a value to set",
+            ),
+            (&["config", "reset"], "unknown config command: reset"),
+        ];
+
+        for (args, message) in cases {
+            assert_eq!(parse_error(args), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn command_lines() {
+        let cases = [
+            (&[][..], false),
+            (&["download", "url"], true),
+            (&["info"], true),
+            (&["help"], true),
+            (&["-h"], true),
+            (&["--help"], true),
+            (&["--version"], true),
+            (&["--json", "info", "url"], true),
+            (&["--json", "--version"], true),
+            (&["--json"], false),
+            (&["video.mp4"], false),
+            (&["-psn_0_12345"], false),
+            (&["--flag", "download"], false),
+        ];
+
+        for (args, expected) in cases {
+            assert_eq!(is_command_line(args), expected, "{:?}", args);
+        }
+    }
+}
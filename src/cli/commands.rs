@@ -0,0 +1,796 @@
+//! Commands of the headless command-line interface.
+
+use crate::cli::{parse_args, Command, ConfigCommand, DepsCommand, DownloadArgs, USAGE};
+use crate::services::*;
+use crate::types::*;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::io::{stderr, IsTerminal, Write};
+use std::time::Duration;
+use tokio::sync::mpsc::unbounded_channel;
+
+/// The exit status of the command-line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitStatus {
+    /// The command succeeded.
+    Success = 0,
+    /// The command failed for a reason not covered by another status.
+    Failure = 1,
+    /// The command-line arguments were invalid.
+    Usage = 2,
+    /// The video cannot be downloaded, for example because it is private or
+    /// has been removed.
+    Unavailable = 3,
+    /// YouTube or GitHub could not be reached.
+    Network = 4,
+    /// A dependency binary is missing or failed.
+    Dependency = 5,
+    /// There is not enough disk space to save the download.
+    DiskFull = 6,
+    /// The output file already exists, and the collision policy is to ask.
+    Conflict = 7,
+    /// The command was interrupted with Ctrl+C.
+    Interrupted = 130,
+}
+
+impl ExitStatus {
+    /// Returns the exit status for a failure of the given kind.
+    const fn from_kind(kind: DownloadErrorKind) -> Self {
+        match kind {
+            DownloadErrorKind::VideoUnavailable
+            | DownloadErrorKind::Private
+            | DownloadErrorKind::AgeRestricted
+            | DownloadErrorKind::GeoBlocked
+            | DownloadErrorKind::MembersOnly
+            | DownloadErrorKind::LiveNotStarted => Self::Unavailable,
+            DownloadErrorKind::Network => Self::Network,
+            DownloadErrorKind::Ffmpeg | DownloadErrorKind::MissingBinary => Self::Dependency,
+            DownloadErrorKind::DiskFull => Self::DiskFull,
+            DownloadErrorKind::Unknown => Self::Failure,
+        }
+    }
+}
+
+/// A failed command, along with the exit status it produces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CommandError {
+    /// The exit status.
+    status: ExitStatus,
+    /// The failure to report, or `None` if it has already been reported.
+    failure: Option<DownloadFailure>,
+}
+
+impl CommandError {
+    /// Creates an error with a message and no further details.
+    fn new(status: ExitStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            failure: Some(DownloadFailure::unknown(message)),
+        }
+    }
+
+    /// Creates an error whose cause has already been reported.
+    fn reported(status: ExitStatus) -> Self {
+        Self {
+            status,
+            failure: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for CommandError {
+    fn from(err: anyhow::Error) -> Self {
+        let failure = DownloadFailure::from_error(&err);
+        let status = if err.is::<Cancelled>() {
+            ExitStatus::Interrupted
+        } else if err.is::<FileCollision>() {
+            ExitStatus::Conflict
+        } else {
+            ExitStatus::from_kind(failure.kind)
+        };
+
+        Self {
+            status,
+            failure: Some(failure),
+        }
+    }
+}
+
+/// The result of a command.
+type CommandResult = Result<(), CommandError>;
+
+/// Prints a JSON value to `stdout`.
+fn print_json(value: &Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+    );
+}
+
+/// Reports a failure, on `stdout` as JSON or on `stderr` otherwise.
+fn report_failure(failure: &DownloadFailure, json: bool) {
+    if json {
+        print_json(&json!({ "error": failure }));
+        return;
+    }
+
+    match failure.kind {
+        DownloadErrorKind::Unknown => eprintln!("error: {}", failure.description),
+        DownloadErrorKind::MissingBinary => {
+            eprintln!("error: {}", failure.description);
+            eprintln!("Run `ytdl deps install` to install the dependencies.");
+        }
+        kind => {
+            eprintln!("error: {}", kind.message());
+            eprintln!("{}", failure.description);
+        }
+    }
+}
+
+/// Prints download progress updates to `stderr`. On a terminal the status
+/// line is redrawn in place, while otherwise a line is printed for each
+/// phase and pending retry, to keep logs readable.
+struct ProgressPrinter {
+    /// Whether `stderr` is a terminal.
+    terminal: bool,
+    /// The most recently printed status line, if it has not been finished.
+    last: Option<String>,
+}
+
+impl ProgressPrinter {
+    /// Creates a new progress printer.
+    fn new() -> Self {
+        Self {
+            terminal: stderr().is_terminal(),
+            last: None,
+        }
+    }
+
+    /// Prints a progress update.
+    fn update(&mut self, progress: &DownloadProgress) {
+        let text = progress.to_string();
+
+        if self.terminal {
+            eprint!("\r\x1b[2K{}", text);
+            let _ = stderr().flush();
+        } else {
+            let summary = match progress.retry {
+                Some(_) => text.clone(),
+                None => format!("{}...", progress.phase.phase_name()),
+            };
+
+            if self.last.as_deref() != Some(&summary) {
+                eprintln!("{}", summary);
+            }
+
+            self.last = Some(summary);
+            return;
+        }
+
+        self.last = Some(text);
+    }
+
+    /// Finishes the status line, so that further output begins on a line of
+    /// its own.
+    fn finish(&mut self) {
+        if self.terminal && self.last.take().is_some() {
+            eprint!("\r\x1b[2K");
+            let _ = stderr().flush();
+        }
+
+        self.last = None;
+    }
+}
+
+/// Runs a command, cancelling it through `cancel` if Ctrl+C is pressed.
+async fn interruptible<F>(cancel: &CancelHandle, command: F) -> CommandResult
+where
+    F: Future<Output = CommandResult>,
+{
+    let interrupt = async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+
+        std::future::pending::<()>().await;
+    };
+
+    tokio::select! {
+        res = command => res,
+        () = interrupt => unreachable!(),
+    }
+}
+
+/// Returns the ID of the video a URL refers to, along with the time the URL
+/// starts playback at, or a usage error if it refers to no video.
+fn video_arg(url: &str) -> Result<(String, Option<Duration>), CommandError> {
+    match parse_youtube_url(url) {
+        Some(YoutubeRef::Video { id, start_time, .. }) => Ok((id, start_time)),
+        _ => Err(CommandError::new(
+            ExitStatus::Usage,
+            "invalid YouTube video URL",
+        )),
+    }
+}
+
+/// Builds the download request template for the `download` command, taking
+/// options that were not given from the configuration.
+fn download_request(args: &DownloadArgs, config: &Config) -> Result<DownloadRequest, CommandError> {
+    let output_directory = args
+        .output_directory
+        .clone()
+        .or_else(|| config.output_directory.clone())
+        .ok_or_else(|| CommandError::new(ExitStatus::Usage, "no output directory given"))?;
+    let filename_template = args
+        .filename_template
+        .clone()
+        .unwrap_or_else(|| config.filename_template.clone());
+
+    if let Err(err) = validate_template(&filename_template) {
+        return Err(CommandError::new(ExitStatus::Usage, err.to_string()));
+    }
+
+    let mut format_preference = config.format_preference;
+    format_preference.max_height = args.max_height.or(format_preference.max_height);
+    format_preference.codec = args.codec.unwrap_or(format_preference.codec);
+
+    let clip = ClipRange {
+        start: args.start,
+        end: args.end,
+        mode: if args.accurate_clip {
+            ClipMode::Accurate
+        } else {
+            ClipMode::Fast
+        },
+    };
+
+    if let Err(err) = validate_clip_range(&clip) {
+        return Err(CommandError::new(ExitStatus::Usage, err.to_string()));
+    }
+
+    let mut audio_preference = config.audio_preference;
+    audio_preference.format = args.audio_format.unwrap_or(audio_preference.format);
+    audio_preference.quality = args.audio_quality.unwrap_or(audio_preference.quality);
+
+    let content_type = args.content_type.unwrap_or(config.content_type);
+
+    if content_type == ContentType::Subtitles && args.subtitle_tracks.is_empty() {
+        return Err(CommandError::new(
+            ExitStatus::Usage,
+            "no subtitle languages given (use --subs or --auto-subs)",
+        ));
+    }
+
+    let mut subtitle_preference = config.subtitle_preference;
+    subtitle_preference.format = args.subtitle_format.unwrap_or(subtitle_preference.format);
+    subtitle_preference.embed |= args.embed_subtitles;
+
+    Ok(DownloadRequest {
+        format_preference,
+        format_selector: args.format_selector.clone(),
+        audio_preference,
+        filename_template,
+        collision_policy: args.collision_policy.unwrap_or(config.collision_policy),
+        ignore_archive: args.ignore_archive,
+        retry_policy: config.retry_policy,
+        clip,
+        subtitle_tracks: args.subtitle_tracks.clone(),
+        subtitle_preference,
+        split_chapters: args.split_chapters,
+        extractor: config.extractor,
+        ..DownloadRequest::new(String::new(), content_type, output_directory)
+    })
+}
+
+/// Resolves the URL given to the `download` command into the requests to
+/// perform. The uploads of a channel are filtered with `channel_filter`.
+async fn download_requests(
+    url: &str,
+    template: &DownloadRequest,
+    channel_filter: &ChannelFilter,
+) -> Result<Vec<DownloadRequest>, CommandError> {
+    let (playlist, indexed) = match parse_youtube_url(url) {
+        Some(YoutubeRef::Video {
+            id,
+            start_time,
+            end_time,
+            ..
+        }) => {
+            let clip = ClipRange {
+                start: template.clip.start.or(start_time),
+                end: template.clip.end.or(end_time),
+                ..template.clip
+            };
+
+            if let Err(err) = validate_clip_range(&clip) {
+                return Err(CommandError::new(ExitStatus::Usage, err.to_string()));
+            }
+
+            return Ok(vec![DownloadRequest {
+                video_id: id,
+                clip,
+                ..template.clone()
+            }]);
+        }
+        Some(YoutubeRef::Playlist(playlist_id)) => (
+            extractor(template.extractor)
+                .fetch_playlist(&playlist_id)
+                .await?,
+            true,
+        ),
+        Some(YoutubeRef::Channel(channel)) => (
+            extractor(template.extractor)
+                .fetch_channel(&channel, channel_filter)
+                .await?,
+            false,
+        ),
+        None => return Err(CommandError::new(ExitStatus::Usage, "invalid YouTube URL")),
+    };
+
+    Ok(playlist
+        .entries
+        .into_iter()
+        .map(|entry| DownloadRequest {
+            video_id: entry.video_id,
+            playlist_index: indexed.then_some(entry.index),
+            ..template.clone()
+        })
+        .collect())
+}
+
+/// Describes the outcome of a download in a human-readable way.
+fn outcome_text(outcome: &DownloadOutcome) -> String {
+    if outcome.archived {
+        return format!(
+            "Already downloaded to {} (in download archive)",
+            outcome.path.display()
+        );
+    }
+
+    let mut text = format!("Saved to {}", outcome.path.display());
+
+    if let Some(strategy) = outcome.audio_strategy {
+        text.push_str(&format!(" ({})", strategy));
+    }
+
+    if let Some(collision) = outcome.collision {
+        text.push_str(&format!(" ({})", collision.decision_name()));
+    }
+
+    for path in outcome.chapter_paths.iter().chain(&outcome.subtitle_paths) {
+        text.push_str(&format!("\nSaved to {}", path.display()));
+    }
+
+    for warning in &outcome.warnings {
+        text.push_str(&format!("\nwarning: {}", warning));
+    }
+
+    text
+}
+
+/// Describes the outcome of a download as JSON.
+fn outcome_json(outcome: &DownloadOutcome) -> Value {
+    json!({
+        "path": outcome.path,
+        "archived": outcome.archived,
+        "audio_strategy": outcome.audio_strategy.map(|strategy| strategy.to_string()),
+        "collision": outcome.collision.map(|collision| collision.decision_name()),
+        "chapter_paths": outcome.chapter_paths,
+        "subtitle_paths": outcome.subtitle_paths,
+        "warnings": outcome.warnings,
+    })
+}
+
+/// Downloads a video, playlist or channel. Videos are downloaded one at a
+/// time, continuing past failures, and the command fails with the status of
+/// the first failure.
+async fn download_command(args: DownloadArgs, json: bool, cancel: &CancelHandle) -> CommandResult {
+    let config = Config::load().await?;
+    let template = download_request(&args, &config)?;
+    let requests = download_requests(&args.url, &template, &args.channel_filter).await?;
+    let archive = DownloadArchive::load().await?;
+    let mut results = Vec::new();
+    let mut first_status = None;
+
+    for (position, request) in requests.iter().enumerate() {
+        if !json {
+            eprintln!("[{}/{}] {}", position + 1, requests.len(), request.video_id);
+        }
+
+        let (progress_tx, mut progress_rx) = unbounded_channel();
+        let mut printer = ProgressPrinter::new();
+        let download_task = async {
+            let progress = progress_tx;
+            download(request, &archive, &progress, cancel).await
+        };
+        let progress_task = async {
+            while let Some(update) = progress_rx.recv().await {
+                if !json {
+                    printer.update(&update);
+                }
+            }
+        };
+        let (res, ()) = tokio::join!(download_task, progress_task);
+        printer.finish();
+
+        match res {
+            Ok(outcome) => {
+                if json {
+                    results.push(json!({
+                        "video_id": request.video_id,
+                        "outcome": outcome_json(&outcome),
+                    }));
+                } else {
+                    println!("{}", outcome_text(&outcome));
+                }
+            }
+            Err(err) => {
+                let err = CommandError::from(err);
+
+                if let Some(failure) = &err.failure {
+                    if json {
+                        results.push(json!({
+                            "video_id": request.video_id,
+                            "error": failure,
+                        }));
+                    } else {
+                        report_failure(failure, false);
+                    }
+                }
+
+                first_status.get_or_insert(err.status);
+
+                if err.status == ExitStatus::Interrupted {
+                    break;
+                }
+            }
+        }
+    }
+
+    if json {
+        print_json(&Value::Array(results));
+    }
+
+    // Failures have been reported alongside each download.
+    match first_status {
+        Some(status) => Err(CommandError::reported(status)),
+        None => Ok(()),
+    }
+}
+
+/// Shows information about a video.
+async fn info_command(url: &str, json: bool, cancel: &CancelHandle) -> CommandResult {
+    let (video_id, start_time) = video_arg(url)?;
+    let config = Config::load().await?;
+    let mut printer = ProgressPrinter::new();
+    let info = retry(
+        &config.retry_policy,
+        Some(cancel),
+        |status| {
+            if !json {
+                printer.update(&DownloadProgress {
+                    retry: Some(status),
+                    ..DownloadProgress::new(DownloadPhase::Preparing)
+                });
+            }
+        },
+        || extractor(config.extractor).fetch_video_info(&video_id, cancel),
+    )
+    .await;
+    printer.finish();
+    let info = info?;
+
+    if json {
+        print_json(&json!(info));
+    } else {
+        println!("Title: {}", info.title);
+        println!("ID: {}", info.id);
+
+        if let Some(artist) = info.artist() {
+            println!("Uploader: {}", artist);
+        }
+
+        if let Some(date) = info.formatted_upload_date() {
+            println!("Uploaded: {}", date);
+        }
+
+        println!("URL: {}", info.source_url());
+
+        if let Some(start_time) = start_time {
+            println!("Starts at: {}", format_timestamp(start_time));
+        }
+
+        let chapters = info.chapters.as_deref().unwrap_or_default();
+
+        if !chapters.is_empty() {
+            println!("Chapters:");
+
+            for (i, chapter) in chapters.iter().enumerate() {
+                println!(
+                    "  {:>2}. {} {}",
+                    i + 1,
+                    format_timestamp(chapter.start_time),
+                    chapter.display_title(i + 1)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the subtitle tracks available for a video.
+async fn subtitles_command(url: &str, json: bool) -> CommandResult {
+    let (video_id, _) = video_arg(url)?;
+    let config = Config::load().await?;
+    let subtitles = extractor(config.extractor)
+        .fetch_subtitles(&video_id)
+        .await?;
+
+    if json {
+        print_json(&json!(subtitles));
+    } else if subtitles.is_empty() {
+        println!("The video has no subtitles.");
+    } else {
+        let label_width = subtitles
+            .iter()
+            .map(|subtitle| subtitle.track.file_label().len())
+            .max()
+            .unwrap_or_default();
+
+        for subtitle in &subtitles {
+            println!(
+                "{:<width$}  {} [{}]",
+                subtitle.track.file_label(),
+                subtitle.description(),
+                subtitle.formats.join(", "),
+                width = label_width
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the streams available for a video.
+async fn formats_command(url: &str, json: bool) -> CommandResult {
+    let (video_id, _) = video_arg(url)?;
+    let config = Config::load().await?;
+    let formats = extractor(config.extractor).fetch_formats(&video_id).await?;
+
+    if json {
+        print_json(&json!(formats));
+    } else {
+        let id_width = formats
+            .iter()
+            .map(|format| format.format_id.len())
+            .max()
+            .unwrap_or_default();
+
+        for format in &formats {
+            println!(
+                "{:<width$}  {}",
+                format.format_id,
+                format.description(),
+                width = id_width
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Manages the dependency binaries.
+async fn deps_command(command: DepsCommand, json: bool) -> CommandResult {
+    let config = Config::load().await?;
+    let (fetched, updated) = match command {
+        DepsCommand::Status => (Vec::new(), Vec::new()),
+        DepsCommand::Install => prepare_binaries(&config, false).await?,
+        DepsCommand::Update => prepare_binaries(&config, true).await?,
+    };
+
+    let mut binaries = Vec::new();
+
+    for dependency in Dependency::required(config.extractor) {
+        let resolved = resolve_binary(dependency, dependency.configured_path(&config))
+            .await
+            .map_err(|err| err.to_string());
+        binaries.push((dependency, resolved));
+    }
+
+    if json {
+        let status = binaries
+            .iter()
+            .map(|(dependency, resolved)| {
+                let candidate = resolved.as_ref().ok().and_then(Option::as_ref);
+
+                (
+                    dependency.dependency_name().to_owned(),
+                    json!({
+                        "path": candidate.map(|candidate| &candidate.path),
+                        "source": candidate.map(|candidate| candidate.source.source_name()),
+                        "version": candidate.and_then(|candidate| candidate.version.as_ref().ok()),
+                        "installed": candidate.is_some(),
+                        "fetched": fetched.contains(dependency),
+                        "updated": updated.contains(dependency),
+                        "error": resolved.as_ref().err(),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        print_json(&Value::Object(status));
+    } else {
+        for (dependency, resolved) in &binaries {
+            let name = dependency.dependency_name();
+
+            match resolved {
+                Ok(Some(candidate)) => {
+                    let status = if fetched.contains(dependency) {
+                        "installed now"
+                    } else if updated.contains(dependency) {
+                        "updated"
+                    } else {
+                        "installed"
+                    };
+                    println!("{}: {} ({})", name, status, candidate.description());
+                }
+                Ok(None) => println!("{}: missing", name),
+                Err(err) => println!("{}: unusable ({})", name, err),
+            }
+        }
+    }
+
+    if binaries
+        .iter()
+        .all(|(_, resolved)| matches!(resolved, Ok(Some(_))))
+    {
+        Ok(())
+    } else {
+        // The status has been printed along with each binary's path.
+        if !json {
+            eprintln!(
+                "Run `ytdl deps install` to install the missing dependencies, or change the configured binary paths."
+            );
+        }
+
+        Err(CommandError::reported(ExitStatus::Dependency))
+    }
+}
+
+/// Formats a configuration value for human-readable output. Strings are
+/// printed without quotes.
+fn config_value_text(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Views or changes the configuration.
+async fn config_command(command: ConfigCommand, json: bool) -> CommandResult {
+    let mut config = Config::load().await?;
+
+    let (key, value) = match command {
+        ConfigCommand::Get(None) => {
+            let fields = config.to_json()?;
+
+            if json {
+                print_json(&Value::Object(fields));
+            } else {
+                for (key, value) in &fields {
+                    println!("{} = {}", key, config_value_text(value));
+                }
+            }
+
+            return Ok(());
+        }
+        ConfigCommand::Get(Some(key)) => {
+            let value = config.to_json()?.remove(&key).ok_or_else(|| {
+                CommandError::new(
+                    ExitStatus::Usage,
+                    format!("unknown configuration key: {}", key),
+                )
+            })?;
+            (key, value)
+        }
+        ConfigCommand::Set(key, value) => {
+            // Values that are not valid JSON, such as paths, are taken as
+            // strings.
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            config.set_json_field(&key, value).map_err(|err| {
+                CommandError::new(ExitStatus::Usage, format!("invalid value: {}", err))
+            })?;
+            config.save().await?;
+            let value = config.to_json()?.remove(&key).unwrap_or_default();
+            (key, value)
+        }
+    };
+
+    if json {
+        print_json(&json!({ key: value }));
+    } else {
+        println!("{}", config_value_text(&value));
+    }
+
+    Ok(())
+}
+
+/// Runs a parsed command.
+async fn run_command(command: Command, json: bool) -> CommandResult {
+    let cancel = CancelHandle::new();
+
+    interruptible(&cancel, async {
+        // Commands that run the dependency binaries run those that are
+        // configured or found, without fetching any.
+        if !matches!(
+            command,
+            Command::Deps(_) | Command::Config(_) | Command::Help | Command::Version
+        ) {
+            resolve_binaries(&Config::load().await?).await?;
+        }
+
+        match command {
+            Command::Download(args) => download_command(args, json, &cancel).await,
+            Command::Info(url) => info_command(&url, json, &cancel).await,
+            Command::Formats(url) => formats_command(&url, json).await,
+            Command::Subtitles(url) => subtitles_command(&url, json).await,
+            Command::Deps(command) => deps_command(command, json).await,
+            Command::Config(command) => config_command(command, json).await,
+            Command::Help => {
+                println!("{}", USAGE);
+                Ok(())
+            }
+            Command::Version => {
+                let version = env!("CARGO_PKG_VERSION");
+
+                if json {
+                    print_json(&json!({ "version": version }));
+                } else {
+                    println!("ytdl {}", version);
+                }
+
+                Ok(())
+            }
+        }
+    })
+    .await
+}
+
+/// Runs the command-line interface with the given arguments, excluding the
+/// program name, and returns the process exit code. No window is created.
+pub fn run<I>(args: I) -> i32
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitStatus::Usage as i32;
+        }
+    };
+
+    // Services hold their state in `Rc`s, so commands run on a
+    // single-threaded runtime.
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitStatus::Failure as i32;
+        }
+    };
+
+    match runtime.block_on(run_command(args.command, args.json)) {
+        Ok(()) => ExitStatus::Success as i32,
+        Err(err) => {
+            if let Some(failure) = &err.failure {
+                report_failure(failure, args.json);
+            }
+
+            err.status as i32
+        }
+    }
+}
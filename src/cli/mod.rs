@@ -0,0 +1,28 @@
+//! Headless command-line interface, used when the application is run with a
+//! command. Commands reuse the application services without creating a
+//! window.
+//!
+//! Release builds on Windows use the GUI subsystem, so they start without a
+//! console. Commands attach to the console of the shell they were run from
+//! so that their output is shown there.
+
+mod args;
+mod commands;
+
+pub use args::*;
+pub use commands::*;
+
+/// Attaches the process to the console of the process that started it, such
+/// as a shell, so that the output of commands is shown in it. Nothing happens
+/// if the process already has a console or its parent has none.
+#[cfg(target_os = "windows")]
+#[allow(unsafe_code)]
+pub fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: `AttachConsole` takes no pointers, and only reports failure
+    // through its return value, which is safe to ignore.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
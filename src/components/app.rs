@@ -15,7 +15,7 @@ enum DepFetchStatus {
     /// Currently fetching dependencies.
     Pending,
     /// Fetching dependencies completed successfully.
-    Completed(Config),
+    Completed(Box<Config>, DownloadQueue, DownloadArchive),
     /// Fetching dependencies failed with a provided error.
     Failed(Rc<Error>),
 }
@@ -27,22 +27,25 @@ pub fn App() -> Element {
 
     use_future(move || async move {
         let res = async move {
-            if !ffmpeg_binary_exists()? {
-                fetch_ffmpeg_binary().await?;
-            }
-
-            if !youtube_dl_binary_exists()? {
-                fetch_youtube_dl_binary().await?;
-            } else {
-                update_youtube_dl_binary().await?;
-            }
+            let config = Config::load().await?;
+            prepare_binaries(&config, true).await?;
 
-            Config::load().await
+            let archive = DownloadArchive::load().await?;
+            let queue =
+                DownloadQueue::load(config.max_concurrent_downloads, archive.clone()).await?;
+            Ok::<_, Error>((config, queue, archive))
         }
         .await;
 
         match res {
-            Ok(config) => dep_fetch_status.set(DepFetchStatus::Completed(config)),
+            Ok((config, queue, archive)) => {
+                dep_fetch_status.set(DepFetchStatus::Completed(
+                    Box::new(config),
+                    queue.clone(),
+                    archive,
+                ));
+                queue.run().await;
+            }
             Err(err) => dep_fetch_status.set(DepFetchStatus::Failed(Rc::new(err))),
         }
     });
@@ -62,9 +65,11 @@ pub fn App() -> Element {
                         text: "Installing/updating application dependencies..."
                     }
                 },
-                DepFetchStatus::Completed(config) => rsx! {
+                DepFetchStatus::Completed(config, queue, archive) => rsx! {
                     Downloader {
-                        config: config,
+                        config: *config,
+                        queue: queue,
+                        archive: archive,
                     }
                 },
                 DepFetchStatus::Failed(err) => rsx! {
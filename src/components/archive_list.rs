@@ -0,0 +1,118 @@
+//! UI component listing the entries in the download archive.
+
+use crate::components::{Button, ButtonStyle};
+use crate::services::{ArchiveEntry, DownloadArchive};
+use dioxus::prelude::*;
+
+/// A single entry in the download archive.
+#[component]
+fn ArchiveListItem(
+    /// The download archive.
+    archive: DownloadArchive,
+    /// The entry to display.
+    entry: ArchiveEntry,
+) -> Element {
+    let key = entry.key.clone();
+
+    rsx! {
+        div {
+            class: "archive-list-item",
+
+            div {
+                class: "archive-list-item-info",
+
+                span {
+                    class: "archive-list-item-title",
+                    "{entry.title}"
+                }
+
+                span {
+                    class: "archive-list-item-details",
+                    "{entry.key.content_type.type_name()} ({entry.key.format}) - {entry.path.display()}"
+                }
+            }
+
+            Button {
+                text: "Remove",
+                style: ButtonStyle::Secondary,
+                onclick: move |_| {
+                    let archive = archive.clone();
+                    let key = key.clone();
+                    spawn(async move {
+                        let _ = archive.remove(&key).await;
+                    });
+                },
+            }
+        }
+    }
+}
+
+/// A collapsible list of the entries in the download archive.
+#[component]
+pub fn ArchiveList(
+    /// The download archive.
+    archive: DownloadArchive,
+) -> Element {
+    let mut entries = use_signal(|| archive.entries());
+    let mut expanded = use_signal(|| false);
+
+    let subscribe_archive = archive.clone();
+    use_future(move || {
+        let archive = subscribe_archive.clone();
+        async move {
+            let mut receiver = archive.subscribe();
+            receiver.mark_changed();
+
+            while receiver.changed().await.is_ok() {
+                let updated_entries = receiver.borrow_and_update().clone();
+                entries.set(updated_entries);
+            }
+        }
+    });
+
+    let count = entries().len();
+    let toggle_text = if expanded() { "Hide" } else { "Show" };
+
+    rsx! {
+        div {
+            class: "archive-list-container",
+
+            div {
+                class: "archive-list-header",
+
+                span {
+                    class: "archive-list-label",
+                    "Download archive ({count})"
+                }
+
+                Button {
+                    text: toggle_text,
+                    style: ButtonStyle::Secondary,
+                    onclick: move |_| expanded.set(!expanded()),
+                }
+            }
+
+            if expanded() {
+                div {
+                    class: "archive-list",
+
+                    if count == 0 {
+                        span {
+                            class: "archive-list-empty",
+                            "No downloads have been archived"
+                        }
+                    }
+
+                    // Newest entries are shown first.
+                    for entry in entries().into_iter().rev() {
+                        ArchiveListItem {
+                            key: "{entry.key.video_id}-{entry.key.content_type.type_name()}-{entry.key.format}",
+                            archive: archive.clone(),
+                            entry: entry,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! UI component for choosing the format and quality of downloaded audio.
+
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// Encodes an audio quality as the value of a `select` option.
+fn quality_value(quality: AudioQuality) -> String {
+    match quality {
+        AudioQuality::Bitrate(bitrate) => format!("b{}", bitrate),
+        AudioQuality::Vbr(level) => format!("v{}", level),
+    }
+}
+
+/// Decodes an audio quality from the value of a `select` option.
+fn parse_quality_value(value: &str) -> Option<AudioQuality> {
+    if let Some(bitrate) = value.strip_prefix('b') {
+        bitrate.parse().ok().map(AudioQuality::Bitrate)
+    } else if let Some(level) = value.strip_prefix('v') {
+        level.parse().ok().map(AudioQuality::Vbr)
+    } else {
+        None
+    }
+}
+
+/// Returns the qualities that can be selected for a format.
+fn quality_options(format: AudioFormat) -> Vec<AudioQuality> {
+    let mut options = AudioFormat::BITRATES
+        .into_iter()
+        .map(AudioQuality::Bitrate)
+        .collect::<Vec<_>>();
+
+    if let Some((min, max)) = format.vbr_levels() {
+        options.extend((min..=max).map(AudioQuality::Vbr));
+    }
+
+    options
+}
+
+/// A component to select the audio output format and quality.
+#[component]
+pub fn AudioFormatSelector(
+    /// The audio preference state.
+    state: Signal<AudioPreference>,
+) -> Element {
+    let format = state().format;
+    let quality = state().quality;
+    let format_value = format.extension();
+    let quality_value_str = quality_value(quality);
+
+    rsx! {
+        div {
+            class: "format-selector-container",
+
+            span {
+                class: "format-selector-label",
+                "Audio format"
+            }
+
+            div {
+                class: "format-selector-preferences",
+
+                select {
+                    class: "format-selector-select",
+                    value: "{format_value}",
+                    onchange: move |event| {
+                        let Some(format) = AudioFormat::ALL
+                            .into_iter()
+                            .find(|format| format.extension() == event.value())
+                        else {
+                            return;
+                        };
+                        let mut preference = state.write();
+                        preference.format = format;
+
+                        if !quality_options(format).contains(&preference.quality) {
+                            preference.quality = AudioQuality::default();
+                        }
+                    },
+
+                    for option_format in AudioFormat::ALL {
+                        option {
+                            key: "{option_format.extension()}",
+                            value: option_format.extension(),
+                            selected: option_format == format,
+                            {option_format.format_name()}
+                        }
+                    }
+                }
+
+                if !format.is_lossless() {
+                    select {
+                        class: "format-selector-select",
+                        value: "{quality_value_str}",
+                        onchange: move |event| {
+                            if let Some(quality) = parse_quality_value(&event.value()) {
+                                state.write().quality = quality;
+                            }
+                        },
+
+                        for option_quality in quality_options(format) {
+                            option {
+                                key: "{quality_value(option_quality)}",
+                                value: quality_value(option_quality),
+                                selected: option_quality == quality,
+                                {option_quality.quality_name(format)}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! UI component for entering many video URLs at once.
+
+use crate::components::ControlError;
+use crate::hooks::*;
+use crate::services::BatchImport;
+use dioxus::prelude::*;
+
+/// A multi-line input for video URLs and IDs, which can also be filled from
+/// text or CSV files, with a summary of the videos found and the lines that
+/// were rejected.
+#[component]
+pub fn BatchUrlInput(
+    /// The batch input text state.
+    state: Signal<String>,
+    /// The videos extracted from the text.
+    import: BatchImport,
+) -> Element {
+    let id = use_id();
+    let file_id = use_id();
+    let found = import.video_ids.len();
+    let summary = match found {
+        1 => "1 video found".to_owned(),
+        found => format!("{} videos found", found),
+    };
+    let error = (found == 0 && !state().trim().is_empty()).then(|| "No videos found".to_owned());
+
+    rsx! {
+        div {
+            class: "batch-url-input-container",
+
+            div {
+                class: "batch-url-input-header",
+
+                label {
+                    class: "batch-url-input-label",
+                    r#for: "{id}",
+                    "Video URLs or IDs"
+                }
+
+                label {
+                    class: "batch-url-input-import primary",
+                    r#for: "{file_id}",
+                    "Import file"
+                }
+
+                input {
+                    id: "{file_id}",
+                    class: "batch-url-input-file",
+                    r#type: "file",
+                    accept: ".txt,.csv",
+                    multiple: true,
+                    onchange: move |event| {
+                        if let Some(file_engine) = event.files() {
+                            spawn(async move {
+                                for path in file_engine.files() {
+                                    if let Some(contents) = file_engine.read_file_to_string(&path).await {
+                                        let mut text = state();
+
+                                        if !text.is_empty() && !text.ends_with('\n') {
+                                            text.push('\n');
+                                        }
+
+                                        text.push_str(&contents);
+                                        state.set(text);
+                                    }
+                                }
+                            });
+                        }
+                    }
+                }
+            }
+
+            textarea {
+                id: "{id}",
+                class: "batch-url-input",
+                rows: 8,
+                placeholder: "Paste URLs or IDs, one or more per line",
+                value: "{state}",
+                oninput: move |event| state.set(event.value()),
+            }
+
+            span {
+                class: "batch-url-input-summary",
+                "{summary}"
+            }
+
+            if !import.rejected.is_empty() {
+                div {
+                    class: "batch-url-input-rejected",
+
+                    for rejected in import.rejected {
+                        div {
+                            class: "batch-url-input-rejected-line",
+
+                            span {
+                                class: "batch-url-input-rejected-reason",
+                                "Line {rejected.line_number}: {rejected.reason.message()}"
+                            }
+
+                            span {
+                                class: "batch-url-input-rejected-text",
+                                "{rejected.text}"
+                            }
+                        }
+                    }
+                }
+            }
+
+            ControlError {
+                message: error
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! UI component for enumerating and selecting the uploads of a channel.
+
+use crate::components::{
+    Button, ButtonStyle, Loading, LoadingSpinnerSize, PlaylistChecklist, PlaylistStatus, TextInput,
+};
+use crate::services::{extractor, ChannelFilter, ChannelRef, UploadDate};
+use crate::types::*;
+use dioxus::prelude::*;
+use std::collections::BTreeSet;
+
+/// Filter controls and a checklist of the uploads of a channel.
+#[component]
+pub fn ChannelSelector(
+    /// The channel to enumerate.
+    channel: ChannelRef,
+    /// The channel loading state.
+    state: Signal<PlaylistStatus>,
+    /// The indices of the selected videos.
+    selected: Signal<BTreeSet<usize>>,
+    /// The extractor that enumerates the channel's uploads.
+    extractor_kind: ExtractorKind,
+) -> Element {
+    let uploaded_after = use_signal(String::new);
+    let max_videos = use_signal(String::new);
+    let mut skip_shorts = use_signal(|| true);
+
+    let uploaded_after_value = uploaded_after();
+    let uploaded_after_parsed = (!uploaded_after_value.trim().is_empty())
+        .then(|| uploaded_after_value.parse::<UploadDate>());
+    let uploaded_after_error = match &uploaded_after_parsed {
+        Some(Err(err)) => Some(err.to_string()),
+        _ => None,
+    };
+
+    let max_videos_value = max_videos();
+    let max_videos_parsed = (!max_videos_value.trim().is_empty()).then(|| {
+        max_videos_value
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+    });
+    let max_videos_error =
+        matches!(max_videos_parsed, Some(None)).then(|| "Expected a positive number".to_owned());
+
+    let filter = match (uploaded_after_parsed, max_videos_parsed) {
+        (Some(Err(_)), _) | (_, Some(None)) => None,
+        (uploaded_after, max_videos) => Some(ChannelFilter {
+            uploaded_after: uploaded_after.and_then(Result::ok),
+            max_videos: max_videos.flatten(),
+            skip_shorts: skip_shorts(),
+        }),
+    };
+    let loading = state() == PlaylistStatus::Loading;
+    let allow_load = filter.is_some() && !loading;
+
+    let load_channel = move |_| {
+        let channel = channel.clone();
+        let Some(filter) = filter.clone() else {
+            return;
+        };
+
+        spawn(async move {
+            state.set(PlaylistStatus::Loading);
+
+            match extractor(extractor_kind)
+                .fetch_channel(&channel, &filter)
+                .await
+            {
+                Ok(playlist) => {
+                    selected.set(playlist.entries.iter().map(|entry| entry.index).collect());
+                    state.set(PlaylistStatus::Loaded(playlist));
+                }
+                Err(err) => state.set(PlaylistStatus::Failed(err.to_string())),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "channel-selector-container",
+
+            span {
+                class: "channel-selector-label",
+                "Channel"
+            }
+
+            div {
+                class: "channel-selector-filters",
+
+                TextInput {
+                    state: uploaded_after,
+                    label: "Uploaded on or after",
+                    placeholder: "YYYY-MM-DD",
+                    disabled: loading,
+                    error: uploaded_after_error,
+                }
+
+                TextInput {
+                    state: max_videos,
+                    label: "Newest videos to include",
+                    placeholder: "All",
+                    disabled: loading,
+                    error: max_videos_error,
+                }
+            }
+
+            label {
+                class: "channel-selector-skip-shorts",
+
+                input {
+                    r#type: "checkbox",
+                    checked: skip_shorts(),
+                    disabled: loading,
+                    onchange: move |event| skip_shorts.set(event.checked()),
+                }
+
+                "Skip Shorts"
+            }
+
+            div {
+                class: "channel-selector-load",
+
+                Button {
+                    text: "Find videos",
+                    style: ButtonStyle::Primary,
+                    disabled: !allow_load,
+                    onclick: load_channel,
+                }
+            }
+
+            match state() {
+                PlaylistStatus::NotLoaded => rsx! {},
+                PlaylistStatus::Loading => rsx! {
+                    Loading {
+                        size: LoadingSpinnerSize::Small,
+                        text: "Enumerating channel uploads...",
+                    }
+                },
+                PlaylistStatus::Failed(message) => rsx! {
+                    span {
+                        class: "control-error",
+                        "Failed to enumerate channel: {message}"
+                    }
+                },
+                PlaylistStatus::Loaded(playlist) => rsx! {
+                    PlaylistChecklist {
+                        playlist: playlist,
+                        selected: selected,
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! UI component for choosing a time range of a video to keep.
+
+use crate::components::{ControlError, TextInput};
+use crate::services::parse_clip_range;
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// Start and end time inputs for clipping a download, along with the mode
+/// in which the clip is cut.
+#[component]
+pub fn ClipRangeInput(
+    /// The start time state.
+    start: Signal<String>,
+    /// The end time state.
+    end: Signal<String>,
+    /// The clip mode state.
+    mode: Signal<ClipMode>,
+) -> Element {
+    let current_mode = mode();
+    let error = parse_clip_range(&start(), &end(), current_mode)
+        .err()
+        .map(|err| err.to_string());
+
+    rsx! {
+        div {
+            class: "clip-range-input-container",
+
+            div {
+                class: "clip-range-input-times",
+
+                TextInput {
+                    state: start,
+                    label: "Start time",
+                    placeholder: "0:00",
+                }
+
+                TextInput {
+                    state: end,
+                    label: "End time",
+                    placeholder: "End of video",
+                }
+            }
+
+            div {
+                class: "clip-range-input-mode-container",
+
+                span {
+                    class: "clip-range-input-label",
+                    "Cut"
+                }
+
+                select {
+                    class: "clip-range-input-mode",
+                    onchange: move |event| {
+                        if let Some(clip_mode) = ClipMode::ALL
+                            .into_iter()
+                            .find(|clip_mode| clip_mode.mode_name() == event.value())
+                        {
+                            mode.set(clip_mode);
+                        }
+                    },
+
+                    for option_mode in ClipMode::ALL {
+                        option {
+                            key: "{option_mode.mode_name()}",
+                            value: option_mode.mode_name(),
+                            selected: option_mode == current_mode,
+                            {option_mode.mode_name()}
+                        }
+                    }
+                }
+            }
+
+            ControlError {
+                message: error
+            }
+        }
+    }
+}
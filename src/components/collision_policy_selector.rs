@@ -0,0 +1,45 @@
+//! UI component for choosing how to handle output files that already exist.
+
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// A component to select the collision policy.
+#[component]
+pub fn CollisionPolicySelector(
+    /// The collision policy state.
+    state: Signal<CollisionPolicy>,
+) -> Element {
+    let policy = state();
+
+    rsx! {
+        div {
+            class: "collision-policy-selector-container",
+
+            span {
+                class: "collision-policy-selector-label",
+                "If the file already exists"
+            }
+
+            select {
+                class: "collision-policy-selector",
+                onchange: move |event| {
+                    if let Some(policy) = CollisionPolicy::ALL
+                        .into_iter()
+                        .find(|policy| policy.policy_name() == event.value())
+                    {
+                        state.set(policy);
+                    }
+                },
+
+                for option_policy in CollisionPolicy::ALL {
+                    option {
+                        key: "{option_policy.policy_name()}",
+                        value: option_policy.policy_name(),
+                        selected: option_policy == policy,
+                        {option_policy.policy_name()}
+                    }
+                }
+            }
+        }
+    }
+}
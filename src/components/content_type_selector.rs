@@ -1,12 +1,12 @@
-//! UI component to enable selection between video, audio, and thumbnail
-//! downloading.
+//! UI component to enable selection between video, audio, thumbnail, and
+//! subtitle downloading.
 
 use crate::classes::*;
 use crate::types::*;
 use dioxus::prelude::*;
 
-/// A component to enable selection between video, audio, and thumbnail
-/// downloading.
+/// A component to enable selection between video, audio, thumbnail, and
+/// subtitle downloading.
 #[component]
 pub fn ContentTypeSelector(
     /// The content type state.
@@ -25,6 +25,11 @@ pub fn ContentTypeSelector(
         "content-type-selector-option",
         matches!(state(), ContentType::Video).then_some("content-type-selector-option-selected")
     );
+    let subtitles_option_class = classes!(
+        "content-type-selector-option",
+        matches!(state(), ContentType::Subtitles)
+            .then_some("content-type-selector-option-selected")
+    );
 
     rsx! {
         div {
@@ -55,6 +60,12 @@ pub fn ContentTypeSelector(
                     onclick: move |_| state.set(ContentType::Video),
                     "Video"
                 }
+
+                div {
+                    class: "{subtitles_option_class}",
+                    onclick: move |_| state.set(ContentType::Subtitles),
+                    "Subtitles"
+                }
             }
         }
     }
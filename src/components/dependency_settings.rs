@@ -0,0 +1,278 @@
+//! UI component for choosing the dependency binaries that are run.
+
+use crate::components::{Button, ButtonStyle, ControlError, Loading, LoadingSpinnerSize};
+use crate::services::{
+    find_binaries, prepare_binary, resolve_binary, resolved_binary_path, BinaryCandidate,
+    Dependency,
+};
+use crate::types::*;
+use dioxus::prelude::*;
+use std::path::PathBuf;
+
+/// The value of the option that leaves a binary to be found automatically.
+const AUTOMATIC_OPTION: &str = "";
+
+/// The status of finding the binaries that could be used for a dependency.
+#[derive(Debug, Clone, Default, PartialEq)]
+enum CandidatesStatus {
+    /// The binaries have not been searched for.
+    #[default]
+    NotLoaded,
+    /// The binaries are being searched for and probed.
+    Loading,
+    /// The binaries were found.
+    Loaded(Vec<BinaryCandidate>),
+    /// Searching for the binaries failed.
+    Failed(String),
+}
+
+/// A choice of the binary to run for a single dependency.
+#[component]
+fn DependencySelector(
+    /// The dependency to choose a binary for.
+    dependency: Dependency,
+    /// The configured binary path state.
+    state: Signal<Option<PathBuf>>,
+) -> Element {
+    let mut status = use_signal(CandidatesStatus::default);
+    let mut resolved = use_signal(|| resolved_binary_path(dependency));
+    let mut error = use_signal(|| None::<String>);
+
+    // Each option, as its value, its description and whether it is selected.
+    let configured = state();
+    let mut options = vec![(
+        AUTOMATIC_OPTION.to_owned(),
+        "Find automatically".to_owned(),
+        configured.is_none(),
+    )];
+
+    if let CandidatesStatus::Loaded(candidates) = status() {
+        for candidate in candidates {
+            let selected = configured.as_ref() == Some(&candidate.path);
+            options.push((
+                candidate.path.to_string_lossy().into_owned(),
+                candidate.description(),
+                selected,
+            ));
+        }
+    }
+
+    if let Some(path) = &configured {
+        let value = path.to_string_lossy().into_owned();
+
+        if !options.iter().any(|(option, _, _)| option == &value) {
+            options.push((value.clone(), value, true));
+        }
+    }
+
+    let loading = status() == CandidatesStatus::Loading;
+    let error_message = match status() {
+        CandidatesStatus::Failed(message) => Some(format!("Failed to find binaries: {}", message)),
+        _ => error(),
+    };
+    let resolved_text = match resolved() {
+        Some(path) => format!("Running {}", path.display()),
+        None => "No binary found".to_owned(),
+    };
+
+    let find = move |_| {
+        spawn(async move {
+            status.set(CandidatesStatus::Loading);
+
+            match find_binaries(dependency, state().as_deref()).await {
+                Ok(candidates) => status.set(CandidatesStatus::Loaded(candidates)),
+                Err(err) => status.set(CandidatesStatus::Failed(err.to_string())),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "dependency-selector",
+
+            div {
+                class: "dependency-selector-header",
+
+                span {
+                    class: "dependency-selector-label",
+                    {dependency.dependency_name()}
+                }
+
+                span {
+                    class: "dependency-selector-resolved",
+                    "{resolved_text}"
+                }
+
+                Button {
+                    text: "Find binaries",
+                    style: ButtonStyle::Secondary,
+                    disabled: loading,
+                    onclick: find,
+                }
+            }
+
+            select {
+                class: "dependency-selector-select",
+                disabled: loading,
+                onchange: move |event| {
+                    let value = event.value();
+                    let path = (value != AUTOMATIC_OPTION).then(|| PathBuf::from(value));
+                    state.set(path.clone());
+
+                    spawn(async move {
+                        match resolve_binary(dependency, path.as_deref()).await {
+                            Ok(candidate) => {
+                                resolved.set(candidate.map(|candidate| candidate.path));
+                                error.set(None);
+                            }
+                            Err(err) => error.set(Some(err.to_string())),
+                        }
+                    });
+                },
+
+                for (value, description, selected) in options {
+                    option {
+                        key: "{value}",
+                        value: "{value}",
+                        selected: selected,
+                        "{description}"
+                    }
+                }
+            }
+
+            ControlError {
+                message: error_message
+            }
+        }
+    }
+}
+
+/// A collapsible choice of the extractor, followed by the dependencies it
+/// needs, with a choice of the binary to run for each among those found in
+/// the application's directory and on the system's `PATH`. Choosing a binary
+/// pins it in the configuration. Choosing an extractor whose binary is not
+/// found installs it.
+#[component]
+pub fn DependencySettings(
+    /// The extractor state.
+    extractor_kind: Signal<ExtractorKind>,
+    /// The configured ffmpeg binary path state.
+    ffmpeg_path: Signal<Option<PathBuf>>,
+    /// The configured youtube-dl binary path state.
+    youtube_dl_path: Signal<Option<PathBuf>>,
+    /// The configured yt-dlp binary path state.
+    yt_dlp_path: Signal<Option<PathBuf>>,
+    /// How fetching an extractor's binary is retried.
+    retry_policy: RetryPolicy,
+) -> Element {
+    let mut expanded = use_signal(|| false);
+    let mut preparing = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+    let toggle_text = if expanded() { "Hide" } else { "Show" };
+
+    let kind = extractor_kind();
+
+    let path_state = move |dependency| match dependency {
+        Dependency::Ffmpeg => ffmpeg_path,
+        Dependency::YoutubeDl => youtube_dl_path,
+        Dependency::YtDlp => yt_dlp_path,
+    };
+    let dependencies = Dependency::required(kind)
+        .into_iter()
+        .map(|dependency| (dependency, path_state(dependency)))
+        .collect::<Vec<_>>();
+
+    rsx! {
+        div {
+            class: "dependency-settings-container",
+
+            div {
+                class: "dependency-settings-header",
+
+                span {
+                    class: "dependency-settings-label",
+                    "Dependencies"
+                }
+
+                Button {
+                    text: toggle_text,
+                    style: ButtonStyle::Secondary,
+                    onclick: move |_| expanded.set(!expanded()),
+                }
+            }
+
+            if expanded() {
+                div {
+                    class: "dependency-settings-extractor",
+
+                    span {
+                        class: "dependency-selector-label",
+                        "Extractor"
+                    }
+
+                    select {
+                        class: "dependency-selector-select",
+                        disabled: preparing(),
+                        onchange: move |event| {
+                            let Some(kind) = ExtractorKind::ALL
+                                .into_iter()
+                                .find(|kind| kind.extractor_name() == event.value())
+                            else {
+                                return;
+                            };
+
+                            extractor_kind.set(kind);
+                            error.set(None);
+
+                            let Some(dependency) = Dependency::for_extractor(kind) else {
+                                return;
+                            };
+                            let state = path_state(dependency);
+
+                            spawn(async move {
+                                preparing.set(true);
+
+                                if let Err(err) =
+                                    prepare_binary(dependency, state().as_deref(), &retry_policy)
+                                        .await
+                                {
+                                    error.set(Some(err.to_string()));
+                                }
+
+                                preparing.set(false);
+                            });
+                        },
+
+                        for option_kind in ExtractorKind::ALL {
+                            option {
+                                key: "{option_kind.extractor_name()}",
+                                value: option_kind.extractor_name(),
+                                selected: option_kind == kind,
+                                {option_kind.extractor_name()}
+                            }
+                        }
+                    }
+                }
+
+                ControlError {
+                    message: error().map(|message| format!("Failed to install the extractor: {}", message))
+                }
+
+                if preparing() {
+                    Loading {
+                        size: LoadingSpinnerSize::Small,
+                        text: "Installing the extractor...",
+                    }
+                } else {
+                    for (dependency, state) in dependencies {
+                        DependencySelector {
+                            key: "{dependency.dependency_name()}",
+                            dependency: dependency,
+                            state: state,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
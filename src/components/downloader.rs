@@ -1,74 +1,172 @@
 //! Download orchestration component.
 
 use crate::components::{
-    Button, ButtonStyle, ContentTypeSelector, Loading, LoadingSpinnerSize, OutputDirectorySelector,
-    TextInput,
+    ArchiveList, AudioFormatSelector, BatchUrlInput, Button, ButtonStyle, ChannelSelector,
+    ClipRangeInput, CollisionPolicySelector, ContentTypeSelector, DependencySettings,
+    FilenameTemplateInput, FormatSelector, OutputDirectorySelector, PlaylistSelector,
+    PlaylistStatus, QueueList, SubtitleSelector, TextInput,
 };
 use crate::constants::*;
-use crate::services::{download, parse_video_url, Config};
+use crate::services::{
+    extract_batch, format_timestamp, parse_channel_url, parse_clip_range, parse_playlist_url,
+    parse_video_url, parse_youtube_url, validate_template, Config, DownloadArchive, DownloadQueue,
+    DownloadRequest, YoutubeRef,
+};
+use crate::types::*;
 use dioxus::prelude::*;
+use std::collections::BTreeSet;
 use tokio::time::sleep;
 
-/// The status of a download operation.
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
-enum DownloadStatus {
-    /// No download has occurred or is occurring.
-    #[default]
-    Init,
-    /// A download operation is running.
-    Running,
-    /// A download operation was successful.
-    Success(String),
-    /// A download operation failed.
-    Failure(String),
-}
-
 /// Download configuration and trigger component.
 #[component]
 pub fn Downloader(
     /// The application configuration state.
     config: Config,
+    /// The download queue.
+    queue: DownloadQueue,
+    /// The download archive.
+    archive: DownloadArchive,
 ) -> Element {
     let video_url = use_signal(|| config.video_url);
     let content_type = use_signal(|| config.content_type);
     let output_directory = use_signal(|| config.output_directory);
-
-    let mut status = use_signal(DownloadStatus::default);
+    let max_concurrent_downloads = use_signal(|| config.max_concurrent_downloads);
+    let format_preference = use_signal(|| config.format_preference);
+    let audio_preference = use_signal(|| config.audio_preference);
+    let filename_template = use_signal(|| config.filename_template);
+    let collision_policy = use_signal(|| config.collision_policy);
+    let subtitle_preference = use_signal(|| config.subtitle_preference);
+    let ffmpeg_path = use_signal(|| config.ffmpeg_path);
+    let youtube_dl_path = use_signal(|| config.youtube_dl_path);
+    let yt_dlp_path = use_signal(|| config.yt_dlp_path);
+    let extractor_kind = use_signal(|| config.extractor);
+    // The retry policy is only configurable through the configuration file.
+    let retry_policy = config.retry_policy;
+    let mut ignore_archive = use_signal(|| false);
+    let mut split_chapters = use_signal(|| false);
+    let mut batch_mode = use_signal(|| false);
+    let mut batch_text = use_signal(String::new);
+    let mut clip_start = use_signal(String::new);
+    let mut clip_end = use_signal(String::new);
+    let clip_mode = use_signal(ClipMode::default);
+    let mut format_selector = use_signal(|| None::<String>);
+    let mut subtitle_tracks = use_signal(Vec::<SubtitleTrack>::new);
+    let mut playlist_status = use_signal(PlaylistStatus::default);
+    let mut selected_entries = use_signal(BTreeSet::new);
 
     let video_url_value = video_url();
     let video_id = parse_video_url(&video_url_value);
+    let playlist_id = use_memo(move || parse_playlist_url(&video_url()));
+    let channel = use_memo(move || parse_channel_url(&video_url()));
     let video_url_error = if video_url_value.is_empty() {
         Some("No URL provided".to_owned())
     } else {
-        video_id.is_none().then(|| "Invalid YouTube URL".to_owned())
+        (video_id.is_none() && playlist_id().is_none() && channel().is_none())
+            .then(|| "Invalid YouTube URL".to_owned())
     };
 
-    let allow_download = video_id.is_some()
-        && output_directory().is_some()
-        && !matches!(status(), DownloadStatus::Running);
+    use_effect(move || {
+        let _ = (playlist_id(), channel());
+        playlist_status.set(PlaylistStatus::NotLoaded);
+        selected_entries.set(BTreeSet::new());
+    });
+
+    let single_video_id = use_memo(move || parse_video_url(&video_url()));
+    use_effect(move || {
+        let _ = single_video_id();
+        format_selector.set(None);
+        subtitle_tracks.set(Vec::new());
+    });
+
+    // Timestamps in a video URL prefill the time range, which can then be
+    // changed freely until the URL's timestamps change.
+    let url_times = use_memo(move || match parse_youtube_url(&video_url()) {
+        Some(YoutubeRef::Video {
+            start_time,
+            end_time,
+            ..
+        }) => (start_time, end_time),
+        _ => (None, None),
+    });
+    use_effect(move || {
+        let (start_time, end_time) = url_times();
+        clip_start.set(start_time.map(format_timestamp).unwrap_or_default());
+        clip_end.set(end_time.map(format_timestamp).unwrap_or_default());
+    });
+
+    let batch_import = use_memo(move || extract_batch(&batch_text()));
+
+    let loaded_playlist = match playlist_status() {
+        PlaylistStatus::Loaded(playlist) => Some(playlist),
+        _ => None,
+    };
+    let download_text = if batch_mode() {
+        format!("Download {} videos", batch_import().video_ids.len())
+    } else {
+        match &loaded_playlist {
+            Some(_) => format!("Download {} selected", selected_entries().len()),
+            None => "Download".to_owned(),
+        }
+    };
+    let format_video_id = (!batch_mode() && loaded_playlist.is_none())
+        .then(|| video_id.clone())
+        .flatten();
+    let clip_available =
+        !batch_mode() && loaded_playlist.is_none() && content_type() != ContentType::Thumbnail;
+    let clip = parse_clip_range(&clip_start(), &clip_end(), clip_mode());
+    let clip_range = match &clip {
+        Ok(clip) if clip_available => *clip,
+        _ => ClipRange::default(),
+    };
+    let template_extension = match content_type() {
+        ContentType::Thumbnail => "png",
+        ContentType::Audio => audio_preference().format.extension(),
+        ContentType::Video => "mp4",
+        ContentType::Subtitles => subtitle_preference().format.extension(),
+    };
+    let allow_download = output_directory().is_some()
+        && validate_template(&filename_template()).is_ok()
+        && (!clip_available || clip.is_ok())
+        && (content_type() != ContentType::Subtitles
+            || (format_video_id.is_some() && !subtitle_tracks().is_empty()))
+        && if batch_mode() {
+            !batch_import().video_ids.is_empty()
+        } else {
+            match &loaded_playlist {
+                Some(_) => !selected_entries().is_empty(),
+                None => video_id.is_some(),
+            }
+        };
 
     let mut save_task = use_signal(|| None);
 
-    let save_config = move |video_url, content_type, output_directory| {
+    let save_config = move |config: Config| {
         spawn(async move {
-            let _ = Config {
-                video_url,
-                content_type,
-                output_directory,
-            }
-            .save()
-            .await;
+            let _ = config.save().await;
         });
     };
 
     use_effect(move || {
-        let video_url = video_url();
-        let content_type = content_type();
-        let output_directory = output_directory();
+        let config = Config {
+            video_url: video_url(),
+            content_type: content_type(),
+            output_directory: output_directory(),
+            max_concurrent_downloads: max_concurrent_downloads(),
+            format_preference: format_preference(),
+            audio_preference: audio_preference(),
+            filename_template: filename_template(),
+            collision_policy: collision_policy(),
+            retry_policy,
+            subtitle_preference: subtitle_preference(),
+            extractor: extractor_kind(),
+            ffmpeg_path: ffmpeg_path(),
+            youtube_dl_path: youtube_dl_path(),
+            yt_dlp_path: yt_dlp_path(),
+        };
 
         let previous_task = save_task.replace(Some(spawn(async move {
             sleep(SAVE_CONFIG_SLEEP_DURATION).await;
-            save_config(video_url, content_type, output_directory);
+            save_config(config);
         })));
 
         if let Some(task) = previous_task {
@@ -76,47 +174,87 @@ pub fn Downloader(
         }
     });
 
+    let effect_queue = queue.clone();
+    use_effect(move || {
+        effect_queue.set_max_concurrent(max_concurrent_downloads());
+    });
+
+    let download_queue = queue.clone();
     let perform_download = move |_| {
-        let video_id = video_id.clone();
-        spawn(async move {
-            status.set(DownloadStatus::Running);
-
-            let video_id = match video_id {
-                Some(video_id) => video_id,
-                None => {
-                    status.set(DownloadStatus::Failure(
-                        "unknown video URL format".to_owned(),
-                    ));
-                    return;
-                }
-            };
-
-            let output_dir = match output_directory() {
-                Some(output_dir) => output_dir,
-                None => {
-                    status.set(DownloadStatus::Failure(
-                        "no output directory specified".to_owned(),
-                    ));
-                    return;
-                }
-            };
-
-            let res = download(&video_id, content_type(), &output_dir).await;
-
-            match res {
-                Ok(_) => status.set(DownloadStatus::Success(format!(
-                    "Saved to {}",
-                    output_dir
-                        .components()
-                        .last()
-                        .map(|component| component.as_os_str().to_string_lossy().into_owned())
-                        .unwrap_or_else(|| "folder".to_owned())
-                ))),
-                Err(err) => {
-                    status.set(DownloadStatus::Failure(err.to_string()));
+        let Some(output_dir) = output_directory() else {
+            return;
+        };
+
+        if batch_mode() {
+            for video_id in batch_import().video_ids {
+                download_queue.enqueue(DownloadRequest {
+                    format_preference: format_preference(),
+                    audio_preference: audio_preference(),
+                    filename_template: filename_template(),
+                    collision_policy: collision_policy(),
+                    ignore_archive: ignore_archive(),
+                    retry_policy,
+                    split_chapters: split_chapters(),
+                    extractor: extractor_kind(),
+                    subtitle_preference: subtitle_preference(),
+                    ..DownloadRequest::new(video_id, content_type(), output_dir.clone())
+                });
+            }
+
+            batch_text.set(String::new());
+            return;
+        }
+
+        match &loaded_playlist {
+            Some(playlist) => {
+                let selected = selected_entries();
+
+                // Channel uploads are listed newest first, so their position
+                // in the list is not meaningful for naming.
+                let indexed = channel().is_none();
+
+                for entry in &playlist.entries {
+                    if selected.contains(&entry.index) {
+                        download_queue.enqueue(DownloadRequest {
+                            playlist_index: indexed.then_some(entry.index),
+                            format_preference: format_preference(),
+                            audio_preference: audio_preference(),
+                            filename_template: filename_template(),
+                            collision_policy: collision_policy(),
+                            ignore_archive: ignore_archive(),
+                            retry_policy,
+                            split_chapters: split_chapters(),
+                            extractor: extractor_kind(),
+                            subtitle_preference: subtitle_preference(),
+                            ..DownloadRequest::new(
+                                entry.video_id.clone(),
+                                content_type(),
+                                output_dir.clone(),
+                            )
+                        });
+                    }
                 }
             }
-        });
+            None => {
+                if let Some(video_id) = video_id.clone() {
+                    download_queue.enqueue(DownloadRequest {
+                        format_preference: format_preference(),
+                        format_selector: format_selector(),
+                        audio_preference: audio_preference(),
+                        filename_template: filename_template(),
+                        collision_policy: collision_policy(),
+                        ignore_archive: ignore_archive(),
+                        retry_policy,
+                        split_chapters: split_chapters(),
+                        extractor: extractor_kind(),
+                        clip: clip_range,
+                        subtitle_tracks: subtitle_tracks(),
+                        subtitle_preference: subtitle_preference(),
+                        ..DownloadRequest::new(video_id, content_type(), output_dir)
+                    });
+                }
+            }
+        }
     };
 
     rsx! {
@@ -131,26 +269,133 @@ pub fn Downloader(
                     "YouTube Downloader"
                 }
 
-                TextInput {
-                    state: video_url,
-                    label: "Video URL",
-                    placeholder: "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
-                    error: video_url_error,
+                label {
+                    class: "downloader-batch-mode",
+
+                    input {
+                        r#type: "checkbox",
+                        checked: batch_mode(),
+                        onchange: move |event| batch_mode.set(event.checked()),
+                    }
+
+                    "Download many videos at once"
+                }
+
+                if batch_mode() {
+                    BatchUrlInput {
+                        state: batch_text,
+                        import: batch_import(),
+                    }
+                } else {
+                    TextInput {
+                        state: video_url,
+                        label: "Video, playlist, or channel URL",
+                        placeholder: "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                        error: video_url_error,
+                    }
+
+                    if let Some(channel) = channel() {
+                        ChannelSelector {
+                            channel: channel,
+                            state: playlist_status,
+                            selected: selected_entries,
+                            extractor_kind: extractor_kind(),
+                        }
+                    }
+
+                    if let Some(playlist_id) = playlist_id() {
+                        PlaylistSelector {
+                            playlist_id: playlist_id,
+                            state: playlist_status,
+                            selected: selected_entries,
+                            extractor_kind: extractor_kind(),
+                        }
+                    }
                 }
 
                 ContentTypeSelector {
                     state: content_type,
                 }
 
+                if content_type() == ContentType::Audio {
+                    AudioFormatSelector {
+                        state: audio_preference,
+                    }
+                }
+
+                if content_type() == ContentType::Video {
+                    FormatSelector {
+                        video_id: format_video_id.clone(),
+                        preference: format_preference,
+                        selector: format_selector,
+                        extractor_kind: extractor_kind(),
+                    }
+                }
+
+                if content_type() == ContentType::Subtitles
+                    || (content_type() == ContentType::Video && format_video_id.is_some())
+                {
+                    SubtitleSelector {
+                        video_id: format_video_id.clone(),
+                        tracks: subtitle_tracks,
+                        preference: subtitle_preference,
+                        allow_embed: content_type() == ContentType::Video && !split_chapters(),
+                        extractor_kind: extractor_kind(),
+                    }
+                }
+
+                if clip_available {
+                    ClipRangeInput {
+                        start: clip_start,
+                        end: clip_end,
+                        mode: clip_mode,
+                    }
+                }
+
+                if matches!(content_type(), ContentType::Audio | ContentType::Video) {
+                    label {
+                        class: "downloader-split-chapters",
+
+                        input {
+                            r#type: "checkbox",
+                            checked: split_chapters(),
+                            onchange: move |event| split_chapters.set(event.checked()),
+                        }
+
+                        "Split into one file per chapter, if the video has chapters"
+                    }
+                }
+
                 OutputDirectorySelector {
                     state: output_directory,
                 }
 
+                FilenameTemplateInput {
+                    state: filename_template,
+                    extension: template_extension,
+                }
+
+                CollisionPolicySelector {
+                    state: collision_policy,
+                }
+
+                label {
+                    class: "downloader-ignore-archive",
+
+                    input {
+                        r#type: "checkbox",
+                        checked: ignore_archive(),
+                        onchange: move |event| ignore_archive.set(event.checked()),
+                    }
+
+                    "Download again even if already archived"
+                }
+
                 div {
                     class: "download-button-container",
 
                     Button {
-                        text: "Download",
+                        text: download_text,
                         class: "download-button",
                         style: ButtonStyle::Primary,
                         disabled: !allow_download,
@@ -158,34 +403,21 @@ pub fn Downloader(
                     }
                 }
 
-                div {
-                    class: "download-status",
-
-                    match status() {
-                        DownloadStatus::Init => rsx! {
-                            span { }
-                        },
-                        DownloadStatus::Running => rsx! {
-                            div {
-                                class: "download-status-running",
-
-                                Loading {
-                                    size: LoadingSpinnerSize::Small,
-                                    class: "download-status-running-spinner",
-                                }
-
-                                span {
-                                    "Performing download..."
-                                }
-                            }
-                        },
-                        DownloadStatus::Success(message) => rsx! {
-                            span { "{message}" }
-                        },
-                        DownloadStatus::Failure(message) => rsx! {
-                            span { "Download failed: {message}" }
-                        },
-                    }
+                QueueList {
+                    queue: queue,
+                    max_concurrent: max_concurrent_downloads,
+                }
+
+                ArchiveList {
+                    archive: archive,
+                }
+
+                DependencySettings {
+                    extractor_kind: extractor_kind,
+                    ffmpeg_path: ffmpeg_path,
+                    youtube_dl_path: youtube_dl_path,
+                    yt_dlp_path: yt_dlp_path,
+                    retry_policy: retry_policy,
                 }
             }
         }
@@ -0,0 +1,78 @@
+//! UI component for editing the output filename template.
+
+use crate::components::TextInput;
+use crate::constants::*;
+use crate::services::{
+    render_template, validate_template, TemplateValues, VideoInfo, TEMPLATE_FIELDS,
+};
+use dioxus::prelude::*;
+
+/// Returns the video information used to preview templates.
+fn preview_info() -> VideoInfo {
+    VideoInfo {
+        id: "dQw4w9WgXcQ".to_owned(),
+        title: "Rick Astley - Never Gonna Give You Up (Official Music Video)".to_owned(),
+        uploader: Some("Rick Astley".to_owned()),
+        channel: Some("Rick Astley".to_owned()),
+        upload_date: Some("20091025".to_owned()),
+        description: None,
+        webpage_url: None,
+        thumbnail: None,
+        chapters: None,
+    }
+}
+
+/// A text input for the filename template, with a preview of the path it
+/// produces.
+#[component]
+pub fn FilenameTemplateInput(
+    /// The filename template state.
+    state: Signal<String>,
+    /// The extension to show in the preview.
+    extension: String,
+) -> Element {
+    let template = state();
+    let error = validate_template(&template)
+        .err()
+        .map(|err| err.to_string());
+    let preview = render_template(
+        &template,
+        &TemplateValues {
+            info: &preview_info(),
+            playlist_index: None,
+            extension: &extension,
+        },
+    )
+    .ok()
+    .map(|path| path.display().to_string());
+    let fields = TEMPLATE_FIELDS
+        .iter()
+        .map(|field| format!("{{{}}}", field))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    rsx! {
+        div {
+            class: "filename-template-container",
+
+            TextInput {
+                state: state,
+                label: "Filename template",
+                placeholder: DEFAULT_FILENAME_TEMPLATE,
+                error: error,
+            }
+
+            span {
+                class: "filename-template-hint",
+                "Fields: {fields}"
+            }
+
+            if let Some(preview) = preview {
+                span {
+                    class: "filename-template-preview",
+                    "Preview: {preview}"
+                }
+            }
+        }
+    }
+}
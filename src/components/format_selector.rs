@@ -0,0 +1,189 @@
+//! UI component for choosing the quality and format of downloaded videos.
+
+use crate::components::{Button, ButtonStyle, Loading, LoadingSpinnerSize};
+use crate::services::{extractor, VideoFormat};
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// The maximum video heights offered as quality preferences.
+const MAX_HEIGHT_OPTIONS: [u32; 6] = [2160, 1440, 1080, 720, 480, 360];
+
+/// The status of probing a video's available formats.
+#[derive(Debug, Clone, Default, PartialEq)]
+enum FormatsStatus {
+    /// The video's formats have not been requested.
+    #[default]
+    NotLoaded,
+    /// The video's formats are being fetched.
+    Loading,
+    /// The formats of the video with the given ID were fetched successfully.
+    Loaded(String, Vec<VideoFormat>),
+    /// Fetching the video's formats failed.
+    Failed(String),
+}
+
+/// Quality preference controls, with an optional explicit choice among the
+/// formats available for a single video.
+#[component]
+pub fn FormatSelector(
+    /// The ID of the video whose formats can be probed, if a single video is
+    /// being downloaded.
+    video_id: Option<String>,
+    /// The quality preference state.
+    preference: Signal<FormatPreference>,
+    /// The explicitly selected format selector state, overriding the
+    /// preference.
+    selector: Signal<Option<String>>,
+    /// The extractor that probes the formats.
+    extractor_kind: ExtractorKind,
+) -> Element {
+    let mut status = use_signal(FormatsStatus::default);
+
+    let loaded_formats = match (status(), &video_id) {
+        (FormatsStatus::Loaded(loaded_id, formats), Some(video_id)) if &loaded_id == video_id => {
+            Some(formats)
+        }
+        _ => None,
+    };
+    let loading = status() == FormatsStatus::Loading;
+
+    let probe_video_id = video_id.clone();
+    let probe_formats = move |_| {
+        let Some(video_id) = probe_video_id.clone() else {
+            return;
+        };
+
+        spawn(async move {
+            status.set(FormatsStatus::Loading);
+
+            match extractor(extractor_kind).fetch_formats(&video_id).await {
+                Ok(formats) => status.set(FormatsStatus::Loaded(video_id, formats)),
+                Err(err) => status.set(FormatsStatus::Failed(err.to_string())),
+            }
+        });
+    };
+
+    let max_height_value = preference()
+        .max_height
+        .map(|height| height.to_string())
+        .unwrap_or_default();
+    let codec_value = preference().codec.codec_name();
+    let selector_value = selector().unwrap_or_default();
+
+    rsx! {
+        div {
+            class: "format-selector-container",
+
+            span {
+                class: "format-selector-label",
+                "Video quality"
+            }
+
+            div {
+                class: "format-selector-preferences",
+
+                select {
+                    class: "format-selector-select",
+                    value: "{max_height_value}",
+                    onchange: move |event| {
+                        preference.write().max_height = event.value().parse().ok();
+                    },
+
+                    option {
+                        value: "",
+                        selected: max_height_value.is_empty(),
+                        "Best available"
+                    }
+
+                    for height in MAX_HEIGHT_OPTIONS {
+                        option {
+                            key: "{height}",
+                            value: "{height}",
+                            selected: max_height_value == height.to_string(),
+                            "Up to {height}p"
+                        }
+                    }
+                }
+
+                select {
+                    class: "format-selector-select",
+                    value: "{codec_value}",
+                    onchange: move |event| {
+                        if let Some(codec) = VideoCodec::ALL
+                            .into_iter()
+                            .find(|codec| codec.codec_name() == event.value())
+                        {
+                            preference.write().codec = codec;
+                        }
+                    },
+
+                    for codec in VideoCodec::ALL {
+                        option {
+                            key: "{codec.codec_name()}",
+                            value: codec.codec_name(),
+                            selected: codec == preference().codec,
+                            {codec.codec_name()}
+                        }
+                    }
+                }
+            }
+
+            if video_id.is_some() {
+                match (loaded_formats, status()) {
+                    (Some(formats), _) => rsx! {
+                        select {
+                            class: "format-selector-select",
+                            value: "{selector_value}",
+                            onchange: move |event| {
+                                let value = event.value();
+                                selector.set((!value.is_empty()).then_some(value));
+                            },
+
+                            option {
+                                value: "",
+                                selected: selector_value.is_empty(),
+                                "Automatic (use preference)"
+                            }
+
+                            for format in formats.into_iter().rev() {
+                                option {
+                                    key: "{format.format_id}",
+                                    value: format.format_selector(),
+                                    selected: selector_value == format.format_selector(),
+                                    {format.description()}
+                                }
+                            }
+                        }
+                    },
+                    (None, FormatsStatus::Loading) => rsx! {
+                        Loading {
+                            size: LoadingSpinnerSize::Small,
+                            text: "Probing available formats...",
+                        }
+                    },
+                    (None, status) => rsx! {
+                        div {
+                            class: "format-selector-probe",
+
+                            if let FormatsStatus::Failed(message) = status {
+                                span {
+                                    class: "control-error",
+                                    "Failed to probe formats: {message}"
+                                }
+                            } else {
+                                span { "Choose a specific format instead of a preference." }
+                            }
+
+                            Button {
+                                text: "Probe formats",
+                                style: ButtonStyle::Secondary,
+                                disabled: loading,
+                                onclick: probe_formats,
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
@@ -1,21 +1,47 @@
 //! Application UI components.
 
 mod app;
+mod archive_list;
+mod audio_format_selector;
+mod batch_url_input;
 mod button;
+mod channel_selector;
+mod clip_range_input;
+mod collision_policy_selector;
 mod content_type_selector;
 mod control_error;
+mod dependency_settings;
 mod downloader;
 mod error;
+mod filename_template_input;
+mod format_selector;
 mod loading;
 mod output_directory_selector;
+mod playlist_selector;
+mod progress_bar;
+mod queue_list;
+mod subtitle_selector;
 mod text_input;
 
 pub use app::*;
+pub use archive_list::*;
+pub use audio_format_selector::*;
+pub use batch_url_input::*;
 pub use button::*;
+pub use channel_selector::*;
+pub use clip_range_input::*;
+pub use collision_policy_selector::*;
 pub use content_type_selector::*;
 pub use control_error::*;
+pub use dependency_settings::*;
 pub use downloader::*;
 pub use error::*;
+pub use filename_template_input::*;
+pub use format_selector::*;
 pub use loading::*;
 pub use output_directory_selector::*;
+pub use playlist_selector::*;
+pub use progress_bar::*;
+pub use queue_list::*;
+pub use subtitle_selector::*;
 pub use text_input::*;
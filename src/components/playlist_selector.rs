@@ -0,0 +1,185 @@
+//! UI component for selecting which entries of a playlist to download.
+
+use crate::classes::*;
+use crate::components::{Button, ButtonStyle, Loading, LoadingSpinnerSize};
+use crate::services::{extractor, Playlist};
+use crate::types::*;
+use dioxus::prelude::*;
+use std::collections::BTreeSet;
+
+/// The status of loading a playlist's entries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PlaylistStatus {
+    /// The playlist's entries have not been requested.
+    #[default]
+    NotLoaded,
+    /// The playlist's entries are being fetched.
+    Loading,
+    /// The playlist's entries were fetched successfully.
+    Loaded(Playlist),
+    /// Fetching the playlist's entries failed.
+    Failed(String),
+}
+
+/// A checklist of the entries in a playlist.
+#[component]
+pub fn PlaylistSelector(
+    /// The ID of the playlist.
+    playlist_id: String,
+    /// The playlist loading state.
+    state: Signal<PlaylistStatus>,
+    /// The playlist indices of the selected entries.
+    selected: Signal<BTreeSet<usize>>,
+    /// The extractor that fetches the playlist's entries.
+    extractor_kind: ExtractorKind,
+) -> Element {
+    let load_playlist = move |_| {
+        let playlist_id = playlist_id.clone();
+        spawn(async move {
+            state.set(PlaylistStatus::Loading);
+
+            match extractor(extractor_kind).fetch_playlist(&playlist_id).await {
+                Ok(playlist) => {
+                    selected.set(playlist.entries.iter().map(|entry| entry.index).collect());
+                    state.set(PlaylistStatus::Loaded(playlist));
+                }
+                Err(err) => state.set(PlaylistStatus::Failed(err.to_string())),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "playlist-selector-container",
+
+            span {
+                class: "playlist-selector-label",
+                "Playlist"
+            }
+
+            match state() {
+                PlaylistStatus::NotLoaded => rsx! {
+                    div {
+                        class: "playlist-selector-load",
+
+                        span { "This URL refers to a playlist." }
+
+                        Button {
+                            text: "Load entries",
+                            style: ButtonStyle::Primary,
+                            onclick: load_playlist,
+                        }
+                    }
+                },
+                PlaylistStatus::Loading => rsx! {
+                    Loading {
+                        size: LoadingSpinnerSize::Small,
+                        text: "Loading playlist entries...",
+                    }
+                },
+                PlaylistStatus::Failed(message) => rsx! {
+                    div {
+                        class: "playlist-selector-load",
+
+                        span {
+                            class: "control-error",
+                            "Failed to load playlist: {message}"
+                        }
+
+                        Button {
+                            text: "Retry",
+                            style: ButtonStyle::Primary,
+                            onclick: load_playlist,
+                        }
+                    }
+                },
+                PlaylistStatus::Loaded(playlist) => rsx! {
+                    PlaylistChecklist {
+                        playlist: playlist,
+                        selected: selected,
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// The checklist of a loaded playlist's entries.
+#[component]
+pub fn PlaylistChecklist(
+    /// The loaded playlist.
+    playlist: Playlist,
+    /// The playlist indices of the selected entries.
+    selected: Signal<BTreeSet<usize>>,
+) -> Element {
+    let title = playlist
+        .title
+        .clone()
+        .unwrap_or_else(|| playlist.id.clone());
+    let total = playlist.entries.len();
+    let selected_count = selected().len();
+    let all_indices = playlist
+        .entries
+        .iter()
+        .map(|entry| entry.index)
+        .collect::<BTreeSet<_>>();
+
+    rsx! {
+        div {
+            class: "playlist-selector-header",
+
+            span {
+                class: "playlist-selector-title",
+                "{title} ({selected_count} of {total} selected)"
+            }
+
+            Button {
+                text: "Select all",
+                style: ButtonStyle::Secondary,
+                onclick: move |_| selected.set(all_indices.clone()),
+            }
+
+            Button {
+                text: "Select none",
+                style: ButtonStyle::Secondary,
+                onclick: move |_| selected.set(BTreeSet::new()),
+            }
+        }
+
+        div {
+            class: "playlist-selector",
+
+            for entry in playlist.entries {
+                label {
+                    key: "{entry.index}",
+                    class: classes!(
+                        "playlist-selector-entry",
+                        selected().contains(&entry.index).then_some("playlist-selector-entry-selected")
+                    ),
+
+                    input {
+                        r#type: "checkbox",
+                        checked: selected().contains(&entry.index),
+                        onchange: move |event| {
+                            if event.checked() {
+                                selected.write().insert(entry.index);
+                            } else {
+                                selected.write().remove(&entry.index);
+                            }
+                        },
+                    }
+
+                    span {
+                        class: "playlist-selector-entry-index",
+                        "{entry.index}."
+                    }
+
+                    span {
+                        class: "playlist-selector-entry-title",
+                        {entry.title.clone().unwrap_or_else(|| entry.video_id.clone())}
+                    }
+                }
+            }
+        }
+    }
+}
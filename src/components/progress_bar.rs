@@ -0,0 +1,27 @@
+//! Progress bar component.
+
+use crate::classes::*;
+use dioxus::prelude::*;
+
+/// A progress bar component.
+#[component]
+pub fn ProgressBar(
+    /// The completion percentage, from 0 to 100.
+    percent: f64,
+    /// An optional class name for the progress bar element.
+    class: Option<String>,
+) -> Element {
+    let container_class = classes!("progress-bar", class);
+    let percent = percent.clamp(0.0, 100.0);
+
+    rsx! {
+        div {
+            class: "{container_class}",
+
+            div {
+                class: "progress-bar-fill",
+                style: "width: {percent}%",
+            }
+        }
+    }
+}
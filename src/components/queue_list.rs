@@ -0,0 +1,313 @@
+//! UI component listing the jobs in the download queue.
+
+use crate::classes::*;
+use crate::components::{Button, ButtonStyle, Loading, LoadingSpinnerSize, ProgressBar};
+use crate::services::{DownloadFailure, DownloadQueue, JobState, QueueJob};
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// A description of a failed download, with its raw log available on
+/// demand.
+#[component]
+fn FailureDetails(
+    /// The failure to describe.
+    failure: DownloadFailure,
+) -> Element {
+    let mut show_log = use_signal(|| false);
+    let log = if failure.log.trim().is_empty() {
+        failure.description.clone()
+    } else {
+        format!("{}\n\n{}", failure.description, failure.log.trim())
+    };
+
+    rsx! {
+        div {
+            class: "queue-list-item-failure",
+
+            div {
+                class: "queue-list-item-failure-summary",
+
+                span { "Download failed: {failure.message()}" }
+
+                Button {
+                    text: if show_log() { "Hide log" } else { "Show log" },
+                    style: ButtonStyle::Secondary,
+                    onclick: move |_| show_log.set(!show_log()),
+                }
+            }
+
+            if show_log() {
+                pre {
+                    class: "queue-list-item-log",
+                    "{log}"
+                }
+            }
+        }
+    }
+}
+
+/// A single job in the download queue.
+#[component]
+fn QueueListItem(
+    /// The download queue.
+    queue: DownloadQueue,
+    /// The job to display.
+    job: QueueJob,
+) -> Element {
+    let id = job.id;
+    let title = match job.request.playlist_index {
+        Some(index) => format!("#{} {}", index, job.request.video_id),
+        None => job.request.video_id.clone(),
+    };
+    let running = matches!(job.state, JobState::Running(_));
+    let cancellable = running || job.state == JobState::Pending;
+    let retryable = job.state.is_retryable();
+    let item_class = classes!(
+        "queue-list-item",
+        match &job.state {
+            JobState::Pending => "queue-list-item-pending",
+            JobState::Running(_) => "queue-list-item-running",
+            JobState::Completed(_) => "queue-list-item-completed",
+            JobState::Failed(_) => "queue-list-item-failed",
+            JobState::Conflict(_) => "queue-list-item-conflict",
+            JobState::Cancelled => "queue-list-item-cancelled",
+        }
+    );
+
+    let cancel_queue = queue.clone();
+    let retry_queue = queue.clone();
+    let remove_queue = queue.clone();
+    let resolve_queue = queue.clone();
+
+    rsx! {
+        div {
+            class: "{item_class}",
+
+            div {
+                class: "queue-list-item-header",
+
+                span {
+                    class: "queue-list-item-title",
+                    "{title}"
+                }
+
+                span {
+                    class: "queue-list-item-content-type",
+                    "{job.request.content_type.type_name()}"
+                }
+
+                div {
+                    class: "queue-list-item-actions",
+
+                    if cancellable {
+                        Button {
+                            text: "Cancel",
+                            style: ButtonStyle::Secondary,
+                            onclick: move |_| cancel_queue.cancel(id),
+                        }
+                    }
+
+                    if retryable {
+                        Button {
+                            text: "Retry",
+                            style: ButtonStyle::Secondary,
+                            onclick: move |_| retry_queue.retry(id),
+                        }
+                    }
+
+                    if !running {
+                        Button {
+                            text: "Remove",
+                            style: ButtonStyle::Secondary,
+                            onclick: move |_| remove_queue.remove(id),
+                        }
+                    }
+                }
+            }
+
+            div {
+                class: "queue-list-item-status",
+
+                match &job.state {
+                    JobState::Pending if job.interrupted => rsx! {
+                        span { "Waiting to resume interrupted download..." }
+                    },
+                    JobState::Pending => rsx! {
+                        span { "Waiting..." }
+                    },
+                    JobState::Running(progress) => rsx! {
+                        div {
+                            class: "queue-list-item-running-status",
+
+                            if let Some(percent) = progress.percent {
+                                ProgressBar {
+                                    percent: percent,
+                                    class: "queue-list-item-progress",
+                                }
+                            } else {
+                                Loading {
+                                    size: LoadingSpinnerSize::Small,
+                                    class: "queue-list-item-spinner",
+                                }
+                            }
+
+                            span {
+                                "{progress}"
+                            }
+                        }
+                    },
+                    JobState::Completed(outcome) if outcome.archived => rsx! {
+                        span { "Already downloaded to {outcome.path.display()} (in download archive)" }
+                    },
+                    JobState::Completed(outcome) => rsx! {
+                        span {
+                            "Saved to {outcome.path.display()}"
+
+                            if let Some(strategy) = outcome.audio_strategy {
+                                " ({strategy})"
+                            }
+
+                            if let Some(collision) = outcome.collision {
+                                " ({collision.decision_name()})"
+                            }
+                        }
+
+                        for warning in outcome.warnings.iter() {
+                            span {
+                                key: "{warning}",
+                                class: "queue-list-item-warning",
+                                "Warning: {warning}"
+                            }
+                        }
+                    },
+                    JobState::Conflict(path) => rsx! {
+                        span { "File already exists: {path.display()}" }
+
+                        div {
+                            class: "queue-list-item-actions",
+
+                            for (text, resolution) in [
+                                ("Overwrite", Some(CollisionPolicy::Overwrite)),
+                                ("Keep both", Some(CollisionPolicy::Rename)),
+                                ("Skip", None),
+                            ] {
+                                Button {
+                                    text: text,
+                                    style: ButtonStyle::Secondary,
+                                    onclick: {
+                                        let queue = resolve_queue.clone();
+                                        move |_| queue.resolve_conflict(id, resolution)
+                                    },
+                                }
+                            }
+                        }
+                    },
+                    JobState::Failed(failure) => rsx! {
+                        FailureDetails {
+                            failure: failure.clone(),
+                        }
+                    },
+                    JobState::Cancelled => rsx! {
+                        span { "Download cancelled" }
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// A list of the jobs in the download queue, along with controls for the
+/// queue as a whole.
+#[component]
+pub fn QueueList(
+    /// The download queue.
+    queue: DownloadQueue,
+    /// The maximum number of concurrent downloads.
+    max_concurrent: Signal<usize>,
+) -> Element {
+    let mut jobs = use_signal(|| queue.jobs());
+
+    let subscribe_queue = queue.clone();
+    use_future(move || {
+        let queue = subscribe_queue.clone();
+        async move {
+            let mut receiver = queue.subscribe();
+            receiver.mark_changed();
+
+            while receiver.changed().await.is_ok() {
+                let updated_jobs = receiver.borrow_and_update().jobs().to_vec();
+                jobs.set(updated_jobs);
+            }
+        }
+    });
+
+    let any_finished = jobs()
+        .iter()
+        .any(|job| matches!(job.state, JobState::Completed(_) | JobState::Cancelled));
+    let clear_queue = queue.clone();
+
+    rsx! {
+        div {
+            class: "queue-list-container",
+
+            div {
+                class: "queue-list-header",
+
+                span {
+                    class: "queue-list-label",
+                    "Download queue"
+                }
+
+                div {
+                    class: "queue-list-concurrency",
+
+                    span { "Concurrent downloads" }
+
+                    Button {
+                        text: "-",
+                        style: ButtonStyle::Secondary,
+                        disabled: max_concurrent() <= 1,
+                        onclick: move |_| max_concurrent.set(max_concurrent().saturating_sub(1).max(1)),
+                    }
+
+                    span {
+                        class: "queue-list-concurrency-value",
+                        "{max_concurrent}"
+                    }
+
+                    Button {
+                        text: "+",
+                        style: ButtonStyle::Secondary,
+                        onclick: move |_| max_concurrent.set(max_concurrent() + 1),
+                    }
+                }
+
+                Button {
+                    text: "Clear finished",
+                    style: ButtonStyle::Secondary,
+                    disabled: !any_finished,
+                    onclick: move |_| clear_queue.clear_finished(),
+                }
+            }
+
+            div {
+                class: "queue-list",
+
+                if jobs().is_empty() {
+                    span {
+                        class: "queue-list-empty",
+                        "No downloads queued"
+                    }
+                }
+
+                for job in jobs() {
+                    QueueListItem {
+                        key: "{job.id}",
+                        queue: queue.clone(),
+                        job: job,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! UI component for choosing subtitle tracks to download.
+
+use crate::components::{Button, ButtonStyle, Loading, LoadingSpinnerSize};
+use crate::services::{extractor, AvailableSubtitle};
+use crate::types::*;
+use dioxus::prelude::*;
+
+/// The status of listing a video's subtitle tracks.
+#[derive(Debug, Clone, Default, PartialEq)]
+enum SubtitlesStatus {
+    /// The video's subtitle tracks have not been requested.
+    #[default]
+    NotLoaded,
+    /// The video's subtitle tracks are being fetched.
+    Loading,
+    /// The subtitle tracks of the video with the given ID were fetched
+    /// successfully.
+    Loaded(String, Vec<AvailableSubtitle>),
+    /// Fetching the video's subtitle tracks failed.
+    Failed(String),
+}
+
+/// Subtitle format and embedding controls, with a choice among the subtitle
+/// and caption tracks available for a single video.
+#[component]
+pub fn SubtitleSelector(
+    /// The ID of the video whose subtitle tracks can be listed, if a single
+    /// video is being downloaded.
+    video_id: Option<String>,
+    /// The selected subtitle tracks state.
+    tracks: Signal<Vec<SubtitleTrack>>,
+    /// The subtitle preference state.
+    preference: Signal<SubtitlePreference>,
+    /// Whether the subtitles can be embedded into a downloaded video.
+    #[props(default = false)]
+    allow_embed: bool,
+    /// The extractor that lists the subtitle tracks.
+    extractor_kind: ExtractorKind,
+) -> Element {
+    let mut status = use_signal(SubtitlesStatus::default);
+    let selected_tracks = tracks();
+
+    // Each available track, with its description and whether it is selected.
+    let loaded_subtitles = match (status(), &video_id) {
+        (SubtitlesStatus::Loaded(loaded_id, subtitles), Some(video_id))
+            if &loaded_id == video_id =>
+        {
+            Some(
+                subtitles
+                    .into_iter()
+                    .map(|subtitle| {
+                        let selected = selected_tracks.contains(&subtitle.track);
+                        (subtitle.track.clone(), subtitle.description(), selected)
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        }
+        _ => None,
+    };
+    let loading = status() == SubtitlesStatus::Loading;
+
+    let list_video_id = video_id.clone();
+    let list_subtitles = move |_| {
+        let Some(video_id) = list_video_id.clone() else {
+            return;
+        };
+
+        spawn(async move {
+            status.set(SubtitlesStatus::Loading);
+
+            match extractor(extractor_kind).fetch_subtitles(&video_id).await {
+                Ok(subtitles) => status.set(SubtitlesStatus::Loaded(video_id, subtitles)),
+                Err(err) => status.set(SubtitlesStatus::Failed(err.to_string())),
+            }
+        });
+    };
+
+    let format_value = preference().format.format_name();
+
+    rsx! {
+        div {
+            class: "subtitle-selector-container",
+
+            span {
+                class: "subtitle-selector-label",
+                "Subtitles"
+            }
+
+            div {
+                class: "subtitle-selector-preferences",
+
+                select {
+                    class: "subtitle-selector-select",
+                    value: "{format_value}",
+                    onchange: move |event| {
+                        if let Some(format) = SubtitleFormat::ALL
+                            .into_iter()
+                            .find(|format| format.format_name() == event.value())
+                        {
+                            preference.write().format = format;
+                        }
+                    },
+
+                    for format in SubtitleFormat::ALL {
+                        option {
+                            key: "{format.format_name()}",
+                            value: format.format_name(),
+                            selected: format == preference().format,
+                            {format.format_name()}
+                        }
+                    }
+                }
+
+                if allow_embed {
+                    label {
+                        class: "subtitle-selector-embed",
+
+                        input {
+                            r#type: "checkbox",
+                            checked: preference().embed,
+                            onchange: move |event| preference.write().embed = event.checked(),
+                        }
+
+                        "Embed into video"
+                    }
+                }
+            }
+
+            if video_id.is_none() {
+                span {
+                    class: "subtitle-selector-note",
+                    "Subtitles can only be chosen when downloading a single video."
+                }
+            } else {
+                match (loaded_subtitles, status()) {
+                    (Some(subtitles), _) if subtitles.is_empty() => rsx! {
+                        span {
+                            class: "subtitle-selector-note",
+                            "This video has no subtitles."
+                        }
+                    },
+                    (Some(subtitles), _) => rsx! {
+                        div {
+                            class: "subtitle-selector-tracks",
+
+                            for (track, description, selected) in subtitles {
+                                label {
+                                    key: "{description}",
+                                    class: "subtitle-selector-track",
+
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: selected,
+                                        onchange: move |event| {
+                                            let mut tracks = tracks.write();
+                                            tracks.retain(|selected| selected != &track);
+
+                                            if event.checked() {
+                                                tracks.push(track.clone());
+                                            }
+                                        },
+                                    }
+
+                                    "{description}"
+                                }
+                            }
+                        }
+                    },
+                    (None, SubtitlesStatus::Loading) => rsx! {
+                        Loading {
+                            size: LoadingSpinnerSize::Small,
+                            text: "Listing available subtitles...",
+                        }
+                    },
+                    (None, status) => rsx! {
+                        div {
+                            class: "subtitle-selector-list",
+
+                            if let SubtitlesStatus::Failed(message) = status {
+                                span {
+                                    class: "control-error",
+                                    "Failed to list subtitles: {message}"
+                                }
+                            } else {
+                                span { "List the subtitle and caption languages available." }
+                            }
+
+                            Button {
+                                text: "List subtitles",
+                                style: ButtonStyle::Secondary,
+                                disabled: loading,
+                                onclick: list_subtitles,
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
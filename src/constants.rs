@@ -26,6 +26,10 @@ pub const FFMPEG_BINARY_URL: &str = "https://github.com/BtbN/FFmpeg-Builds/relea
 #[cfg(not(target_os = "windows"))]
 pub const FFMPEG_BINARY_URL: &str = "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linux64-gpl.tar.xz";
 
+/// The URL of the SHA-256 checksums published with the ffmpeg builds.
+pub const FFMPEG_CHECKSUMS_URL: &str =
+    "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/checksums.sha256";
+
 /// The youtube-dl binary file name.
 #[cfg(target_os = "windows")]
 pub const YOUTUBE_DL_BINARY_NAME: &str = "youtube-dl.exe";
@@ -33,15 +37,41 @@ pub const YOUTUBE_DL_BINARY_NAME: &str = "youtube-dl.exe";
 #[cfg(not(target_os = "windows"))]
 pub const YOUTUBE_DL_BINARY_NAME: &str = "youtube-dl";
 
+/// The URL of the youtube-dl release the binary is fetched from.
+const YOUTUBE_DL_RELEASE_URL: &str =
+    "https://github.com/ytdl-org/ytdl-nightly/releases/download/2024.08.07/";
+
 /// The youtube-dl binary URL.
-pub const YOUTUBE_DL_BINARY_URL: &str = concatcp!(
-    "https://github.com/ytdl-org/ytdl-nightly/releases/download/2024.08.07/",
-    YOUTUBE_DL_BINARY_NAME
-);
+pub const YOUTUBE_DL_BINARY_URL: &str = concatcp!(YOUTUBE_DL_RELEASE_URL, YOUTUBE_DL_BINARY_NAME);
+
+/// The URL of the SHA-256 checksums published with the youtube-dl release.
+pub const YOUTUBE_DL_CHECKSUMS_URL: &str = concatcp!(YOUTUBE_DL_RELEASE_URL, "SHA2-256SUMS");
+
+/// The yt-dlp binary file name.
+#[cfg(target_os = "windows")]
+pub const YT_DLP_BINARY_NAME: &str = "yt-dlp.exe";
+/// The yt-dlp binary file name.
+#[cfg(not(target_os = "windows"))]
+pub const YT_DLP_BINARY_NAME: &str = "yt-dlp";
+
+/// The URL of the yt-dlp release the binary is fetched from.
+const YT_DLP_RELEASE_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/";
+
+/// The yt-dlp binary URL.
+pub const YT_DLP_BINARY_URL: &str = concatcp!(YT_DLP_RELEASE_URL, YT_DLP_BINARY_NAME);
+
+/// The URL of the SHA-256 checksums published with the yt-dlp release.
+pub const YT_DLP_CHECKSUMS_URL: &str = concatcp!(YT_DLP_RELEASE_URL, "SHA2-256SUMS");
 
 /// The name of the configuration file.
 pub const CONFIG_FILE_NAME: &str = "config.json";
 
+/// The name of the download queue file.
+pub const QUEUE_FILE_NAME: &str = "queue.json";
+
+/// The default maximum number of downloads to run at once.
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+
 /// The duration of time to wait before saving the configuration file.
 pub const SAVE_CONFIG_SLEEP_DURATION: Duration = Duration::from_secs(2);
 
@@ -49,3 +79,42 @@ pub const SAVE_CONFIG_SLEEP_DURATION: Duration = Duration::from_secs(2);
 /// external binaries.
 #[cfg(windows)]
 pub const CREATE_NO_WINDOW_FLAG: u32 = 0x08000000;
+
+/// The default audio bitrate in kilobits per second.
+pub const DEFAULT_AUDIO_BITRATE: u32 = 192;
+
+/// The x264 constant rate factor used when re-encoding video clips. Lower
+/// values give higher quality.
+pub const CLIP_VIDEO_CRF: u32 = 18;
+
+/// The default template from which output file paths are rendered.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{title}.{ext}";
+
+/// The name of the download archive file.
+pub const ARCHIVE_FILE_NAME: &str = "archive.json";
+
+/// The name of the file recording the verified dependency binaries.
+pub const DEPENDENCY_MANIFEST_FILE_NAME: &str = "dependencies.json";
+
+/// The maximum time to wait for a dependency binary to report its version.
+pub const BINARY_PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The name of the directory in which downloads are staged.
+pub const STAGING_DIR_NAME: &str = "staging";
+
+/// The default maximum number of times an operation is attempted before it
+/// is considered failed.
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 5;
+
+/// The default delay in seconds before the first retry of a failed
+/// operation.
+pub const DEFAULT_RETRY_INITIAL_DELAY_SECS: u64 = 2;
+
+/// The default factor by which the delay grows with each retry.
+pub const DEFAULT_RETRY_BACKOFF_FACTOR: u32 = 2;
+
+/// The default maximum delay in seconds before any retry.
+pub const DEFAULT_RETRY_MAX_DELAY_SECS: u64 = 60;
+
+/// The default maximum random variation of retry delays, as a percentage.
+pub const DEFAULT_RETRY_JITTER_PERCENT: u32 = 25;
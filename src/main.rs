@@ -1,6 +1,6 @@
 //! A tool to download content from YouTube.
 
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 #![deny(missing_docs)]
 #![warn(unused_mut)]
 #![warn(clippy::missing_docs_in_private_items)]
@@ -10,6 +10,7 @@
 )]
 
 mod classes;
+mod cli;
 mod components;
 mod constants;
 mod hooks;
@@ -25,6 +26,15 @@ use dioxus::desktop::{Config, WindowBuilder};
 use dioxus::prelude::*;
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if cli::is_command_line(&args) {
+        #[cfg(target_os = "windows")]
+        cli::attach_console();
+
+        std::process::exit(cli::run(args));
+    }
+
     let image_icon =
         image::load_from_memory_with_format(WINDOW_ICON, image::ImageFormat::Ico).unwrap();
     let icon = Icon::from_rgba(
@@ -0,0 +1,130 @@
+//! A persistent record of completed downloads, used to skip videos that have
+//! already been fetched.
+
+use crate::constants::*;
+use crate::types::*;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::env::current_exe;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tokio::fs;
+use tokio::sync::watch;
+
+/// Returns the path to the archive file.
+fn archive_file_path() -> Result<PathBuf> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let joined = Path::new(here).join(ARCHIVE_FILE_NAME);
+    Ok(joined)
+}
+
+/// Identifies a download in the archive. The same video may be archived
+/// several times, once for each content type and format it was downloaded
+/// in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArchiveKey {
+    /// The ID of the downloaded video.
+    pub video_id: String,
+    /// The downloaded content type.
+    pub content_type: ContentType,
+    /// The format the content was downloaded in.
+    pub format: String,
+}
+
+/// A completed download recorded in the archive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// The download this entry records.
+    #[serde(flatten)]
+    pub key: ArchiveKey,
+    /// The title of the downloaded video.
+    pub title: String,
+    /// The path the download was saved to.
+    pub path: PathBuf,
+}
+
+/// A handle to the download archive. Clones of a handle all refer to the
+/// same archive.
+#[derive(Debug, Clone)]
+pub struct DownloadArchive {
+    /// The archived entries, in the order they were recorded, along with the
+    /// means to notify subscribers of changes.
+    entries: Rc<watch::Sender<Vec<ArchiveEntry>>>,
+}
+
+impl PartialEq for DownloadArchive {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl DownloadArchive {
+    /// Loads the archive from the file.
+    pub async fn load() -> Result<Self> {
+        let archive_path = archive_file_path()?;
+
+        let entries = if archive_path.exists() {
+            let archive_bytes = fs::read(archive_path).await?;
+            serde_json::from_slice::<Vec<ArchiveEntry>>(&archive_bytes)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            entries: Rc::new(watch::Sender::new(entries)),
+        })
+    }
+
+    /// Creates an empty archive without loading the file.
+    #[cfg(test)]
+    pub fn empty() -> Self {
+        Self {
+            entries: Rc::new(watch::Sender::new(Vec::new())),
+        }
+    }
+
+    /// Saves the archive to the file.
+    async fn save(&self) -> Result<()> {
+        let archive_path = archive_file_path()?;
+        let archive_bytes = serde_json::to_vec(&*self.entries.borrow())?;
+        fs::write(archive_path, archive_bytes).await?;
+        Ok(())
+    }
+
+    /// Subscribes to changes in the archived entries.
+    pub fn subscribe(&self) -> watch::Receiver<Vec<ArchiveEntry>> {
+        self.entries.subscribe()
+    }
+
+    /// Returns a snapshot of all archived entries.
+    pub fn entries(&self) -> Vec<ArchiveEntry> {
+        self.entries.borrow().clone()
+    }
+
+    /// Returns the archived entry for a download, if there is one.
+    pub fn get(&self, key: &ArchiveKey) -> Option<ArchiveEntry> {
+        self.entries
+            .borrow()
+            .iter()
+            .find(|entry| &entry.key == key)
+            .cloned()
+    }
+
+    /// Records a completed download, replacing any previous entry for it.
+    pub async fn record(&self, entry: ArchiveEntry) -> Result<()> {
+        self.entries.send_modify(|entries| {
+            entries.retain(|existing| existing.key != entry.key);
+            entries.push(entry);
+        });
+        self.save().await
+    }
+
+    /// Removes a download from the archive, so that it will be downloaded
+    /// again.
+    pub async fn remove(&self, key: &ArchiveKey) -> Result<()> {
+        self.entries
+            .send_modify(|entries| entries.retain(|entry| &entry.key != key));
+        self.save().await
+    }
+}
@@ -0,0 +1,192 @@
+//! Extraction of video IDs from arbitrary text, for importing many videos at
+//! once.
+
+use crate::services::{parse_youtube_url, valid_video_id, YoutubeRef};
+use std::collections::HashMap;
+
+/// Characters that separate candidate URLs and IDs within a line. These cover
+/// whitespace-separated lists, CSV files, and URLs pasted with surrounding
+/// quotes or brackets.
+const SEPARATORS: [char; 11] = [',', ';', '\t', '"', '\'', '<', '>', '(', ')', '|', '`'];
+
+/// Why a line of batch input was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RejectReason {
+    /// The line contains no YouTube URL or video ID.
+    NothingFound,
+    /// The URL is not a YouTube URL.
+    NotYoutube(String),
+    /// The URL refers to a playlist rather than a single video.
+    Playlist(String),
+    /// The URL refers to a channel rather than a single video.
+    Channel(String),
+    /// The URL is a YouTube URL, but not one that refers to a video.
+    NotVideo(String),
+    /// The video was already found on an earlier line.
+    Duplicate {
+        /// The ID of the video.
+        video_id: String,
+        /// The 1-based number of the line the video was first found on.
+        first_line: usize,
+    },
+}
+
+impl RejectReason {
+    /// Gets a human-readable explanation of the rejection.
+    pub fn message(&self) -> String {
+        match self {
+            Self::NothingFound => "no YouTube URL or video ID found".to_owned(),
+            Self::NotYoutube(url) => format!("not a YouTube URL: {}", url),
+            Self::Playlist(url) => {
+                format!("playlist URLs must be downloaded one at a time: {}", url)
+            }
+            Self::Channel(url) => {
+                format!("channel URLs must be downloaded one at a time: {}", url)
+            }
+            Self::NotVideo(url) => format!("not a video URL: {}", url),
+            Self::Duplicate {
+                video_id,
+                first_line,
+            } => format!("duplicate of {} on line {}", video_id, first_line),
+        }
+    }
+}
+
+/// A line of batch input that was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RejectedLine {
+    /// The 1-based line number.
+    pub line_number: usize,
+    /// The text of the line.
+    pub text: String,
+    /// Why the line was rejected.
+    pub reason: RejectReason,
+}
+
+/// The videos found in batch input, along with the lines that were
+/// rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BatchImport {
+    /// The IDs of the videos found, in the order they first appear, without
+    /// duplicates.
+    pub video_ids: Vec<String>,
+    /// The lines that were rejected, in line order. A line may be rejected
+    /// more than once if it contains several unusable URLs.
+    pub rejected: Vec<RejectedLine>,
+}
+
+/// Returns whether a token looks like a URL, with or without a scheme.
+fn looks_like_url(token: &str) -> bool {
+    token.contains("://") || token.starts_with("www.") || is_youtube_url(token)
+}
+
+/// Returns whether a token is plausibly a bare video ID. Ordinary words of
+/// the right length are excluded by requiring something other than letters.
+fn looks_like_video_id(token: &str) -> bool {
+    valid_video_id(token) && !token.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Returns whether a URL mentions a YouTube domain.
+fn is_youtube_url(url: &str) -> bool {
+    let lowercase = url.to_lowercase();
+    lowercase.contains("youtube.com")
+        || lowercase.contains("youtube-nocookie.com")
+        || lowercase.contains("youtu.be")
+}
+
+/// The result of examining a single token of batch input.
+enum TokenResult {
+    /// The token refers to a video.
+    Video(String),
+    /// The token is a URL that cannot be imported.
+    Rejected(RejectReason),
+    /// The token is not a URL or ID.
+    Ignored,
+}
+
+/// Examines a single token of batch input.
+fn examine_token(token: &str) -> TokenResult {
+    if looks_like_url(token) {
+        match parse_youtube_url(token) {
+            Some(YoutubeRef::Video { id, .. }) => TokenResult::Video(id),
+            Some(YoutubeRef::Playlist(_)) => {
+                TokenResult::Rejected(RejectReason::Playlist(token.to_owned()))
+            }
+            Some(YoutubeRef::Channel(_)) => {
+                TokenResult::Rejected(RejectReason::Channel(token.to_owned()))
+            }
+            None if is_youtube_url(token) => {
+                TokenResult::Rejected(RejectReason::NotVideo(token.to_owned()))
+            }
+            None => TokenResult::Rejected(RejectReason::NotYoutube(token.to_owned())),
+        }
+    } else if looks_like_video_id(token) {
+        TokenResult::Video(token.to_owned())
+    } else {
+        TokenResult::Ignored
+    }
+}
+
+/// Extracts every YouTube video URL and bare video ID from arbitrary text,
+/// such as a list of URLs, a CSV file, or a pasted document. Each video is
+/// only included once. Lines that yield no video are reported as rejected,
+/// as are URLs that do not refer to a single video, and repeated videos.
+/// Blank lines are ignored.
+pub fn extract_batch(text: &str) -> BatchImport {
+    let mut import = BatchImport::default();
+    let mut first_lines = HashMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut reasons = Vec::new();
+        let mut found = false;
+
+        let tokens = line
+            .split(|c: char| c.is_whitespace() || SEPARATORS.contains(&c))
+            .map(|token| token.trim_end_matches(['.', '!', '?']))
+            .filter(|token| !token.is_empty());
+
+        for token in tokens {
+            match examine_token(token) {
+                TokenResult::Video(video_id) => {
+                    found = true;
+
+                    match first_lines.get(&video_id) {
+                        // The same video appearing twice on a line, such as
+                        // in both a URL and an ID column, is not a duplicate.
+                        Some(&first_line) if first_line == line_number => {}
+                        Some(&first_line) => reasons.push(RejectReason::Duplicate {
+                            video_id,
+                            first_line,
+                        }),
+                        None => {
+                            first_lines.insert(video_id.clone(), line_number);
+                            import.video_ids.push(video_id);
+                        }
+                    }
+                }
+                TokenResult::Rejected(reason) => reasons.push(reason),
+                TokenResult::Ignored => {}
+            }
+        }
+
+        if !found && reasons.is_empty() {
+            reasons.push(RejectReason::NothingFound);
+        }
+
+        import
+            .rejected
+            .extend(reasons.into_iter().map(|reason| RejectedLine {
+                line_number,
+                text: line.to_owned(),
+                reason,
+            }));
+    }
+
+    import
+}
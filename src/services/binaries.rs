@@ -0,0 +1,432 @@
+//! Resolution of the dependency binaries that are run, which may be
+//! configured, installed alongside the application, or installed on the
+//! system.
+
+use crate::constants::*;
+use crate::services::{
+    fetch_ffmpeg_binary, fetch_youtube_dl_binary, fetch_yt_dlp_binary, ffmpeg_binary_exists,
+    ffmpeg_install_path, update_youtube_dl_binary, update_yt_dlp_binary, youtube_dl_binary_exists,
+    youtube_dl_install_path, yt_dlp_binary_exists, yt_dlp_install_path, Config, DepError,
+};
+use crate::types::*;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::env::{current_exe, split_paths, var_os};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// The binaries resolved for each dependency, which commands are run with.
+static RESOLVED_BINARIES: Mutex<BTreeMap<Dependency, PathBuf>> = Mutex::new(BTreeMap::new());
+
+/// A binary the application depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dependency {
+    /// ffmpeg, which converts, clips and tags media.
+    Ffmpeg,
+    /// youtube-dl, which fetches videos when it is the selected extractor.
+    YoutubeDl,
+    /// yt-dlp, which fetches videos when it is the selected extractor.
+    YtDlp,
+}
+
+impl Dependency {
+    /// Returns the dependency that runs an extractor, if it runs a binary.
+    pub const fn for_extractor(extractor: ExtractorKind) -> Option<Self> {
+        match extractor {
+            ExtractorKind::YoutubeDl => Some(Self::YoutubeDl),
+            ExtractorKind::YtDlp => Some(Self::YtDlp),
+            #[cfg(test)]
+            ExtractorKind::Fake => None,
+        }
+    }
+
+    /// Returns the dependencies needed with an extractor, in the order they
+    /// are resolved.
+    pub fn required(extractor: ExtractorKind) -> Vec<Self> {
+        [Self::Ffmpeg]
+            .into_iter()
+            .chain(Self::for_extractor(extractor))
+            .collect()
+    }
+
+    /// Gets a human-readable name of the dependency.
+    pub const fn dependency_name(self) -> &'static str {
+        match self {
+            Self::Ffmpeg => "ffmpeg",
+            Self::YoutubeDl => "youtube-dl",
+            Self::YtDlp => "yt-dlp",
+        }
+    }
+
+    /// Returns the file name of the dependency's binary.
+    const fn binary_name(self) -> &'static str {
+        match self {
+            Self::Ffmpeg => FFMPEG_BINARY_NAME,
+            Self::YoutubeDl => YOUTUBE_DL_BINARY_NAME,
+            Self::YtDlp => YT_DLP_BINARY_NAME,
+        }
+    }
+
+    /// Returns the argument that makes the binary print its version.
+    const fn version_arg(self) -> &'static str {
+        match self {
+            Self::Ffmpeg => "-version",
+            Self::YoutubeDl | Self::YtDlp => "--version",
+        }
+    }
+
+    /// Returns the path the binary is installed to alongside the
+    /// application.
+    fn install_path(self) -> Result<PathBuf> {
+        match self {
+            Self::Ffmpeg => ffmpeg_install_path(),
+            Self::YoutubeDl => youtube_dl_install_path(),
+            Self::YtDlp => yt_dlp_install_path(),
+        }
+    }
+
+    /// Checks if a verified binary is installed alongside the application.
+    async fn installed(self) -> Result<bool> {
+        match self {
+            Self::Ffmpeg => ffmpeg_binary_exists().await,
+            Self::YoutubeDl => youtube_dl_binary_exists().await,
+            Self::YtDlp => yt_dlp_binary_exists().await,
+        }
+    }
+
+    /// Fetches the binary and installs it alongside the application.
+    async fn fetch(self, retry_policy: &RetryPolicy) -> Result<()> {
+        match self {
+            Self::Ffmpeg => fetch_ffmpeg_binary(retry_policy).await,
+            Self::YoutubeDl => fetch_youtube_dl_binary(retry_policy).await,
+            Self::YtDlp => fetch_yt_dlp_binary(retry_policy).await,
+        }
+    }
+
+    /// Updates the binary installed alongside the application to the
+    /// published release, if a newer one can be fetched. Returns whether it
+    /// was updated.
+    async fn update(self, retry_policy: &RetryPolicy) -> Result<bool> {
+        match self {
+            Self::Ffmpeg => Ok(false),
+            Self::YoutubeDl => update_youtube_dl_binary(retry_policy).await,
+            Self::YtDlp => update_yt_dlp_binary(retry_policy).await,
+        }
+    }
+
+    /// Returns the binary path the configuration pins the dependency to, if
+    /// any.
+    pub fn configured_path(self, config: &Config) -> Option<&Path> {
+        match self {
+            Self::Ffmpeg => config.ffmpeg_path.as_deref(),
+            Self::YoutubeDl => config.youtube_dl_path.as_deref(),
+            Self::YtDlp => config.yt_dlp_path.as_deref(),
+        }
+    }
+}
+
+/// Where a dependency's binary was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinarySource {
+    /// The path pinned in the configuration.
+    Configured,
+    /// The application's directory, where fetched binaries are installed.
+    AppDirectory,
+    /// A directory on the system's `PATH`.
+    SystemPath,
+}
+
+impl BinarySource {
+    /// Gets a human-readable name of the source.
+    pub const fn source_name(self) -> &'static str {
+        match self {
+            Self::Configured => "configured",
+            Self::AppDirectory => "app directory",
+            Self::SystemPath => "PATH",
+        }
+    }
+}
+
+/// A binary found for a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinaryCandidate {
+    /// The absolute path of the binary.
+    pub path: PathBuf,
+    /// Where the binary was found.
+    pub source: BinarySource,
+    /// The version the binary reported, or why it could not be run.
+    pub version: Result<String, String>,
+}
+
+impl BinaryCandidate {
+    /// Describes the candidate in a human-readable way, e.g.
+    /// `/usr/bin/ffmpeg (PATH, ffmpeg version 6.1.1)`.
+    pub fn description(&self) -> String {
+        let version = match &self.version {
+            Ok(version) => version.clone(),
+            Err(err) => format!("unusable: {}", err),
+        };
+
+        format!(
+            "{} ({}, {})",
+            self.path.display(),
+            self.source.source_name(),
+            version
+        )
+    }
+}
+
+/// Runs a binary to find out its version, returning the first line it
+/// prints.
+pub async fn probe_binary(dependency: Dependency, path: &Path) -> Result<String> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(path);
+    cmd.arg(dependency.version_arg());
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = timeout(
+        BINARY_PROBE_TIMEOUT,
+        cmd.current_dir(here).kill_on_drop(true).output(),
+    )
+    .await
+    .map_err(|_| {
+        DepError::new(
+            "binary did not report its version",
+            path.display().to_string(),
+        )
+    })??;
+    let stdout = String::from_utf8_lossy(&res.stdout);
+    let version = stdout.lines().map(str::trim).find(|line| !line.is_empty());
+
+    match version {
+        Some(version) if res.status.success() => Ok(version.to_owned()),
+        _ => Err(DepError::new(
+            "binary did not report its version",
+            String::from_utf8_lossy(&res.stderr),
+        )
+        .into()),
+    }
+}
+
+/// Probes a binary, recording the result in a candidate.
+async fn probe_candidate(
+    dependency: Dependency,
+    path: PathBuf,
+    source: BinarySource,
+) -> BinaryCandidate {
+    let version = probe_binary(dependency, &path)
+        .await
+        .map_err(|err| err.to_string());
+
+    BinaryCandidate {
+        path,
+        source,
+        version,
+    }
+}
+
+/// Returns the paths at which the binary of a dependency is found without
+/// configuration: a verified binary installed alongside the application,
+/// followed by any binaries on the system's `PATH`.
+async fn unconfigured_paths(dependency: Dependency) -> Result<Vec<(PathBuf, BinarySource)>> {
+    let mut paths = Vec::new();
+
+    if dependency.installed().await? {
+        paths.push((dependency.install_path()?, BinarySource::AppDirectory));
+    }
+
+    let install_dir = dependency.install_path()?.parent().map(Path::to_path_buf);
+
+    for dir in var_os("PATH").iter().flat_map(split_paths) {
+        // Unverified binaries in the application's directory are never run.
+        if !dir.is_absolute() || Some(&dir) == install_dir.as_ref() {
+            continue;
+        }
+
+        let path = dir.join(dependency.binary_name());
+
+        if path.is_file() && !paths.iter().any(|(existing, _)| existing == &path) {
+            paths.push((path, BinarySource::SystemPath));
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Finds and probes every binary that could be used for a dependency, in
+/// order of precedence: the configured path, the application's directory,
+/// and then the system's `PATH`.
+pub async fn find_binaries(
+    dependency: Dependency,
+    configured: Option<&Path>,
+) -> Result<Vec<BinaryCandidate>> {
+    let mut candidates = Vec::new();
+
+    if let Some(path) = configured {
+        candidates
+            .push(probe_candidate(dependency, path.to_path_buf(), BinarySource::Configured).await);
+    }
+
+    for (path, source) in unconfigured_paths(dependency).await? {
+        candidates.push(probe_candidate(dependency, path, source).await);
+    }
+
+    Ok(candidates)
+}
+
+/// Resolves the binary to run for a dependency, which is then used by every
+/// command that runs it. A configured path is always used, and an error is
+/// returned if it cannot be run. Otherwise, the first binary that runs is
+/// used, searching the application's directory and then the system's
+/// `PATH`. If no binary is found, `None` is returned.
+pub async fn resolve_binary(
+    dependency: Dependency,
+    configured: Option<&Path>,
+) -> Result<Option<BinaryCandidate>> {
+    let resolved = match configured {
+        Some(path) => {
+            let candidate =
+                probe_candidate(dependency, path.to_path_buf(), BinarySource::Configured).await;
+
+            if let Err(err) = &candidate.version {
+                return Err(DepError::new(
+                    format!(
+                        "the configured {} binary cannot be run",
+                        dependency.dependency_name()
+                    ),
+                    format!("{}: {}", path.display(), err),
+                )
+                .into());
+            }
+
+            Some(candidate)
+        }
+        None => {
+            let mut resolved = None;
+
+            for (path, source) in unconfigured_paths(dependency).await? {
+                let candidate = probe_candidate(dependency, path, source).await;
+
+                if candidate.version.is_ok() {
+                    resolved = Some(candidate);
+                    break;
+                }
+            }
+
+            resolved
+        }
+    };
+
+    let mut binaries = RESOLVED_BINARIES.lock().unwrap();
+
+    match &resolved {
+        Some(candidate) => binaries.insert(dependency, candidate.path.clone()),
+        None => binaries.remove(&dependency),
+    };
+
+    Ok(resolved)
+}
+
+/// Resolves the binaries of the dependencies needed with the configured
+/// extractor without fetching any. Binaries that are not found are left
+/// unresolved, so that running them fails.
+pub async fn resolve_binaries(config: &Config) -> Result<()> {
+    for dependency in Dependency::required(config.extractor) {
+        resolve_binary(dependency, dependency.configured_path(config)).await?;
+    }
+
+    Ok(())
+}
+
+/// Resolves the binary of a dependency, fetching and installing it if it is
+/// not found. The download is retried according to `retry_policy` if it fails
+/// transiently. Returns the resolved binary and whether it was fetched.
+pub async fn prepare_binary(
+    dependency: Dependency,
+    configured: Option<&Path>,
+    retry_policy: &RetryPolicy,
+) -> Result<(BinaryCandidate, bool)> {
+    if let Some(candidate) = resolve_binary(dependency, configured).await? {
+        return Ok((candidate, false));
+    }
+
+    dependency.fetch(retry_policy).await?;
+    let candidate = resolve_binary(dependency, configured)
+        .await?
+        .ok_or_else(|| {
+            DepError::new(
+                format!(
+                    "the fetched {} binary cannot be run",
+                    dependency.dependency_name()
+                ),
+                dependency
+                    .install_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            )
+        })?;
+
+    Ok((candidate, true))
+}
+
+/// Resolves the binaries of the dependencies needed with the configured
+/// extractor, fetching and installing those that are not found. If `update`
+/// is set, binaries that were already installed alongside the application
+/// are updated to their published releases, where the published release has
+/// changed. Returns the dependencies that were fetched and those that were
+/// updated.
+pub async fn prepare_binaries(
+    config: &Config,
+    update: bool,
+) -> Result<(Vec<Dependency>, Vec<Dependency>)> {
+    let mut fetched = Vec::new();
+    let mut updated = Vec::new();
+
+    for dependency in Dependency::required(config.extractor) {
+        let configured = dependency.configured_path(config);
+        let (candidate, was_fetched) =
+            prepare_binary(dependency, configured, &config.retry_policy).await?;
+
+        if was_fetched {
+            fetched.push(dependency);
+        } else if update
+            // Binaries installed on the system are left to the system to
+            // update.
+            && candidate.source == BinarySource::AppDirectory
+            && dependency.update(&config.retry_policy).await?
+        {
+            updated.push(dependency);
+        }
+    }
+
+    Ok((fetched, updated))
+}
+
+/// Returns the path of the binary resolved for a dependency, if one has
+/// been resolved.
+pub fn resolved_binary_path(dependency: Dependency) -> Option<PathBuf> {
+    RESOLVED_BINARIES.lock().unwrap().get(&dependency).cloned()
+}
+
+/// Returns the path of the binary to run for a dependency. If no binary has
+/// been resolved, the path it is installed to alongside the application is
+/// returned.
+pub fn binary_path(dependency: Dependency) -> Result<PathBuf> {
+    match resolved_binary_path(dependency) {
+        Some(path) => Ok(path),
+        None => dependency.install_path(),
+    }
+}
+
+/// Returns the path of the ffmpeg binary to run.
+pub fn ffmpeg_binary_path() -> Result<PathBuf> {
+    binary_path(Dependency::Ffmpeg)
+}
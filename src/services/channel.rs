@@ -0,0 +1,363 @@
+//! Enumeration of the uploads of YouTube channels.
+
+#[cfg(windows)]
+use crate::constants::*;
+use crate::services::{ChannelRef, CommandLineExtractor, DownloadError, Playlist, PlaylistEntry};
+use anyhow::Result;
+use serde::Deserialize;
+use std::env::current_exe;
+use std::fmt::Display;
+use std::path::Path;
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+/// A calendar date on which a video was uploaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UploadDate {
+    /// The year.
+    year: u16,
+    /// The month, from 1 to 12.
+    month: u8,
+    /// The day of the month, from 1 to 31.
+    day: u8,
+}
+
+impl UploadDate {
+    /// Creates a date from its components, returning `None` if they do not
+    /// form a plausible date.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some(Self { year, month, day })
+    }
+
+    /// Parses a date in youtube-dl's compact `YYYYMMDD` format.
+    pub fn parse_compact(date: &str) -> Option<Self> {
+        if date.len() != 8 || !date.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        Self::new(
+            date[0..4].parse().ok()?,
+            date[4..6].parse().ok()?,
+            date[6..8].parse().ok()?,
+        )
+    }
+}
+
+impl FromStr for UploadDate {
+    type Err = anyhow::Error;
+
+    /// Parses a date in `YYYY-MM-DD` format.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('-');
+        let date = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None) if year.len() == 4 => {
+                Self::new(year.parse()?, month.parse()?, day.parse()?)
+            }
+            _ => None,
+        };
+
+        date.ok_or_else(|| anyhow::anyhow!("expected a date in the form YYYY-MM-DD"))
+    }
+}
+
+impl Display for UploadDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Filters to apply when enumerating a channel's uploads.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ChannelFilter {
+    /// Only include videos uploaded on or after this date.
+    pub uploaded_after: Option<UploadDate>,
+    /// Only include this many of the newest videos.
+    pub max_videos: Option<usize>,
+    /// Exclude YouTube Shorts.
+    pub skip_shorts: bool,
+}
+
+/// A video as reported by youtube-dl's JSON output, in either flat or full
+/// extraction mode.
+#[derive(Debug, Clone, Deserialize)]
+struct ChannelVideo {
+    /// The ID of the video.
+    id: String,
+    /// The title of the video.
+    title: Option<String>,
+    /// The upload date of the video in `YYYYMMDD` format. This is only
+    /// reported in full extraction mode.
+    upload_date: Option<String>,
+}
+
+/// The videos enumerated from one tab of a channel, with their upload dates
+/// if known.
+type TabVideos = Vec<(ChannelVideo, Option<UploadDate>)>;
+
+/// Enumerates the videos on one tab of a channel, newest first.
+///
+/// When filtering by upload date, each video must be fully extracted to learn
+/// its upload date, which is slow. Since uploads are listed newest first,
+/// enumeration stops at the first video older than the cutoff.
+async fn fetch_channel_tab(
+    cli: &CommandLineExtractor,
+    tab_url: &str,
+    filter: &ChannelFilter,
+) -> Result<TabVideos> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("--dump-json").arg("--ignore-errors");
+
+    match filter.uploaded_after {
+        Some(_) => {
+            cmd.arg("--skip-download");
+        }
+        None => {
+            cmd.arg("--flat-playlist");
+
+            if let Some(max_videos) = filter.max_videos {
+                cmd.arg("--playlist-end").arg(max_videos.to_string());
+            }
+        }
+    }
+
+    cmd.arg(tab_url);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let mut child = cmd
+        .current_dir(here)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_task = tokio::spawn(async move {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output).await;
+        output
+    });
+
+    let mut videos = Vec::new();
+    let mut stopped_early = false;
+
+    while let Some(line) = stdout.next_line().await? {
+        let Ok(video) = serde_json::from_str::<ChannelVideo>(&line) else {
+            continue;
+        };
+        let upload_date = video
+            .upload_date
+            .as_deref()
+            .and_then(UploadDate::parse_compact);
+
+        if let (Some(after), Some(date)) = (filter.uploaded_after, upload_date) {
+            if date < after {
+                stopped_early = true;
+                break;
+            }
+        }
+
+        videos.push((video, upload_date));
+
+        if filter
+            .max_videos
+            .is_some_and(|max_videos| videos.len() >= max_videos)
+        {
+            stopped_early = true;
+            break;
+        }
+    }
+
+    if stopped_early {
+        let _ = child.kill().await;
+        return Ok(videos);
+    }
+
+    let status = child.wait().await?;
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    // With `--ignore-errors`, a failure to extract a single video results in
+    // a non-zero exit code, so only fail if nothing could be enumerated.
+    if status.success() || !videos.is_empty() {
+        Ok(videos)
+    } else {
+        Err(DownloadError::new("failed to enumerate channel videos", stderr).into())
+    }
+}
+
+/// Merges the videos of a channel's videos and shorts tabs, each newest
+/// first, into a single list, newest first. When every upload date is known
+/// the tabs are merged by date. Flat enumeration usually omits upload dates,
+/// in which case the tabs are interleaved by their position in each tab,
+/// which only approximates the upload order.
+fn merge_tabs(videos: TabVideos, shorts: TabVideos) -> TabVideos {
+    let dated = videos.iter().chain(&shorts).all(|(_, date)| date.is_some());
+
+    if dated {
+        let mut merged = videos.into_iter().chain(shorts).collect::<Vec<_>>();
+        // The sort is stable, so videos uploaded on the same day keep their
+        // order within each tab.
+        merged.sort_by(|(_, a), (_, b)| b.cmp(a));
+        return merged;
+    }
+
+    let mut merged = Vec::with_capacity(videos.len() + shorts.len());
+    let mut videos = videos.into_iter();
+    let mut shorts = shorts.into_iter();
+
+    loop {
+        match (videos.next(), shorts.next()) {
+            (None, None) => break,
+            (video, short) => merged.extend(video.into_iter().chain(short)),
+        }
+    }
+
+    merged
+}
+
+/// Combines the result of enumerating a channel's videos tab with that of its
+/// shorts tab, if shorts are included. A channel that has only posted shorts
+/// has no videos tab, and one that has never posted shorts has no shorts tab,
+/// so a tab that fails to be enumerated is treated as empty. Enumeration only
+/// fails if every tab does.
+fn combine_tabs(videos: Result<TabVideos>, shorts: Option<Result<TabVideos>>) -> Result<TabVideos> {
+    match (videos, shorts) {
+        (videos, None) => videos,
+        (Ok(videos), Some(Ok(shorts))) => Ok(merge_tabs(videos, shorts)),
+        (Ok(videos), Some(Err(_))) => Ok(videos),
+        (Err(_), Some(Ok(shorts))) => Ok(shorts),
+        (Err(err), Some(Err(_))) => Err(err),
+    }
+}
+
+/// Enumerates the uploads of a channel using a youtube-dl compatible
+/// extractor, applying the given filters. The result is presented as a
+/// playlist of the channel's videos, newest first. When shorts are included
+/// and upload dates are unknown, the order of videos relative to shorts is
+/// approximate, as described in `merge_tabs`.
+pub async fn fetch_channel(
+    cli: &CommandLineExtractor,
+    channel: &ChannelRef,
+    filter: &ChannelFilter,
+) -> Result<Playlist> {
+    let channel_url = channel.url();
+    let videos = fetch_channel_tab(cli, &format!("{}/videos", channel_url), filter).await;
+    let shorts = if filter.skip_shorts {
+        None
+    } else {
+        Some(fetch_channel_tab(cli, &format!("{}/shorts", channel_url), filter).await)
+    };
+    let mut videos = combine_tabs(videos, shorts)?;
+
+    if let Some(max_videos) = filter.max_videos {
+        videos.truncate(max_videos);
+    }
+
+    let entries = videos
+        .into_iter()
+        .enumerate()
+        .map(|(i, (video, _))| PlaylistEntry {
+            index: i + 1,
+            video_id: video.id,
+            title: video.title,
+        })
+        .collect();
+
+    Ok(Playlist {
+        id: channel_url,
+        title: Some(channel.display_name()),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an enumerated video with the given ID and upload date in
+    /// `YYYYMMDD` format.
+    fn video(id: &str, upload_date: Option<&str>) -> (ChannelVideo, Option<UploadDate>) {
+        let video = ChannelVideo {
+            id: id.to_owned(),
+            title: None,
+            upload_date: upload_date.map(str::to_owned),
+        };
+        let upload_date = upload_date.and_then(UploadDate::parse_compact);
+        (video, upload_date)
+    }
+
+    /// Returns the IDs of enumerated videos, in order.
+    fn ids(videos: &TabVideos) -> Vec<&str> {
+        videos.iter().map(|(video, _)| video.id.as_str()).collect()
+    }
+
+    /// Creates a failed tab enumeration.
+    fn failed_tab() -> Result<TabVideos> {
+        Err(DownloadError::new("failed to enumerate channel videos", String::new()).into())
+    }
+
+    #[test]
+    fn merge_tabs_by_date() {
+        let videos = vec![
+            video("v3", Some("20240310")),
+            video("v2", Some("20240201")),
+            video("v1", Some("20240101")),
+        ];
+        let shorts = vec![video("s2", Some("20240310")), video("s1", Some("20240115"))];
+
+        assert_eq!(
+            ids(&merge_tabs(videos, shorts)),
+            ["v3", "s2", "v2", "s1", "v1"]
+        );
+    }
+
+    #[test]
+    fn merge_tabs_by_position() {
+        let videos = vec![video("v3", None), video("v2", None), video("v1", None)];
+        let shorts = vec![video("s2", Some("20240310")), video("s1", None)];
+
+        assert_eq!(
+            ids(&merge_tabs(videos, shorts)),
+            ["v3", "s2", "v2", "s1", "v1"]
+        );
+        assert_eq!(
+            ids(&merge_tabs(Vec::new(), vec![video("s1", None)])),
+            ["s1"]
+        );
+    }
+
+    #[test]
+    fn combine_missing_tabs() {
+        let videos = || Ok(vec![video("v2", None), video("v1", None)]);
+        let shorts = || Ok(vec![video("s1", None)]);
+
+        assert_eq!(
+            ids(&combine_tabs(videos(), Some(shorts())).unwrap()),
+            ["v2", "s1", "v1"]
+        );
+        assert_eq!(ids(&combine_tabs(videos(), None).unwrap()), ["v2", "v1"]);
+        assert_eq!(
+            ids(&combine_tabs(videos(), Some(failed_tab())).unwrap()),
+            ["v2", "v1"]
+        );
+        assert_eq!(
+            ids(&combine_tabs(failed_tab(), Some(shorts())).unwrap()),
+            ["s1"]
+        );
+        assert_eq!(
+            ids(&combine_tabs(Ok(Vec::new()), Some(shorts())).unwrap()),
+            ["s1"]
+        );
+        assert!(combine_tabs(failed_tab(), Some(failed_tab())).is_err());
+        assert!(combine_tabs(failed_tab(), None).is_err());
+    }
+}
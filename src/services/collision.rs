@@ -0,0 +1,60 @@
+//! Handling of output files that already exist.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// How an existing file at a download's output path was handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CollisionDecision {
+    /// The existing file was replaced.
+    Overwritten,
+    /// The existing file is of the same video, so the download was skipped.
+    Skipped,
+    /// The download was saved to a new name alongside the existing file.
+    Renamed,
+}
+
+impl CollisionDecision {
+    /// Gets a human-readable description of the decision.
+    pub const fn decision_name(self) -> &'static str {
+        match self {
+            Self::Overwritten => "overwrote existing file",
+            Self::Skipped => "already downloaded",
+            Self::Renamed => "renamed to keep existing file",
+        }
+    }
+}
+
+/// An error indicating that a download's output file already exists, and the
+/// user must decide what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileCollision {
+    /// The path of the existing file.
+    pub path: PathBuf,
+}
+
+impl Display for FileCollision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "file already exists: {}", self.path.display())
+    }
+}
+
+impl std::error::Error for FileCollision {}
+
+/// Returns the first path of the form `name (n).ext`, counting from 2, that
+/// does not exist yet.
+pub fn suffixed_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
@@ -4,6 +4,7 @@ use crate::constants::*;
 use crate::types::*;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::env::current_exe;
 use std::path::{Path, PathBuf};
 use tokio::fs;
@@ -25,6 +26,28 @@ struct ConfigOpt {
     content_type: Option<ContentType>,
     /// The selected output directory.
     output_directory: Option<PathBuf>,
+    /// The maximum number of downloads to run at once.
+    max_concurrent_downloads: Option<usize>,
+    /// The preferred video quality.
+    format_preference: Option<FormatPreference>,
+    /// The preferred audio format and quality.
+    audio_preference: Option<AudioPreference>,
+    /// The template from which output file paths are rendered.
+    filename_template: Option<String>,
+    /// How to handle existing files at output paths.
+    collision_policy: Option<CollisionPolicy>,
+    /// How operations that fail transiently are retried.
+    retry_policy: Option<RetryPolicy>,
+    /// How downloaded subtitles are saved.
+    subtitle_preference: Option<SubtitlePreference>,
+    /// The program used to extract video information and download streams.
+    extractor: Option<ExtractorKind>,
+    /// The ffmpeg binary to run, overriding the binary otherwise found.
+    ffmpeg_path: Option<PathBuf>,
+    /// The youtube-dl binary to run, overriding the binary otherwise found.
+    youtube_dl_path: Option<PathBuf>,
+    /// The yt-dlp binary to run, overriding the binary otherwise found.
+    yt_dlp_path: Option<PathBuf>,
 }
 
 impl From<Config> for ConfigOpt {
@@ -33,6 +56,17 @@ impl From<Config> for ConfigOpt {
             video_url: Some(value.video_url),
             content_type: Some(value.content_type),
             output_directory: value.output_directory,
+            max_concurrent_downloads: Some(value.max_concurrent_downloads),
+            format_preference: Some(value.format_preference),
+            audio_preference: Some(value.audio_preference),
+            filename_template: Some(value.filename_template),
+            collision_policy: Some(value.collision_policy),
+            retry_policy: Some(value.retry_policy),
+            subtitle_preference: Some(value.subtitle_preference),
+            extractor: Some(value.extractor),
+            ffmpeg_path: value.ffmpeg_path,
+            youtube_dl_path: value.youtube_dl_path,
+            yt_dlp_path: value.yt_dlp_path,
         }
     }
 }
@@ -46,6 +80,28 @@ pub struct Config {
     pub content_type: ContentType,
     /// The selected output directory.
     pub output_directory: Option<PathBuf>,
+    /// The maximum number of downloads to run at once.
+    pub max_concurrent_downloads: usize,
+    /// The preferred video quality.
+    pub format_preference: FormatPreference,
+    /// The preferred audio format and quality.
+    pub audio_preference: AudioPreference,
+    /// The template from which output file paths are rendered.
+    pub filename_template: String,
+    /// How to handle existing files at output paths.
+    pub collision_policy: CollisionPolicy,
+    /// How operations that fail transiently are retried.
+    pub retry_policy: RetryPolicy,
+    /// How downloaded subtitles are saved.
+    pub subtitle_preference: SubtitlePreference,
+    /// The program used to extract video information and download streams.
+    pub extractor: ExtractorKind,
+    /// The ffmpeg binary to run, overriding the binary otherwise found.
+    pub ffmpeg_path: Option<PathBuf>,
+    /// The youtube-dl binary to run, overriding the binary otherwise found.
+    pub youtube_dl_path: Option<PathBuf>,
+    /// The yt-dlp binary to run, overriding the binary otherwise found.
+    pub yt_dlp_path: Option<PathBuf>,
 }
 
 impl From<ConfigOpt> for Config {
@@ -66,6 +122,21 @@ impl From<ConfigOpt> for Config {
                     }
                 })
             }),
+            max_concurrent_downloads: value
+                .max_concurrent_downloads
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_DOWNLOADS),
+            format_preference: value.format_preference.unwrap_or_default(),
+            audio_preference: value.audio_preference.unwrap_or_default(),
+            filename_template: value
+                .filename_template
+                .unwrap_or_else(|| DEFAULT_FILENAME_TEMPLATE.to_owned()),
+            collision_policy: value.collision_policy.unwrap_or_default(),
+            retry_policy: value.retry_policy.unwrap_or_default(),
+            subtitle_preference: value.subtitle_preference.unwrap_or_default(),
+            extractor: value.extractor.unwrap_or_default(),
+            ffmpeg_path: value.ffmpeg_path,
+            youtube_dl_path: value.youtube_dl_path,
+            yt_dlp_path: value.yt_dlp_path,
         }
     }
 }
@@ -90,6 +161,28 @@ impl Config {
         }
     }
 
+    /// Returns the configuration as a JSON object, keyed by field name.
+    pub fn to_json(&self) -> Result<Map<String, Value>> {
+        match serde_json::to_value(ConfigOpt::from(self.clone()))? {
+            Value::Object(fields) => Ok(fields),
+            _ => unreachable!("the configuration serializes to an object"),
+        }
+    }
+
+    /// Sets a single configuration field from its JSON representation.
+    pub fn set_json_field(&mut self, key: &str, value: Value) -> Result<()> {
+        let mut fields = self.to_json()?;
+
+        match fields.get_mut(key) {
+            Some(field) => *field = value,
+            None => return Err(anyhow::anyhow!("unknown configuration key: {}", key)),
+        }
+
+        let config_opt = serde_json::from_value::<ConfigOpt>(Value::Object(fields))?;
+        *self = Self::from(config_opt);
+        Ok(())
+    }
+
     /// Saves the configuration state to the file.
     pub async fn save(&self) -> Result<()> {
         let config_path = config_file_path()?;
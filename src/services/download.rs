@@ -1,15 +1,29 @@
-//! API interfacing with the youtube-dl binary.
+//! Downloading of videos and their audio, thumbnails and subtitles, using an
+//! extractor to fetch the streams and the ffmpeg binary to process them.
 
 use crate::constants::*;
+use crate::services::{
+    chapter_metadata, create_staging_dir, extractor, ffmpeg_binary_path, metadata_args,
+    move_staged_file, parse_youtube_dl_progress, read_lines, render_template, retry,
+    sanitize_file_name, staged_copy_path, suffixed_path, ArchiveEntry, ArchiveKey, CancelHandle,
+    Cancelled, Chapter, CollisionDecision, CommandLineExtractor, DownloadArchive,
+    DownloadErrorKind, DownloadPhase, DownloadProgress, Extractor, FfmpegProgressParser,
+    FileCollision, ProgressSender, TemplateValues, VideoInfo,
+};
 use crate::types::*;
 use anyhow::Result;
-use image::ImageReader;
+use image::{DynamicImage, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::env::current_exe;
 use std::fmt::Display;
+use std::future::Future;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
 use tempfile::TempDir;
-use tokio::process::Command;
+use tokio::fs;
+use tokio::process::{Child, Command};
 
 /// An error occurring during a download operation.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -19,6 +33,8 @@ pub struct DownloadError {
     /// The output of the download operation. This is typically the `stderr` log
     /// of the youtube-dl binary invocation.
     output: String,
+    /// The cause of the error, as classified from the output.
+    kind: DownloadErrorKind,
 }
 
 impl Display for DownloadError {
@@ -27,159 +43,2164 @@ impl Display for DownloadError {
     }
 }
 
+impl DownloadError {
+    /// Creates a new download error from a description and the output of the
+    /// failed operation, classifying its cause from the output.
+    pub fn new(description: impl Into<String>, output: impl Into<String>) -> Self {
+        Self::with_fallback_kind(description, output, DownloadErrorKind::Unknown)
+    }
+
+    /// Creates a new download error for a failed ffmpeg invocation. Unless
+    /// the output indicates a more specific cause, the error is attributed to
+    /// ffmpeg.
+    pub fn ffmpeg(description: impl Into<String>, output: impl Into<String>) -> Self {
+        Self::with_fallback_kind(description, output, DownloadErrorKind::Ffmpeg)
+    }
+
+    /// Creates a new download error for a binary that could not be found.
+    pub fn missing_binary(name: &str) -> Self {
+        Self {
+            description: format!("failed to run {}", name),
+            output: format!("{} was not found", name),
+            kind: DownloadErrorKind::MissingBinary,
+        }
+    }
+
+    /// Creates a new download error, classifying its cause from the output,
+    /// or using `fallback_kind` if the output indicates no known cause.
+    fn with_fallback_kind(
+        description: impl Into<String>,
+        output: impl Into<String>,
+        fallback_kind: DownloadErrorKind,
+    ) -> Self {
+        let output = output.into();
+
+        Self {
+            description: description.into(),
+            kind: DownloadErrorKind::classify(&output).unwrap_or(fallback_kind),
+            output,
+        }
+    }
+
+    /// Returns a description of the error.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the output of the failed operation.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Returns the cause of the error.
+    pub fn kind(&self) -> DownloadErrorKind {
+        self.kind
+    }
+}
+
 impl std::error::Error for DownloadError {}
 
-/// Returns the title of the requested video using the youtube-dl binary.
-async fn video_title(video_id: &str) -> Result<String> {
+/// A request to download content from a single video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DownloadRequest {
+    /// The ID of the video to download.
+    pub video_id: String,
+    /// The content type to download.
+    pub content_type: ContentType,
+    /// The directory to save the downloaded content to.
+    pub output_directory: PathBuf,
+    /// The 1-based index of the video within its playlist, if it was selected
+    /// from one.
+    #[serde(default)]
+    pub playlist_index: Option<usize>,
+    /// The preferred video quality.
+    #[serde(default)]
+    pub format_preference: FormatPreference,
+    /// An explicit youtube-dl format selector, overriding the preferred
+    /// quality.
+    #[serde(default)]
+    pub format_selector: Option<String>,
+    /// The preferred audio format and quality.
+    #[serde(default)]
+    pub audio_preference: AudioPreference,
+    /// The template from which the output file's path is rendered.
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+    /// How to handle an existing file at the output path.
+    #[serde(default)]
+    pub collision_policy: CollisionPolicy,
+    /// Whether to download the content even if it is recorded in the download
+    /// archive.
+    #[serde(default)]
+    pub ignore_archive: bool,
+    /// How steps of the download that fail transiently are retried.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// The time range of the video to keep. This is ignored for thumbnails.
+    #[serde(default)]
+    pub clip: ClipRange,
+    /// The subtitle tracks to download, either as the requested content or
+    /// along with a video.
+    #[serde(default)]
+    pub subtitle_tracks: Vec<SubtitleTrack>,
+    /// How downloaded subtitles are saved.
+    #[serde(default)]
+    pub subtitle_preference: SubtitlePreference,
+    /// Whether to split audio or video into one file per chapter, if the
+    /// video has chapters. Subtitles of a split video are saved alongside
+    /// the chapters rather than embedded.
+    #[serde(default)]
+    pub split_chapters: bool,
+    /// The extractor that fetches the video's information and streams.
+    #[serde(default)]
+    pub extractor: ExtractorKind,
+}
+
+/// Returns the default filename template, for requests persisted before
+/// templates were configurable.
+fn default_filename_template() -> String {
+    DEFAULT_FILENAME_TEMPLATE.to_owned()
+}
+
+impl DownloadRequest {
+    /// Creates a request to download content from a single video.
+    pub fn new(video_id: String, content_type: ContentType, output_directory: PathBuf) -> Self {
+        Self {
+            video_id,
+            content_type,
+            output_directory,
+            playlist_index: None,
+            format_preference: FormatPreference::default(),
+            format_selector: None,
+            audio_preference: AudioPreference::default(),
+            filename_template: default_filename_template(),
+            collision_policy: CollisionPolicy::default(),
+            ignore_archive: false,
+            retry_policy: RetryPolicy::default(),
+            clip: ClipRange::default(),
+            subtitle_tracks: Vec::new(),
+            subtitle_preference: SubtitlePreference::default(),
+            split_chapters: false,
+            extractor: ExtractorKind::default(),
+        }
+    }
+
+    /// Returns the label of the time range the request keeps, or `None` if
+    /// it keeps the whole video or is for a thumbnail.
+    fn clip_label(&self) -> Option<String> {
+        match self.content_type {
+            ContentType::Thumbnail => None,
+            ContentType::Audio | ContentType::Video | ContentType::Subtitles => self.clip.label(),
+        }
+    }
+
+    /// Returns the key identifying the request's content and format in the
+    /// download archive.
+    pub fn archive_key(&self) -> ArchiveKey {
+        let format = match self.content_type {
+            ContentType::Thumbnail => "png".to_owned(),
+            ContentType::Audio => self.audio_preference.format.extension().to_owned(),
+            ContentType::Video => self
+                .format_selector
+                .clone()
+                .unwrap_or_else(|| self.format_preference.format_selector()),
+            ContentType::Subtitles => format!(
+                "{} {}",
+                self.subtitle_preference.format.extension(),
+                self.subtitle_tracks
+                    .iter()
+                    .map(SubtitleTrack::file_label)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        };
+
+        let format = match self.clip_label() {
+            Some(label) => format!("{} {}", format, label),
+            None => format,
+        };
+        let format = match self.content_type {
+            ContentType::Audio | ContentType::Video if self.split_chapters => {
+                format!("{} chapters", format)
+            }
+            _ => format,
+        };
+
+        ArchiveKey {
+            video_id: self.video_id.clone(),
+            content_type: self.content_type,
+            format,
+        }
+    }
+}
+
+/// Where the audio of an audio download was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioSource {
+    /// An audio-only stream of the video.
+    AudioStream,
+    /// The full video, because no audio-only stream was available.
+    FullVideo,
+}
+
+/// The strategy used to produce an audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AudioStrategy {
+    /// Where the audio was taken from.
+    pub source: AudioSource,
+    /// Whether the audio was copied into the output file without
+    /// re-encoding.
+    pub stream_copied: bool,
+}
+
+impl Display for AudioStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self.source {
+            AudioSource::AudioStream => "audio-only stream",
+            AudioSource::FullVideo => "full video",
+        };
+        let conversion = if self.stream_copied {
+            "copied"
+        } else {
+            "transcoded"
+        };
+
+        write!(f, "{} {}", conversion, source)
+    }
+}
+
+/// The result of a successful download.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DownloadOutcome {
+    /// The path of the downloaded file.
+    pub path: PathBuf,
+    /// The strategy used to produce the file, for audio downloads.
+    pub audio_strategy: Option<AudioStrategy>,
+    /// How an existing file at the output path was handled, if there was
+    /// one.
+    pub collision: Option<CollisionDecision>,
+    /// Whether the download was skipped because it is recorded in the
+    /// download archive.
+    pub archived: bool,
+    /// The paths of further subtitle files saved along with the file at
+    /// `path`.
+    pub subtitle_paths: Vec<PathBuf>,
+    /// The paths of the files of the chapters after the first, if the
+    /// download was split into chapters. The first chapter's file is at
+    /// `path`.
+    pub chapter_paths: Vec<PathBuf>,
+    /// Problems that did not stop the download, such as cover art that
+    /// could not be embedded.
+    pub warnings: Vec<String>,
+}
+
+impl DownloadOutcome {
+    /// Creates the outcome of a download that produced a file directly.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            audio_strategy: None,
+            collision: None,
+            archived: false,
+            subtitle_paths: Vec::new(),
+            chapter_paths: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Creates the outcome of a download that was skipped because the file
+    /// at `path` is already of the same video.
+    pub fn skipped(path: PathBuf) -> Self {
+        Self {
+            collision: Some(CollisionDecision::Skipped),
+            ..Self::new(path)
+        }
+    }
+}
+
+/// The captured output of a finished command.
+pub struct CommandOutput {
+    /// The exit status of the process.
+    pub status: ExitStatus,
+    /// Everything the process wrote to `stdout`.
+    pub stdout: String,
+    /// Everything the process wrote to `stderr`.
+    pub stderr: String,
+}
+
+/// Kills a child process along with any processes it has spawned, such as
+/// the ffmpeg instances youtube-dl launches for post-processing.
+async fn kill_process_tree(child: &mut Child) -> Result<()> {
+    if let Some(pid) = child.id() {
+        #[cfg(unix)]
+        {
+            use nix::errno::Errno;
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+
+            // The child was spawned as the leader of its own process group,
+            // so signalling the group reaches all of its descendants. The
+            // group no longer existing means there is nothing left to kill.
+            match killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+                Ok(()) | Err(Errno::ESRCH) => {}
+                Err(err) => return Err(std::io::Error::from(err).into()),
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            let mut cmd = Command::new("taskkill");
+            cmd.arg("/F").arg("/T").arg("/PID").arg(pid.to_string());
+            cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+            // taskkill fails if the process exited in the meantime, so only
+            // failing to run it is an error.
+            cmd.status().await?;
+        }
+    }
+
+    child.kill().await?;
+    Ok(())
+}
+
+/// Runs a command to completion, calling `on_line` with each line it writes to
+/// either `stdout` or `stderr` as soon as the line is written. If cancellation
+/// is requested, the process tree is killed and a `Cancelled` error is
+/// returned.
+pub async fn run_command<F>(
+    cmd: &mut Command,
+    cancel: &CancelHandle,
+    on_line: F,
+) -> Result<CommandOutput>
+where
+    F: FnMut(&str),
+{
+    cancel.check()?;
+
+    #[cfg(unix)]
+    {
+        cmd.process_group(0);
+    }
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                let program = cmd.as_std().get_program().to_string_lossy().into_owned();
+                DownloadError::missing_binary(&program).into()
+            }
+            _ => anyhow::Error::from(err),
+        })?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let on_line = RefCell::new(on_line);
+    let read_output = async {
+        let (stdout_res, stderr_res) = tokio::join!(
+            read_lines(stdout, |line| (on_line.borrow_mut())(line)),
+            read_lines(stderr, |line| (on_line.borrow_mut())(line)),
+        );
+        Ok::<_, std::io::Error>((stdout_res?, stderr_res?))
+    };
+
+    tokio::select! {
+        res = read_output => {
+            let (stdout, stderr) = res?;
+            let status = child.wait().await?;
+            Ok(CommandOutput { status, stdout, stderr })
+        }
+        () = cancel.cancelled() => {
+            kill_process_tree(&mut child).await?;
+            Err(Cancelled.into())
+        }
+    }
+}
+
+/// Removes the partial files youtube-dl and ffmpeg leave behind while writing
+/// `output_path`, such as `.part` and `.ytdl` files, along with the copy left
+/// by an interrupted move from a staging directory. The output file itself is
+/// only removed if `remove_output` is set, so that a file which existed before
+/// the download began is never deleted.
+async fn remove_partial_files(output_path: &Path, remove_output: bool) {
+    if remove_output {
+        let _ = fs::remove_file(output_path).await;
+    }
+
+    let _ = fs::remove_file(staged_copy_path(output_path)).await;
+
+    let (Some(parent), Some(file_name)) = (output_path.parent(), output_path.file_name()) else {
+        return;
+    };
+    let partial_prefix = format!("{}.", file_name.to_string_lossy());
+
+    if let Ok(mut entries) = fs::read_dir(parent).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&partial_prefix)
+            {
+                let _ = fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+}
+
+/// Removes partial output files if `res` indicates that the step writing
+/// `output_path` was cancelled.
+async fn clean_up_if_cancelled<T>(
+    res: Result<T>,
+    output_path: &Path,
+    preexisting: bool,
+) -> Result<T> {
+    if matches!(&res, Err(err) if err.is::<Cancelled>()) {
+        remove_partial_files(output_path, !preexisting).await;
+    }
+
+    res
+}
+
+/// Runs a step of a download, retrying it under the request's retry policy if
+/// it fails transiently. Pending retries are reported as progress updates in
+/// `phase`.
+async fn retry_step<T, F, Fut>(
+    request: &DownloadRequest,
+    phase: DownloadPhase,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+    operation: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    retry(
+        &request.retry_policy,
+        Some(cancel),
+        |status| {
+            let _ = progress.send(DownloadProgress {
+                retry: Some(status),
+                ..DownloadProgress::new(phase)
+            });
+        },
+        operation,
+    )
+    .await
+}
+
+/// Fetches the info JSON of the requested video using a youtube-dl
+/// compatible extractor.
+pub async fn fetch_video_info(
+    cli: &CommandLineExtractor,
+    video_id: &str,
+    cancel: &CancelHandle,
+) -> Result<VideoInfo> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
 
-    let mut cmd = Command::new(YOUTUBE_DL_BINARY_NAME);
-    cmd.arg("--get-title").arg(video_id);
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("--dump-json").arg(video_id);
 
     #[cfg(windows)]
     {
         cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
     }
 
-    let res = cmd.current_dir(here).output().await?;
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await?;
 
     if res.status.success() {
-        Ok(String::from_utf8(res.stdout)?.trim().to_owned())
+        Ok(serde_json::from_str(&res.stdout)?)
     } else {
-        Err(DownloadError {
-            description: "failed to fetch video title".to_owned(),
-            output: String::from_utf8_lossy(&res.stderr).into_owned(),
-        }
-        .into())
+        Err(DownloadError::new("failed to fetch video info", res.stderr).into())
+    }
+}
+
+/// Returns the path of the output file for a request, rendering the
+/// request's filename template. Any directories introduced by the template are
+/// created.
+async fn output_path(
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    extension: &str,
+) -> Result<PathBuf> {
+    let relative_path = render_template(
+        &request.filename_template,
+        &TemplateValues {
+            info,
+            playlist_index: request.playlist_index,
+            extension,
+        },
+    )?;
+    let mut output_path = request.output_directory.join(relative_path);
+
+    // Clips are named apart from the full video, so that neither replaces
+    // the other.
+    if let Some(label) = request.clip_label() {
+        let stem = output_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        output_path.set_file_name(format!("{} [{}].{}", stem, label, extension));
+    }
+
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).await?;
     }
+
+    Ok(output_path)
+}
+
+/// Escapes a path for use as a youtube-dl output template, in which `%`
+/// introduces a field.
+fn youtube_dl_output_template(path: &Path) -> String {
+    path.to_string_lossy().replace('%', "%%")
 }
 
-/// Returns the title of the requested video, but with special characters
-/// replaced with underscores.
-async fn filename_video_title(video_id: &str) -> Result<String> {
-    video_title(video_id).await.map(|title| {
-        title
-            .chars()
-            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
-            .collect()
+/// Downloads and decodes the thumbnail of a video as part of the given phase
+/// of a download.
+async fn fetch_thumbnail_image(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    phase: DownloadPhase,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DynamicImage> {
+    let Some(video_thumbnail_url) = info.thumbnail.as_deref() else {
+        return Err(DownloadError::new("video has no thumbnail", String::new()).into());
+    };
+
+    let bytes = retry_step(request, phase, progress, cancel, || async {
+        tokio::select! {
+            bytes = extractor.fetch_thumbnail(video_thumbnail_url) => bytes,
+            () = cancel.cancelled() => Err(Cancelled.into()),
+        }
+    })
+    .await?;
+    let img = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .decode()?;
+    cancel.check()?;
+    Ok(img)
+}
+
+/// Downloads the thumbnail of the requested video.
+async fn download_thumbnail(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    let output_path = output_path(request, info, "png").await?;
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+    let img = fetch_thumbnail_image(
+        extractor,
+        request,
+        info,
+        DownloadPhase::Downloading,
+        progress,
+        cancel,
+    )
+    .await?;
+
+    // Thumbnails carry no tags, so an existing thumbnail is identified by
+    // its content.
+    let existing_path = output_path.clone();
+    let target = resolve_collision(request, output_path, async {
+        let existing = ImageReader::open(&existing_path)
+            .ok()
+            .and_then(|reader| reader.with_guessed_format().ok())
+            .and_then(|reader| reader.decode().ok());
+        Ok(existing.is_some_and(|existing| existing.to_rgba8() == img.to_rgba8()))
+    })
+    .await?;
+    let (output_path, collision) = match target {
+        OutputTarget::Write(path, collision) => (path, collision),
+        OutputTarget::Skip(path) => return Ok(DownloadOutcome::skipped(path)),
+    };
+
+    let staging_dir = TempDir::new()?;
+    let staged_path = staging_dir.path().join("thumbnail.png");
+    img.save_with_format(&staged_path, image::ImageFormat::Png)?;
+    move_staged_file(&staged_path, &output_path).await?;
+
+    Ok(DownloadOutcome {
+        collision,
+        ..DownloadOutcome::new(output_path)
     })
 }
 
-/// Downloads the requested video using the youtube-dl binary.
-async fn download_thumbnail(video_id: &str, output_directory: &Path) -> Result<PathBuf> {
+/// Writes the video's metadata tags and the given chapter markers into a
+/// downloaded file using the ffmpeg binary, along with a cover image if one
+/// is given. The streams are copied into a new file, which then replaces the
+/// original.
+async fn tag_file(
+    path: &Path,
+    info: &VideoInfo,
+    chapters: &[Chapter],
+    cover_path: Option<&Path>,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<()> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
-    let video_name = filename_video_title(video_id).await?;
-    let output_path = output_directory.join(format!("{}.png", video_name));
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // The name begins with the output file's name, so that it is removed
+    // along with other partial files if the download is cancelled.
+    let tagged_path = path.with_file_name(format!(
+        "{}.tagged.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        extension
+    ));
+    let chapters_path = path.with_file_name(format!(
+        "{}.chapters.txt",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y").arg("-i").arg(path);
+
+    if let Some(cover_path) = cover_path {
+        cmd.arg("-i").arg(cover_path);
+    }
 
-    let mut cmd = Command::new(YOUTUBE_DL_BINARY_NAME);
-    cmd.arg("--get-thumbnail").arg(video_id);
+    // The chapters are read from a metadata file given as the last input.
+    if !chapters.is_empty() {
+        fs::write(&chapters_path, chapter_metadata(chapters)).await?;
+        cmd.arg("-f")
+            .arg("ffmetadata")
+            .arg("-i")
+            .arg(&chapters_path);
+    }
+
+    match cover_path {
+        Some(_) => {
+            cmd.arg("-map")
+                .arg("0:a")
+                .arg("-map")
+                .arg("1:v")
+                .arg("-disposition:v")
+                .arg("attached_pic");
+        }
+        None => {
+            cmd.arg("-map").arg("0");
+        }
+    }
+
+    if !chapters.is_empty() {
+        let chapters_input = if cover_path.is_some() { 2 } else { 1 };
+        cmd.arg("-map_chapters").arg(chapters_input.to_string());
+    }
+
+    cmd.arg("-c")
+        .arg("copy")
+        .args(metadata_args(info, &extension))
+        .arg(&tagged_path);
 
     #[cfg(windows)]
     {
         cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
     }
 
-    let res = cmd.current_dir(here).output().await?;
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Tagging));
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await;
+    let _ = fs::remove_file(&chapters_path).await;
+    let res = clean_up_if_cancelled(res, &tagged_path, false).await?;
 
     if res.status.success() {
-        let video_thumbnail_url = String::from_utf8(res.stdout)?.trim().to_owned();
-        let bytes = reqwest::get(video_thumbnail_url).await?.bytes().await?;
-        let img = ImageReader::new(Cursor::new(bytes))
-            .with_guessed_format()?
-            .decode()?;
-        img.save_with_format(&output_path, image::ImageFormat::Png)?;
-        Ok(output_path)
+        fs::rename(&tagged_path, path).await?;
+        Ok(())
     } else {
-        Err(DownloadError {
-            description: "failed to fetch video thumbnail URL".to_owned(),
-            output: String::from_utf8_lossy(&res.stderr).into_owned(),
+        let _ = fs::remove_file(&tagged_path).await;
+        Err(DownloadError::ffmpeg("failed to write metadata tags", res.stderr).into())
+    }
+}
+
+/// Writes the video's metadata tags and chapter markers into an audio file,
+/// embedding the video's thumbnail as cover art if the audio format supports
+/// it. Cover art is optional, so if the thumbnail cannot be fetched or
+/// converted the file is tagged without it, and the reason is returned as a
+/// warning.
+async fn tag_audio_file(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    path: &Path,
+    info: &VideoInfo,
+    chapters: &[Chapter],
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<Option<String>> {
+    if !request.audio_preference.format.supports_cover_art() || info.thumbnail.is_none() {
+        tag_file(path, info, chapters, None, progress, cancel).await?;
+        return Ok(None);
+    }
+
+    let cover_dir = TempDir::new()?;
+    let cover_path = cover_dir.path().join("cover.jpg");
+    let cover = async {
+        let img = fetch_thumbnail_image(
+            extractor,
+            request,
+            info,
+            DownloadPhase::Tagging,
+            progress,
+            cancel,
+        )
+        .await?;
+        img.to_rgb8()
+            .save_with_format(&cover_path, image::ImageFormat::Jpeg)?;
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    match cover {
+        Ok(()) => {
+            tag_file(path, info, chapters, Some(&cover_path), progress, cancel).await?;
+            Ok(None)
+        }
+        Err(err) if err.is::<Cancelled>() => Err(err),
+        Err(err) => {
+            tag_file(path, info, chapters, None, progress, cancel).await?;
+            Ok(Some(format!("cover art was not embedded: {}", err)))
+        }
+    }
+}
+
+/// Returns the description ffmpeg prints of a media file's metadata and
+/// streams.
+async fn probe_media(path: &Path, cancel: &CancelHandle) -> Result<String> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-hide_banner").arg("-i").arg(path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    // Without an output file ffmpeg exits with an error after printing the
+    // stream information, so the exit status is not checked.
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await?;
+    Ok(res.stderr)
+}
+
+/// Returns the codec of the first audio stream in a media file, as reported
+/// by ffmpeg, or `None` if the file has no audio stream.
+async fn source_audio_codec(path: &Path, cancel: &CancelHandle) -> Result<Option<String>> {
+    let description = probe_media(path, cancel).await?;
+
+    Ok(description.lines().find_map(|line| {
+        let (_, audio) = line.trim().split_once(": Audio: ")?;
+        audio.split([' ', ',']).next().map(ToOwned::to_owned)
+    }))
+}
+
+/// Returns the video ID tagged in a downloaded media file, if it has one.
+async fn tagged_video_id(path: &Path, cancel: &CancelHandle) -> Result<Option<String>> {
+    let description = probe_media(path, cancel).await?;
+
+    Ok(description.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("video_id")
+            .then(|| value.trim().to_owned())
+    }))
+}
+
+/// Where to write a download's output, once any collision with an existing
+/// file has been resolved.
+enum OutputTarget {
+    /// Write the output to the given path, having handled an existing file as
+    /// described.
+    Write(PathBuf, Option<CollisionDecision>),
+    /// Skip the download, since the file at the given path is of the same
+    /// video.
+    Skip(PathBuf),
+}
+
+/// Resolves a collision between a download's output path and an existing
+/// file according to the request's collision policy. `is_same_video` is only
+/// awaited if the policy needs to know whether the existing file is of the
+/// video being downloaded. If the policy is to prompt, a `FileCollision` error
+/// is returned.
+async fn resolve_collision<F>(
+    request: &DownloadRequest,
+    path: PathBuf,
+    is_same_video: F,
+) -> Result<OutputTarget>
+where
+    F: Future<Output = Result<bool>>,
+{
+    if !path.exists() {
+        return Ok(OutputTarget::Write(path, None));
+    }
+
+    match request.collision_policy {
+        // Downloads are assembled in a staging directory, and the existing
+        // file is only replaced once the finished file is moved into place,
+        // so it is kept if the download fails.
+        CollisionPolicy::Overwrite => Ok(OutputTarget::Write(
+            path,
+            Some(CollisionDecision::Overwritten),
+        )),
+        CollisionPolicy::SkipIfSame if is_same_video.await? => Ok(OutputTarget::Skip(path)),
+        CollisionPolicy::SkipIfSame | CollisionPolicy::Rename => Ok(OutputTarget::Write(
+            suffixed_path(&path),
+            Some(CollisionDecision::Renamed),
+        )),
+        CollisionPolicy::Prompt => Err(FileCollision { path }.into()),
+    }
+}
+
+/// Resolves a collision between a media download's output path and an
+/// existing file, identifying the existing file by its video ID tag.
+async fn resolve_media_collision(
+    request: &DownloadRequest,
+    path: PathBuf,
+    cancel: &CancelHandle,
+) -> Result<OutputTarget> {
+    let existing_path = path.clone();
+    resolve_collision(request, path, async {
+        Ok(tagged_video_id(&existing_path, cancel).await?.as_deref() == Some(&request.video_id))
+    })
+    .await
+}
+
+/// Returns a youtube-dl format selector for the best audio-only stream,
+/// preferring one whose codec can be copied into `format` without
+/// re-encoding.
+fn audio_stream_selector(format: AudioFormat) -> &'static str {
+    match format {
+        AudioFormat::M4a => "bestaudio[acodec^=mp4a]/bestaudio",
+        AudioFormat::Opus => "bestaudio[acodec=opus]/bestaudio",
+        AudioFormat::Ogg => "bestaudio[acodec=vorbis]/bestaudio",
+        AudioFormat::Mp3 | AudioFormat::Flac | AudioFormat::Wav => "bestaudio",
+    }
+}
+
+/// Downloads only the audio stream of the requested video into `directory`
+/// using a youtube-dl compatible extractor, resuming a partial download left
+/// there by an earlier attempt. Returns `None` if the video has no audio-only
+/// stream.
+pub async fn download_audio_stream(
+    cli: &CommandLineExtractor,
+    request: &DownloadRequest,
+    directory: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<Option<PathBuf>> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    // The extension of the stream is not known in advance, so the extractor
+    // is left to choose it.
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("--newline")
+        .arg("--continue")
+        .arg("--format")
+        .arg(audio_stream_selector(request.audio_preference.format))
+        .arg("--output")
+        .arg(directory.join("audio.%(ext)s"))
+        .arg(&request.video_id);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+    let res = run_command(cmd.current_dir(here), cancel, |line| {
+        if let Some(update) = parse_youtube_dl_progress(line) {
+            let _ = progress.send(update);
+        }
+    })
+    .await?;
+
+    if !res.status.success() {
+        return if cli.format_unavailable(&res.stderr) {
+            Ok(None)
+        } else {
+            Err(DownloadError::new("failed to download audio stream", res.stderr).into())
+        };
+    }
+
+    let mut entries = fs::read_dir(directory).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        if file_name.starts_with("audio.")
+            && !file_name.ends_with(".part")
+            && !file_name.ends_with(".ytdl")
+        {
+            return Ok(Some(entry.path()));
         }
-        .into())
     }
+
+    Err(DownloadError::new("downloaded audio stream not found", res.stderr).into())
 }
 
-/// Downloads the requested video using the youtube-dl binary.
-async fn download_audio(video_id: &str, output_directory: &Path) -> Result<PathBuf> {
+/// Converts the audio of a media file into the preferred audio format using
+/// the ffmpeg binary, keeping only the time range `clip`. Returns whether the
+/// audio stream was copied without re-encoding.
+async fn convert_audio(
+    source_path: &Path,
+    output_path: &Path,
+    preference: &AudioPreference,
+    clip: &ClipRange,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<bool> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
-    let temp_video_dir = TempDir::new()?;
-    let video_path = download_video(video_id, temp_video_dir.path()).await?;
-    let video_name = video_path
-        .with_extension("mp3")
-        .components()
-        .last()
-        .unwrap()
-        .as_os_str()
-        .to_string_lossy()
-        .into_owned();
-    let output_path = output_directory.join(video_name);
-
-    let mut cmd = Command::new(FFMPEG_BINARY_NAME);
-    cmd.arg("-i").arg(&video_path).arg(&output_path);
+    let preexisting = output_path.exists();
+
+    // Re-encoding lossy audio into the same codec only loses quality, so the
+    // audio stream is copied as-is when it is already in the target codec,
+    // unless an accurately cut clip is requested.
+    let source_codec = source_audio_codec(source_path, cancel).await?;
+    let stream_copied = source_codec.as_deref() == Some(preference.format.codec_name())
+        && !(clip.is_clipped() && clip.mode == ClipMode::Accurate);
+    let encoder_args = if stream_copied {
+        vec!["-c:a".to_owned(), "copy".to_owned()]
+    } else {
+        preference.encoder_args()
+    };
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y")
+        .args(clip.input_args())
+        .arg("-i")
+        .arg(source_path)
+        .args(clip.output_args())
+        .arg("-vn")
+        .args(encoder_args)
+        .arg(output_path);
 
     #[cfg(windows)]
     {
         cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
     }
 
-    let res = cmd.current_dir(here).output().await?;
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Converting));
+    let mut parser = FfmpegProgressParser::new(DownloadPhase::Converting, clip);
+    let res = run_command(cmd.current_dir(here), cancel, |line| {
+        if let Some(update) = parser.parse_line(line) {
+            let _ = progress.send(update);
+        }
+    })
+    .await;
+    let res = clean_up_if_cancelled(res, output_path, preexisting).await?;
 
     if res.status.success() {
-        Ok(output_path)
+        Ok(stream_copied)
     } else {
-        Err(DownloadError {
-            description: "failed to convert video to audio file".to_owned(),
-            output: String::from_utf8_lossy(&res.stderr).into_owned(),
+        Err(DownloadError::ffmpeg("failed to convert audio file", res.stderr).into())
+    }
+}
+
+/// Downloads the audio of the requested video using the given extractor.
+/// Only the audio stream is downloaded when the video has one, falling back
+/// to downloading the full video and extracting its audio otherwise. The
+/// audio is downloaded, converted and tagged in `staging_dir` before being
+/// moved to the output path, or split into chapters if requested.
+async fn download_audio(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    staging_dir: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    let chapters = info.chapters_within(&request.clip);
+    let split = request.split_chapters && !chapters.is_empty();
+    let output_path =
+        output_path(request, info, request.audio_preference.format.extension()).await?;
+    // Collisions of split downloads are resolved for each chapter's file.
+    let (output_path, collision) = if split {
+        (output_path, None)
+    } else {
+        match resolve_media_collision(request, output_path, cancel).await? {
+            OutputTarget::Write(path, collision) => (path, collision),
+            OutputTarget::Skip(path) => return Ok(DownloadOutcome::skipped(path)),
+        }
+    };
+
+    let (source_path, source) = match retry_step(
+        request,
+        DownloadPhase::Downloading,
+        progress,
+        cancel,
+        || extractor.download_audio_stream(request, staging_dir, progress, cancel),
+    )
+    .await?
+    {
+        Some(stream_path) => (stream_path, AudioSource::AudioStream),
+        None => {
+            // Only the audio is kept, so the video quality preference is
+            // ignored in favour of the best single stream that contains
+            // audio.
+            let video_request = DownloadRequest {
+                format_selector: Some("best".to_owned()),
+                ..request.clone()
+            };
+            let video_path = staging_dir.join("video.mp4");
+            retry_step(
+                request,
+                DownloadPhase::Downloading,
+                progress,
+                cancel,
+                || extractor.download_video_file(&video_request, &video_path, progress, cancel),
+            )
+            .await?;
+            (video_path, AudioSource::FullVideo)
         }
-        .into())
+    };
+
+    let converted_path = staging_dir.join(format!(
+        "converted.{}",
+        request.audio_preference.format.extension()
+    ));
+    let stream_copied = convert_audio(
+        &source_path,
+        &converted_path,
+        &request.audio_preference,
+        &request.clip,
+        progress,
+        cancel,
+    )
+    .await?;
+    let warnings = tag_audio_file(
+        extractor,
+        request,
+        &converted_path,
+        info,
+        &chapters,
+        progress,
+        cancel,
+    )
+    .await?
+    .into_iter()
+    .collect::<Vec<_>>();
+    let audio_strategy = Some(AudioStrategy {
+        source,
+        stream_copied,
+    });
+
+    if split {
+        let outcome = split_chapters(
+            request,
+            info,
+            &converted_path,
+            &output_path,
+            staging_dir,
+            progress,
+            cancel,
+        )
+        .await?;
+
+        return Ok(DownloadOutcome {
+            audio_strategy,
+            warnings,
+            ..outcome
+        });
     }
+
+    move_staged_file(&converted_path, &output_path).await?;
+
+    Ok(DownloadOutcome {
+        audio_strategy,
+        collision,
+        warnings,
+        ..DownloadOutcome::new(output_path)
+    })
 }
 
-/// Downloads the requested video using the youtube-dl binary.
-async fn download_video(video_id: &str, output_directory: &Path) -> Result<PathBuf> {
+/// Downloads the requested video to `output_path` using a youtube-dl
+/// compatible extractor, resuming a partial download left there by an
+/// earlier attempt.
+pub async fn download_video_file(
+    cli: &CommandLineExtractor,
+    request: &DownloadRequest,
+    output_path: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<()> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
-    let video_name = filename_video_title(video_id).await?;
-    let output_path = output_directory.join(format!("{}.mp4", video_name));
 
-    let mut cmd = Command::new(YOUTUBE_DL_BINARY_NAME);
-    cmd.arg("--format")
+    let format_selector = request
+        .format_selector
+        .clone()
+        .unwrap_or_else(|| request.format_preference.format_selector());
+
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("--newline")
+        .arg("--continue")
+        .arg("--format")
+        .arg(format_selector)
+        .arg("--merge-output-format")
         .arg("mp4")
+        .arg("--ffmpeg-location")
+        .arg(ffmpeg_binary_path()?)
         .arg("--output")
-        .arg(&output_path)
-        .arg(video_id);
+        .arg(youtube_dl_output_template(output_path))
+        .arg(&request.video_id);
 
     #[cfg(windows)]
     {
         cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
     }
 
-    let res = cmd.current_dir(here).output().await?;
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+    let res = run_command(cmd.current_dir(here), cancel, |line| {
+        if let Some(update) = parse_youtube_dl_progress(line) {
+            let _ = progress.send(update);
+        }
+    })
+    .await?;
 
     if res.status.success() {
-        Ok(output_path)
+        Ok(())
     } else {
-        Err(DownloadError {
-            description: "failed to perform video download".to_owned(),
-            output: String::from_utf8_lossy(&res.stderr).into_owned(),
+        Err(DownloadError::new("failed to perform video download", res.stderr).into())
+    }
+}
+
+/// Cuts a time range from a video using the ffmpeg binary, either copying the
+/// streams or re-encoding them according to the range's clip mode.
+async fn clip_video(
+    source_path: &Path,
+    output_path: &Path,
+    clip: &ClipRange,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<()> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y")
+        .args(clip.input_args())
+        .arg("-i")
+        .arg(source_path)
+        .args(clip.output_args())
+        .arg("-map")
+        .arg("0")
+        .args(clip.mode.encoder_args());
+
+    if clip.mode == ClipMode::Fast {
+        // Copied streams begin at the keyframe before the start time, which
+        // would otherwise be given a negative timestamp.
+        cmd.arg("-avoid_negative_ts").arg("make_zero");
+    }
+
+    cmd.arg(output_path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Clipping));
+    let mut parser = FfmpegProgressParser::new(DownloadPhase::Clipping, clip);
+    let res = run_command(cmd.current_dir(here), cancel, |line| {
+        if let Some(update) = parser.parse_line(line) {
+            let _ = progress.send(update);
+        }
+    })
+    .await;
+    let res = clean_up_if_cancelled(res, output_path, false).await?;
+
+    if res.status.success() {
+        Ok(())
+    } else {
+        Err(DownloadError::ffmpeg("failed to clip video", res.stderr).into())
+    }
+}
+
+/// Returns the path of the file a chapter is saved to when the file at `path`
+/// is split into chapters. The chapters are saved in a directory named after
+/// the file, as `01 - Title.ext` and so on.
+fn chapter_path(path: &Path, chapter: &Chapter, index: usize) -> PathBuf {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = sanitize_file_name(&format!(
+        "{:02} - {}.{}",
+        index,
+        chapter.display_title(index),
+        extension
+    ));
+
+    path.with_extension("").join(file_name)
+}
+
+/// Cuts a chapter from a tagged file into its own file using the ffmpeg
+/// binary, tagging it with the chapter's title and track number. Audio is
+/// copied as-is, while video is cut according to the request's clip mode.
+async fn cut_chapter(
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    source_path: &Path,
+    output_path: &Path,
+    chapter: &Chapter,
+    (index, total): (usize, usize),
+    cancel: &CancelHandle,
+) -> Result<()> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let extension = output_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mode = match request.content_type {
+        ContentType::Video => request.clip.mode,
+        _ => ClipMode::Fast,
+    };
+    let clip = chapter.clip_range(mode);
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y")
+        .args(clip.input_args())
+        .arg("-i")
+        .arg(source_path)
+        .args(clip.output_args())
+        .arg("-map")
+        .arg("0")
+        .arg("-map_chapters")
+        .arg("-1")
+        .args(mode.encoder_args());
+
+    if mode == ClipMode::Fast {
+        cmd.arg("-avoid_negative_ts").arg("make_zero");
+    }
+
+    cmd.args(metadata_args(info, &extension))
+        .arg("-metadata")
+        .arg(format!("title={}", chapter.display_title(index)))
+        .arg("-metadata")
+        .arg(format!("album={}", info.title))
+        .arg("-metadata")
+        .arg(format!("track={}/{}", index, total))
+        .arg(output_path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await;
+    let res = clean_up_if_cancelled(res, output_path, false).await?;
+
+    if res.status.success() {
+        Ok(())
+    } else {
+        Err(DownloadError::ffmpeg("failed to split chapter", res.stderr).into())
+    }
+}
+
+/// Splits a tagged file in `staging_dir` into one file per chapter of the
+/// requested time range, moving each into the directory of chapters named
+/// after `output_path`. An existing file of a chapter is handled according to
+/// the request's collision policy.
+async fn split_chapters(
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    source_path: &Path,
+    output_path: &Path,
+    staging_dir: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    let chapters = info.chapters_within(&request.clip);
+    let total = chapters.len();
+    let extension = source_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut paths = Vec::new();
+    let mut collision = None;
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let index = i + 1;
+        let _ = progress.send(DownloadProgress {
+            percent: Some(i as f64 / total as f64 * 100.0),
+            ..DownloadProgress::new(DownloadPhase::Splitting)
+        });
+
+        let path = chapter_path(output_path, chapter, index);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        match resolve_media_collision(request, path, cancel).await? {
+            OutputTarget::Write(path, decision) => {
+                let staged_path = staging_dir.join(format!("chapter{}.{}", index, extension));
+                cut_chapter(
+                    request,
+                    info,
+                    source_path,
+                    &staged_path,
+                    chapter,
+                    (index, total),
+                    cancel,
+                )
+                .await?;
+                move_staged_file(&staged_path, &path).await?;
+                collision = collision.or(decision);
+                paths.push(path);
+            }
+            OutputTarget::Skip(path) => {
+                collision = collision.or(Some(CollisionDecision::Skipped));
+                paths.push(path);
+            }
+        }
+    }
+
+    let path = paths.remove(0);
+
+    Ok(DownloadOutcome {
+        collision,
+        chapter_paths: paths,
+        ..DownloadOutcome::new(path)
+    })
+}
+
+/// Returns the path of the file a subtitle track is saved to alongside the
+/// file at `path`, such as `Title.en.srt` alongside `Title.mp4`.
+fn subtitle_path(path: &Path, track: &SubtitleTrack, format: SubtitleFormat) -> PathBuf {
+    path.with_extension(format!("{}.{}", track.file_label(), format.extension()))
+}
+
+/// Downloads the requested subtitle tracks into `directory` using a
+/// youtube-dl compatible extractor, in the format YouTube serves them in.
+/// Returns the path of each track's file. If a track is not available, an
+/// error is returned.
+pub async fn download_subtitle_files(
+    cli: &CommandLineExtractor,
+    request: &DownloadRequest,
+    directory: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<Vec<(SubtitleTrack, PathBuf)>> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let mut files = Vec::new();
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+
+    // Extractors prefer uploaded tracks over automatic captions of the same
+    // language, so each kind is downloaded separately.
+    for automatic in [false, true] {
+        let languages = request
+            .subtitle_tracks
+            .iter()
+            .filter(|track| track.automatic == automatic)
+            .map(|track| track.language.as_str())
+            .collect::<Vec<_>>();
+
+        if languages.is_empty() {
+            continue;
+        }
+
+        let kind = if automatic { "auto" } else { "manual" };
+
+        let mut cmd = Command::new(cli.binary_path()?);
+        cmd.arg("--skip-download")
+            .args(cli.subtitle_args(automatic, &languages.join(",")))
+            .arg("--sub-format")
+            .arg("vtt/best")
+            .arg("--output")
+            .arg(directory.join(format!("{}.%(ext)s", kind)))
+            .arg(&request.video_id);
+
+        #[cfg(windows)]
+        {
+            cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+        }
+
+        let res = run_command(cmd.current_dir(here), cancel, |_| {}).await?;
+
+        if !res.status.success() {
+            return Err(DownloadError::new("failed to download subtitles", res.stderr).into());
+        }
+
+        for language in languages {
+            let prefix = format!("{}.{}.", kind, language);
+            let mut entries = fs::read_dir(directory).await?;
+            let mut path = None;
+
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    path = Some(entry.path());
+                    break;
+                }
+            }
+
+            let Some(path) = path else {
+                return Err(DownloadError::new(
+                    "subtitles not available",
+                    format!("the video has no {} subtitles in {}", kind, language),
+                )
+                .into());
+            };
+
+            files.push((
+                SubtitleTrack {
+                    language: language.to_owned(),
+                    automatic,
+                },
+                path,
+            ));
         }
-        .into())
     }
+
+    Ok(files)
 }
 
-/// Downloads the requested content using the youtube-dl binary.
+/// Converts a subtitle file into the format of `output_path`'s extension
+/// using the ffmpeg binary, keeping only the time range `clip`. A file that
+/// needs neither converting nor clipping is copied as-is.
+async fn convert_subtitles(
+    source_path: &Path,
+    output_path: &Path,
+    clip: &ClipRange,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<()> {
+    if source_path.extension() == output_path.extension() && !clip.is_clipped() {
+        fs::copy(source_path, output_path).await?;
+        return Ok(());
+    }
+
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let preexisting = output_path.exists();
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y")
+        .args(clip.input_args())
+        .arg("-i")
+        .arg(source_path)
+        .args(clip.output_args())
+        .arg(output_path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Converting));
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await;
+    let res = clean_up_if_cancelled(res, output_path, preexisting).await?;
+
+    if res.status.success() {
+        Ok(())
+    } else {
+        Err(DownloadError::ffmpeg("failed to convert subtitles", res.stderr).into())
+    }
+}
+
+/// Embeds subtitle files into a video as soft subtitle tracks using the
+/// ffmpeg binary. The streams are copied into a new file, which then
+/// replaces the original.
+async fn embed_subtitles(
+    path: &Path,
+    files: &[(SubtitleTrack, PathBuf)],
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<()> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // The name begins with the output file's name, so that it is removed
+    // along with other partial files if the download is cancelled.
+    let embedded_path = path.with_file_name(format!(
+        "{}.subtitled.{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        extension
+    ));
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y").arg("-i").arg(path);
+
+    for (_, file) in files {
+        cmd.arg("-i").arg(file);
+    }
+
+    cmd.arg("-map").arg("0");
+
+    for (index, (track, _)) in files.iter().enumerate() {
+        cmd.arg("-map")
+            .arg((index + 1).to_string())
+            .arg(format!("-metadata:s:s:{}", index))
+            .arg(format!("language={}", track.language));
+
+        if track.automatic {
+            cmd.arg(format!("-metadata:s:s:{}", index))
+                .arg(format!("title={} (auto-generated)", track.language));
+        }
+    }
+
+    // MPEG-4 containers only hold subtitles as timed text, while Matroska
+    // takes the text formats as they are.
+    let subtitle_codec = match extension.as_str() {
+        "mp4" | "m4v" | "mov" => "mov_text",
+        _ => "copy",
+    };
+
+    cmd.arg("-c")
+        .arg("copy")
+        .arg("-c:s")
+        .arg(subtitle_codec)
+        .arg(&embedded_path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Converting));
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await;
+    let res = clean_up_if_cancelled(res, &embedded_path, false).await?;
+
+    if res.status.success() {
+        fs::rename(&embedded_path, path).await?;
+        Ok(())
+    } else {
+        let _ = fs::remove_file(&embedded_path).await;
+        Err(DownloadError::ffmpeg("failed to embed subtitles", res.stderr).into())
+    }
+}
+
+/// Converts downloaded subtitle files to the requested format and clip range
+/// in `staging_dir`, then moves them alongside the file at `base_path`. Since
+/// subtitle files carry no tags, an existing file is identified by its
+/// content when resolving collisions. Returns the paths of the saved files
+/// and the first collision decision made.
+async fn save_subtitle_files(
+    request: &DownloadRequest,
+    files: &[(SubtitleTrack, PathBuf)],
+    base_path: &Path,
+    staging_dir: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<(Vec<PathBuf>, Option<CollisionDecision>)> {
+    let format = request.subtitle_preference.format;
+    let mut paths = Vec::new();
+    let mut collision = None;
+
+    for (track, source_path) in files {
+        let converted_path = subtitle_path(&staging_dir.join("converted"), track, format);
+        convert_subtitles(
+            source_path,
+            &converted_path,
+            &request.clip,
+            progress,
+            cancel,
+        )
+        .await?;
+
+        let output_path = subtitle_path(base_path, track, format);
+        let existing_path = output_path.clone();
+        let target = resolve_collision(request, output_path, async {
+            Ok(fs::read(&existing_path).await? == fs::read(&converted_path).await?)
+        })
+        .await?;
+
+        match target {
+            OutputTarget::Write(path, decision) => {
+                move_staged_file(&converted_path, &path).await?;
+                collision = collision.or(decision);
+                paths.push(path);
+            }
+            OutputTarget::Skip(path) => {
+                collision = collision.or(Some(CollisionDecision::Skipped));
+                paths.push(path);
+            }
+        }
+    }
+
+    Ok((paths, collision))
+}
+
+/// Downloads the requested subtitle tracks of a video, converting them to
+/// the preferred format and clipping them to the requested time range. The
+/// tracks are downloaded and converted in `staging_dir` before being moved to
+/// their output paths, which are derived from the filename template.
+async fn download_subtitles(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    staging_dir: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    if request.subtitle_tracks.is_empty() {
+        return Err(DownloadError::new("no subtitle tracks were chosen", String::new()).into());
+    }
+
+    let format = request.subtitle_preference.format;
+    let base_path = output_path(request, info, format.extension()).await?;
+    let files = retry_step(
+        request,
+        DownloadPhase::Downloading,
+        progress,
+        cancel,
+        || extractor.download_subtitle_files(request, staging_dir, progress, cancel),
+    )
+    .await?;
+    let (mut paths, collision) =
+        save_subtitle_files(request, &files, &base_path, staging_dir, progress, cancel).await?;
+    let path = paths.remove(0);
+
+    Ok(DownloadOutcome {
+        collision,
+        subtitle_paths: paths,
+        ..DownloadOutcome::new(path)
+    })
+}
+
+/// Downloads the requested video using the given extractor. The video is
+/// downloaded, given any requested subtitles, clipped to the requested time
+/// range, and tagged in `staging_dir` before being moved to the output path,
+/// or split into chapters if requested. Subtitles that are not embedded are
+/// saved alongside the video. Subtitles are never embedded into chapters, so
+/// those of a split video are saved alongside the chapters instead.
+async fn download_video(
+    extractor: &dyn Extractor,
+    request: &DownloadRequest,
+    info: &VideoInfo,
+    staging_dir: &Path,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    let chapters = info.chapters_within(&request.clip);
+    let split = request.split_chapters && !chapters.is_empty();
+    let output_path = output_path(request, info, "mp4").await?;
+    // Collisions of split downloads are resolved for each chapter's file.
+    let (output_path, collision) = if split {
+        (output_path, None)
+    } else {
+        match resolve_media_collision(request, output_path, cancel).await? {
+            OutputTarget::Write(path, collision) => (path, collision),
+            OutputTarget::Skip(path) => return Ok(DownloadOutcome::skipped(path)),
+        }
+    };
+    let staged_path = staging_dir.join("video.mp4");
+
+    retry_step(
+        request,
+        DownloadPhase::Downloading,
+        progress,
+        cancel,
+        || extractor.download_video_file(request, &staged_path, progress, cancel),
+    )
+    .await?;
+
+    let subtitle_files = if request.subtitle_tracks.is_empty() {
+        Vec::new()
+    } else {
+        retry_step(
+            request,
+            DownloadPhase::Downloading,
+            progress,
+            cancel,
+            || extractor.download_subtitle_files(request, staging_dir, progress, cancel),
+        )
+        .await?
+    };
+    // Chapters are cut without subtitle streams, so the subtitles of a split
+    // download are saved for the whole video alongside the chapter files.
+    let embed = request.subtitle_preference.embed && !split && !subtitle_files.is_empty();
+
+    // Subtitles are embedded before clipping, so that they are clipped along
+    // with the video.
+    if embed {
+        embed_subtitles(&staged_path, &subtitle_files, progress, cancel).await?;
+    }
+
+    let staged_path = if request.clip.is_clipped() {
+        let clipped_path = staging_dir.join("clipped.mp4");
+        clip_video(&staged_path, &clipped_path, &request.clip, progress, cancel).await?;
+        clipped_path
+    } else {
+        staged_path
+    };
+
+    tag_file(&staged_path, info, &chapters, None, progress, cancel).await?;
+
+    let outcome = if split {
+        split_chapters(
+            request,
+            info,
+            &staged_path,
+            &output_path,
+            staging_dir,
+            progress,
+            cancel,
+        )
+        .await?
+    } else {
+        move_staged_file(&staged_path, &output_path).await?;
+        DownloadOutcome {
+            collision,
+            ..DownloadOutcome::new(output_path.clone())
+        }
+    };
+
+    let mut subtitle_paths = Vec::new();
+    let mut warnings = Vec::new();
+
+    if !embed {
+        // The subtitles of a split download are named after the video within
+        // the directory of its chapters, such as `Title/Title.en.srt`.
+        let subtitle_base = if split {
+            output_path
+                .with_extension("")
+                .join(output_path.file_name().unwrap_or_default())
+        } else {
+            output_path.clone()
+        };
+
+        // Collisions with existing subtitle files are resolved under the same
+        // policy as the video, but only the video's decision is reported.
+        (subtitle_paths, _) = save_subtitle_files(
+            request,
+            &subtitle_files,
+            &subtitle_base,
+            staging_dir,
+            progress,
+            cancel,
+        )
+        .await?;
+
+        if split && request.subtitle_preference.embed && !subtitle_paths.is_empty() {
+            warnings.push(
+                "subtitles cannot be embedded into chapters, so they were saved alongside them"
+                    .to_owned(),
+            );
+        }
+    }
+
+    Ok(DownloadOutcome {
+        subtitle_paths,
+        warnings,
+        ..outcome
+    })
+}
+
+/// Downloads the requested content using the request's extractor. Progress
+/// updates are sent on `progress` as the download proceeds. If the download
+/// is cancelled through `cancel`, all spawned processes are killed, partial
+/// files are removed, and a `Cancelled` error is returned.
+///
+/// Audio and video are assembled in a staging directory that is kept if the
+/// download fails or is interrupted, so that running the same request again
+/// resumes from the partial files.
+///
+/// Downloads recorded in `archive` are skipped, unless the request ignores
+/// the archive. Successful downloads are recorded in it.
 pub async fn download(
-    video_id: &str,
-    content_type: ContentType,
-    output_directory: impl AsRef<Path>,
-) -> Result<PathBuf> {
-    let output_directory = output_directory.as_ref();
+    request: &DownloadRequest,
+    archive: &DownloadArchive,
+    progress: &ProgressSender,
+    cancel: &CancelHandle,
+) -> Result<DownloadOutcome> {
+    let _ = progress.send(DownloadProgress::new(DownloadPhase::Preparing));
+    let archive_key = request.archive_key();
+
+    if !request.ignore_archive {
+        if let Some(entry) = archive.get(&archive_key) {
+            return Ok(DownloadOutcome {
+                archived: true,
+                ..DownloadOutcome::new(entry.path)
+            });
+        }
+    }
+
+    let extractor = extractor(request.extractor);
+    let info = retry_step(request, DownloadPhase::Preparing, progress, cancel, || {
+        extractor.fetch_video_info(&request.video_id, cancel)
+    })
+    .await?;
+
+    let res = match request.content_type {
+        ContentType::Thumbnail => {
+            download_thumbnail(extractor, request, &info, progress, cancel).await
+        }
+        ContentType::Audio | ContentType::Video | ContentType::Subtitles => {
+            let staging_dir = create_staging_dir(request).await?;
+            let dir = staging_dir.path();
+            let res = match request.content_type {
+                ContentType::Audio => {
+                    download_audio(extractor, request, &info, dir, progress, cancel).await
+                }
+                ContentType::Video => {
+                    download_video(extractor, request, &info, dir, progress, cancel).await
+                }
+                _ => download_subtitles(extractor, request, &info, dir, progress, cancel).await,
+            };
+
+            // Partial files are only worth keeping if the download may be
+            // retried.
+            if !matches!(&res, Err(err) if !err.is::<Cancelled>()) {
+                let _ = staging_dir.remove().await;
+            }
+
+            res
+        }
+    };
+
+    let mut outcome = res?;
+
+    // The file is already in place, so failing to record it does not fail
+    // the download.
+    let recorded = archive
+        .record(ArchiveEntry {
+            key: archive_key,
+            title: info.title,
+            path: outcome.path.clone(),
+        })
+        .await;
+
+    if let Err(err) = recorded {
+        outcome.warnings.push(format!(
+            "the download was not recorded in the archive: {}",
+            err
+        ));
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{resolve_binary, Dependency};
+    use tokio::sync::mpsc::unbounded_channel;
+
+    /// The ID of the video downloaded through the fake extractor.
+    const VIDEO_ID: &str = "fakevideo01";
+
+    /// Creates a request to download content of the fake video into
+    /// `directory` using the fake extractor.
+    fn fake_request(content_type: ContentType, directory: &Path) -> DownloadRequest {
+        DownloadRequest {
+            extractor: ExtractorKind::Fake,
+            ..DownloadRequest::new(VIDEO_ID.to_owned(), content_type, directory.to_path_buf())
+        }
+    }
+
+    /// Creates a request to download the fake video's English subtitles into
+    /// `directory` as WebVTT, which needs no conversion with ffmpeg.
+    fn subtitles_request(directory: &Path) -> DownloadRequest {
+        DownloadRequest {
+            subtitle_tracks: vec![SubtitleTrack {
+                language: "en".to_owned(),
+                automatic: false,
+            }],
+            subtitle_preference: SubtitlePreference {
+                format: SubtitleFormat::Vtt,
+                embed: false,
+            },
+            ..fake_request(ContentType::Subtitles, directory)
+        }
+    }
+
+    /// Runs a download to completion.
+    async fn run(request: &DownloadRequest, archive: &DownloadArchive) -> Result<DownloadOutcome> {
+        let (progress, _progress_receiver) = unbounded_channel();
+        download(request, archive, &progress, &CancelHandle::new()).await
+    }
+
+    /// Resolves the ffmpeg binary the fake extractor generates media with,
+    /// failing the test if it is not found. Tests that need it are ignored
+    /// by default, and are run with `cargo test -- --include-ignored`.
+    async fn require_ffmpeg() {
+        let found = resolve_binary(Dependency::Ffmpeg, None).await.unwrap();
+        assert!(
+            found.is_some(),
+            "ffmpeg was not found, but is required to run this test"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires ffmpeg"]
+    async fn download_audio() {
+        require_ffmpeg().await;
+
+        let dir = TempDir::new().unwrap();
+        let request = fake_request(ContentType::Audio, dir.path());
+        let outcome = run(&request, &DownloadArchive::empty()).await.unwrap();
+
+        assert!(outcome.path.exists());
+        assert_eq!(
+            outcome.path.extension().unwrap(),
+            request.audio_preference.format.extension()
+        );
+        assert_eq!(
+            outcome.audio_strategy.map(|strategy| strategy.source),
+            Some(AudioSource::AudioStream)
+        );
+        assert_eq!(outcome.warnings, Vec::<String>::new());
+        assert_eq!(
+            tagged_video_id(&outcome.path, &CancelHandle::new())
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(VIDEO_ID)
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires ffmpeg"]
+    async fn download_video() {
+        require_ffmpeg().await;
+
+        let dir = TempDir::new().unwrap();
+        let request = DownloadRequest {
+            subtitle_tracks: vec![SubtitleTrack {
+                language: "en".to_owned(),
+                automatic: false,
+            }],
+            ..fake_request(ContentType::Video, dir.path())
+        };
+        let outcome = run(&request, &DownloadArchive::empty()).await.unwrap();
+
+        assert!(outcome.path.exists());
+        assert_eq!(outcome.path.extension().unwrap(), "mp4");
+        assert_eq!(outcome.subtitle_paths.len(), 1);
+        assert!(outcome.subtitle_paths[0].exists());
+        assert_eq!(
+            tagged_video_id(&outcome.path, &CancelHandle::new())
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(VIDEO_ID)
+        );
+
+        // Existing subtitle files are subject to the collision policy too.
+        fs::remove_file(&outcome.path).await.unwrap();
+        fs::write(&outcome.subtitle_paths[0], "edited")
+            .await
+            .unwrap();
+        let again = run(&request, &DownloadArchive::empty()).await.unwrap();
+        assert_eq!(again.path, outcome.path);
+        assert_eq!(again.subtitle_paths.len(), 1);
+        assert_ne!(again.subtitle_paths[0], outcome.subtitle_paths[0]);
+        assert_eq!(
+            fs::read_to_string(&outcome.subtitle_paths[0])
+                .await
+                .unwrap(),
+            "edited"
+        );
+    }
+
+    #[tokio::test]
+    async fn remove_partial_outputs() {
+        let dir = TempDir::new().unwrap();
+        let output_path = dir.path().join("Title.mp4");
+        let partial_paths = [
+            staged_copy_path(&output_path),
+            dir.path().join("Title.mp4.part"),
+            dir.path().join("Title.mp4.ytdl"),
+        ];
+        assert_eq!(partial_paths[0], dir.path().join(".Title.mp4.part"));
+
+        for path in [&output_path].into_iter().chain(&partial_paths) {
+            fs::write(path, "partial").await.unwrap();
+        }
+
+        remove_partial_files(&output_path, false).await;
+        assert!(output_path.exists());
+
+        for path in &partial_paths {
+            assert!(!path.exists(), "{}", path.display());
+        }
+    }
+
+    #[tokio::test]
+    async fn download_subtitles() {
+        let dir = TempDir::new().unwrap();
+        let request = DownloadRequest {
+            subtitle_tracks: vec![
+                SubtitleTrack {
+                    language: "en".to_owned(),
+                    automatic: false,
+                },
+                SubtitleTrack {
+                    language: "en".to_owned(),
+                    automatic: true,
+                },
+            ],
+            ..subtitles_request(dir.path())
+        };
+        let outcome = run(&request, &DownloadArchive::empty()).await.unwrap();
+
+        assert_eq!(outcome.subtitle_paths.len(), 1);
+        assert_ne!(outcome.path, outcome.subtitle_paths[0]);
+
+        for path in [&outcome.path, &outcome.subtitle_paths[0]] {
+            assert_eq!(path.extension().unwrap(), "vtt");
+            assert!(fs::read_to_string(path)
+                .await
+                .unwrap()
+                .starts_with("WEBVTT"));
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_collisions() {
+        let dir = TempDir::new().unwrap();
+        let archive = DownloadArchive::empty();
+        let request = DownloadRequest {
+            ignore_archive: true,
+            ..subtitles_request(dir.path())
+        };
+        let first = run(&request, &archive).await.unwrap();
+        assert_eq!(first.collision, None);
+
+        // The existing file has the same content, so it is kept.
+        let skipped = run(&request, &archive).await.unwrap();
+        assert_eq!(skipped.path, first.path);
+        assert_eq!(skipped.collision, Some(CollisionDecision::Skipped));
+
+        let renamed = run(
+            &DownloadRequest {
+                collision_policy: CollisionPolicy::Rename,
+                ..request.clone()
+            },
+            &archive,
+        )
+        .await
+        .unwrap();
+        assert_ne!(renamed.path, first.path);
+        assert_eq!(renamed.collision, Some(CollisionDecision::Renamed));
+        assert!(renamed.path.exists());
+
+        // An overwritten file is kept until its replacement is in place.
+        fs::write(&first.path, "old").await.unwrap();
+        let overwritten = run(
+            &DownloadRequest {
+                collision_policy: CollisionPolicy::Overwrite,
+                ..request.clone()
+            },
+            &archive,
+        )
+        .await
+        .unwrap();
+        assert_eq!(overwritten.path, first.path);
+        assert_eq!(overwritten.collision, Some(CollisionDecision::Overwritten));
+        assert_ne!(fs::read_to_string(&first.path).await.unwrap(), "old");
+
+        let err = run(
+            &DownloadRequest {
+                collision_policy: CollisionPolicy::Prompt,
+                ..request
+            },
+            &archive,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.is::<FileCollision>());
+    }
+
+    #[tokio::test]
+    async fn skip_archived_downloads() {
+        let dir = TempDir::new().unwrap();
+        let archive = DownloadArchive::empty();
+        let request = subtitles_request(dir.path());
+
+        let first = run(&request, &archive).await.unwrap();
+        assert!(!first.archived);
+        assert_eq!(
+            archive.get(&request.archive_key()).map(|entry| entry.path),
+            Some(first.path.clone())
+        );
+
+        // Archived downloads are skipped even if the file has been removed.
+        fs::remove_file(&first.path).await.unwrap();
+        let archived = run(&request, &archive).await.unwrap();
+        assert!(archived.archived);
+        assert_eq!(archived.path, first.path);
+        assert!(!first.path.exists());
+
+        let ignored = run(
+            &DownloadRequest {
+                ignore_archive: true,
+                ..request
+            },
+            &archive,
+        )
+        .await
+        .unwrap();
+        assert!(!ignored.archived);
+        assert!(ignored.path.exists());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires ffmpeg"]
+    async fn split_into_chapters() {
+        require_ffmpeg().await;
+
+        let dir = TempDir::new().unwrap();
+        let request = DownloadRequest {
+            split_chapters: true,
+            subtitle_tracks: vec![SubtitleTrack {
+                language: "en".to_owned(),
+                automatic: false,
+            }],
+            subtitle_preference: SubtitlePreference {
+                embed: true,
+                ..SubtitlePreference::default()
+            },
+            ..fake_request(ContentType::Video, dir.path())
+        };
+        let outcome = run(&request, &DownloadArchive::empty()).await.unwrap();
+
+        // The fake video has two chapters, saved in a directory named after
+        // the video along with the subtitles, which cannot be embedded.
+        let chapter_dir = outcome.path.parent().unwrap().to_path_buf();
+        assert_eq!(outcome.chapter_paths.len(), 1);
+        assert_eq!(outcome.subtitle_paths.len(), 1);
+        assert_eq!(outcome.warnings.len(), 1);
+
+        for path in [
+            &outcome.path,
+            &outcome.chapter_paths[0],
+            &outcome.subtitle_paths[0],
+        ] {
+            assert!(path.exists(), "{}", path.display());
+            assert_eq!(path.parent().unwrap(), chapter_dir);
+        }
 
-    match content_type {
-        ContentType::Thumbnail => download_thumbnail(video_id, output_directory).await,
-        ContentType::Audio => download_audio(video_id, output_directory).await,
-        ContentType::Video => download_video(video_id, output_directory).await,
+        assert!(outcome
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("01 - Beginning"));
+        assert!(outcome.chapter_paths[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("02 - End"));
     }
 }
@@ -0,0 +1,234 @@
+//! The extractors that fetch video information and download streams.
+
+use crate::services::{
+    binary_path, download_audio_stream, download_subtitle_files, download_video_file,
+    fetch_channel, fetch_formats, fetch_playlist, fetch_subtitles, fetch_video_info,
+    AvailableSubtitle, CancelHandle, ChannelFilter, ChannelRef, Dependency, DownloadRequest,
+    Playlist, ProgressSender, VideoFormat, VideoInfo,
+};
+use crate::types::*;
+use anyhow::Result;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use std::path::{Path, PathBuf};
+
+/// A program that extracts information about videos from YouTube and
+/// downloads their streams. The operations return boxed futures so that the
+/// extractor can be chosen when the application runs.
+pub trait Extractor {
+    /// Fetches the information of a video.
+    fn fetch_video_info<'a>(
+        &'a self,
+        video_id: &'a str,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<VideoInfo>>;
+
+    /// Fetches the image at a video's thumbnail URL.
+    fn fetch_thumbnail<'a>(&'a self, url: &'a str) -> LocalBoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+            Ok(bytes.to_vec())
+        }
+        .boxed_local()
+    }
+
+    /// Lists the streams available for a video, ordered from worst to best.
+    fn fetch_formats<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<VideoFormat>>>;
+
+    /// Lists the subtitle tracks available for a video. Tracks uploaded with
+    /// the video are listed first, followed by automatically generated
+    /// captions.
+    fn fetch_subtitles<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<AvailableSubtitle>>>;
+
+    /// Fetches the entries of a playlist.
+    fn fetch_playlist<'a>(&'a self, playlist_id: &'a str) -> LocalBoxFuture<'a, Result<Playlist>>;
+
+    /// Enumerates the uploads of a channel, applying the given filters. The
+    /// result is presented as a playlist of the channel's videos, newest
+    /// first.
+    fn fetch_channel<'a>(
+        &'a self,
+        channel: &'a ChannelRef,
+        filter: &'a ChannelFilter,
+    ) -> LocalBoxFuture<'a, Result<Playlist>>;
+
+    /// Downloads only the audio stream of the requested video into
+    /// `directory`, returning the path of the downloaded file. Returns `None`
+    /// if the video has no audio-only stream.
+    fn download_audio_stream<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Option<PathBuf>>>;
+
+    /// Downloads the requested video, with audio, to `output_path` as MP4.
+    fn download_video_file<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        output_path: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<()>>;
+
+    /// Downloads the requested subtitle tracks into `directory`, returning
+    /// the path of each track's file.
+    fn download_subtitle_files<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Vec<(SubtitleTrack, PathBuf)>>>;
+}
+
+/// youtube-dl or a fork of it that shares its command-line interface. The
+/// programs differ in the names of some flags and in the messages they
+/// print, which are recorded here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandLineExtractor {
+    /// The dependency whose binary is run.
+    dependency: Dependency,
+    /// The flag that writes the subtitle tracks uploaded with a video.
+    write_subtitles_flag: &'static str,
+    /// The flag that writes automatically generated caption tracks.
+    write_captions_flag: &'static str,
+    /// The flag that chooses the languages of the tracks to write.
+    subtitle_languages_flag: &'static str,
+    /// The message, in lowercase, printed when no stream matches a format
+    /// selector.
+    format_unavailable_message: &'static str,
+}
+
+/// The youtube-dl extractor.
+pub const YOUTUBE_DL_EXTRACTOR: CommandLineExtractor = CommandLineExtractor {
+    dependency: Dependency::YoutubeDl,
+    write_subtitles_flag: "--write-sub",
+    write_captions_flag: "--write-auto-sub",
+    subtitle_languages_flag: "--sub-lang",
+    format_unavailable_message: "requested format not available",
+};
+
+/// The yt-dlp extractor. yt-dlp pluralized the subtitle flags, keeping
+/// youtube-dl's names only as aliases, and reworded its error messages.
+pub const YT_DLP_EXTRACTOR: CommandLineExtractor = CommandLineExtractor {
+    dependency: Dependency::YtDlp,
+    write_subtitles_flag: "--write-subs",
+    write_captions_flag: "--write-auto-subs",
+    subtitle_languages_flag: "--sub-langs",
+    format_unavailable_message: "requested format is not available",
+};
+
+impl CommandLineExtractor {
+    /// Returns the path of the extractor's binary to run.
+    pub fn binary_path(&self) -> Result<PathBuf> {
+        binary_path(self.dependency)
+    }
+
+    /// Returns the arguments that write the subtitle tracks of the given
+    /// languages, which are either uploaded tracks or automatically generated
+    /// captions.
+    pub fn subtitle_args(&self, automatic: bool, languages: &str) -> [String; 3] {
+        let write_flag = if automatic {
+            self.write_captions_flag
+        } else {
+            self.write_subtitles_flag
+        };
+
+        [
+            write_flag.to_owned(),
+            self.subtitle_languages_flag.to_owned(),
+            languages.to_owned(),
+        ]
+    }
+
+    /// Checks if the output of a failed download reports that no stream
+    /// matched the format selector.
+    pub fn format_unavailable(&self, output: &str) -> bool {
+        output
+            .to_lowercase()
+            .contains(self.format_unavailable_message)
+    }
+}
+
+impl Extractor for CommandLineExtractor {
+    fn fetch_video_info<'a>(
+        &'a self,
+        video_id: &'a str,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<VideoInfo>> {
+        fetch_video_info(self, video_id, cancel).boxed_local()
+    }
+
+    fn fetch_formats<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<VideoFormat>>> {
+        fetch_formats(self, video_id).boxed_local()
+    }
+
+    fn fetch_subtitles<'a>(
+        &'a self,
+        video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<AvailableSubtitle>>> {
+        fetch_subtitles(self, video_id).boxed_local()
+    }
+
+    fn fetch_playlist<'a>(&'a self, playlist_id: &'a str) -> LocalBoxFuture<'a, Result<Playlist>> {
+        fetch_playlist(self, playlist_id).boxed_local()
+    }
+
+    fn fetch_channel<'a>(
+        &'a self,
+        channel: &'a ChannelRef,
+        filter: &'a ChannelFilter,
+    ) -> LocalBoxFuture<'a, Result<Playlist>> {
+        fetch_channel(self, channel, filter).boxed_local()
+    }
+
+    fn download_audio_stream<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Option<PathBuf>>> {
+        download_audio_stream(self, request, directory, progress, cancel).boxed_local()
+    }
+
+    fn download_video_file<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        output_path: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        download_video_file(self, request, output_path, progress, cancel).boxed_local()
+    }
+
+    fn download_subtitle_files<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Vec<(SubtitleTrack, PathBuf)>>> {
+        download_subtitle_files(self, request, directory, progress, cancel).boxed_local()
+    }
+}
+
+/// Returns the extractor of the given kind.
+pub fn extractor(kind: ExtractorKind) -> &'static dyn Extractor {
+    match kind {
+        ExtractorKind::YoutubeDl => &YOUTUBE_DL_EXTRACTOR,
+        ExtractorKind::YtDlp => &YT_DLP_EXTRACTOR,
+        #[cfg(test)]
+        ExtractorKind::Fake => &crate::services::FakeExtractor,
+    }
+}
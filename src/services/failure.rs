@@ -0,0 +1,398 @@
+//! Classification of download failures.
+
+use crate::services::DownloadError;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// The cause of a failed download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DownloadErrorKind {
+    /// The video does not exist or has been removed.
+    VideoUnavailable,
+    /// The video is private.
+    Private,
+    /// The video requires signing in to confirm the viewer's age.
+    AgeRestricted,
+    /// The video is not available in the current country.
+    GeoBlocked,
+    /// The video is only available to members of the channel.
+    MembersOnly,
+    /// The video is a live stream or premiere that has not started yet.
+    LiveNotStarted,
+    /// YouTube could not be reached.
+    Network,
+    /// ffmpeg failed to process the downloaded content.
+    Ffmpeg,
+    /// There is not enough disk space to save the download.
+    DiskFull,
+    /// A required binary could not be found.
+    MissingBinary,
+    /// The cause of the failure is not known.
+    Unknown,
+}
+
+/// Patterns in youtube-dl and ffmpeg output identifying each kind of failure,
+/// in the order they are checked. More specific kinds are checked first, as
+/// youtube-dl often reports a specific cause alongside a generic one.
+const ERROR_PATTERNS: [(DownloadErrorKind, &[&str]); 9] = [
+    (
+        DownloadErrorKind::DiskFull,
+        &[
+            "no space left on device",
+            "not enough space on the disk",
+            "disk quota exceeded",
+        ],
+    ),
+    (
+        DownloadErrorKind::Private,
+        &["private video", "this video is private"],
+    ),
+    (
+        DownloadErrorKind::MembersOnly,
+        &[
+            "members-only",
+            "members only",
+            "join this channel to get access",
+            "available to this channel's members",
+        ],
+    ),
+    (
+        DownloadErrorKind::AgeRestricted,
+        &[
+            "sign in to confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ],
+    ),
+    (
+        DownloadErrorKind::GeoBlocked,
+        &[
+            "not available in your country",
+            "made this video available in your country",
+            "geo restriction",
+            "geo-restricted",
+            "blocked it in your country",
+        ],
+    ),
+    (
+        DownloadErrorKind::LiveNotStarted,
+        &[
+            "premieres in",
+            "premiere will begin",
+            "live event will begin",
+            "this live stream has not started",
+        ],
+    ),
+    (
+        DownloadErrorKind::VideoUnavailable,
+        &[
+            "video unavailable",
+            "this video is unavailable",
+            "this video has been removed",
+            "video is no longer available",
+            "account associated with this video has been terminated",
+            "incomplete youtube id",
+        ],
+    ),
+    (
+        DownloadErrorKind::Ffmpeg,
+        &[
+            "ffmpeg not found",
+            "ffprobe/avprobe and ffmpeg/avconv not found",
+            "postprocessing:",
+            "conversion failed",
+            "error while decoding",
+            "invalid data found when processing input",
+        ],
+    ),
+    (
+        DownloadErrorKind::Network,
+        &[
+            "unable to download webpage",
+            "unable to download video data",
+            "urlopen error",
+            "http error 5",
+            "http error 429",
+            "connection reset",
+            "connection refused",
+            "connection aborted",
+            "timed out",
+            "temporary failure in name resolution",
+            "name or service not known",
+            "getaddrinfo failed",
+            "network is unreachable",
+            "no route to host",
+        ],
+    ),
+];
+
+impl DownloadErrorKind {
+    /// Classifies a failure from the output of the failed operation, or
+    /// returns `None` if the output matches no known cause.
+    pub fn classify(output: &str) -> Option<Self> {
+        let output = output.to_lowercase();
+
+        ERROR_PATTERNS.iter().find_map(|(kind, patterns)| {
+            patterns
+                .iter()
+                .any(|pattern| output.contains(pattern))
+                .then_some(*kind)
+        })
+    }
+
+    /// Gets a human-friendly explanation of the failure.
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::VideoUnavailable => "This video is unavailable. It may have been removed.",
+            Self::Private => "This video is private.",
+            Self::AgeRestricted => {
+                "This video is age-restricted and cannot be downloaded without signing in."
+            }
+            Self::GeoBlocked => "This video is not available in your country.",
+            Self::MembersOnly => "This video is only available to members of the channel.",
+            Self::LiveNotStarted => "This live stream or premiere has not started yet.",
+            Self::Network => "YouTube could not be reached. Check your internet connection.",
+            Self::Ffmpeg => "ffmpeg failed to process the downloaded content.",
+            Self::DiskFull => "There is not enough disk space to save the download.",
+            Self::MissingBinary => {
+                "A required program could not be found. Choose it in the dependency settings or \
+                 restart the application to reinstall it."
+            }
+            Self::Unknown => "An unexpected error occurred.",
+        }
+    }
+
+    /// Returns whether retrying the download as it is could succeed.
+    /// Failures caused by restrictions on the video are not worth retrying,
+    /// nor are those that would recur until the user frees disk space or
+    /// installs a missing binary. A live stream or premiere can be retried,
+    /// since it will succeed once it has started.
+    pub const fn is_retryable(self) -> bool {
+        match self {
+            Self::VideoUnavailable
+            | Self::Private
+            | Self::AgeRestricted
+            | Self::GeoBlocked
+            | Self::MembersOnly
+            | Self::DiskFull
+            | Self::MissingBinary => false,
+            Self::LiveNotStarted | Self::Network | Self::Ffmpeg | Self::Unknown => true,
+        }
+    }
+
+    /// Returns whether the failure is likely to be temporary, such that the
+    /// failed operation is worth retrying automatically.
+    pub const fn is_transient(self) -> bool {
+        matches!(self, Self::Network)
+    }
+}
+
+/// A failed download, as presented to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DownloadFailure {
+    /// The cause of the failure.
+    pub kind: DownloadErrorKind,
+    /// A description of the step that failed.
+    pub description: String,
+    /// The raw output of the failed operation.
+    pub log: String,
+}
+
+impl DownloadFailure {
+    /// Creates a failure of unknown cause from an error message.
+    pub fn unknown(message: impl Into<String>) -> Self {
+        Self {
+            kind: DownloadErrorKind::Unknown,
+            description: message.into(),
+            log: String::new(),
+        }
+    }
+
+    /// Classifies an error produced by a download.
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        if let Some(err) = err.downcast_ref::<DownloadError>() {
+            return Self {
+                kind: err.kind(),
+                description: err.description().to_owned(),
+                log: err.output().to_owned(),
+            };
+        }
+
+        let kind = if let Some(err) = err.downcast_ref::<io::Error>() {
+            match err.kind() {
+                io::ErrorKind::StorageFull => DownloadErrorKind::DiskFull,
+                _ => DownloadErrorKind::classify(&err.to_string())
+                    .unwrap_or(DownloadErrorKind::Unknown),
+            }
+        } else if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+            // Client errors other than rate limiting will not resolve
+            // themselves, so they are not attributed to the network.
+            match err.status() {
+                Some(status)
+                    if status.is_client_error()
+                        && status != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    DownloadErrorKind::Unknown
+                }
+                _ => DownloadErrorKind::Network,
+            }
+        } else {
+            DownloadErrorKind::classify(&err.to_string()).unwrap_or(DownloadErrorKind::Unknown)
+        };
+
+        Self {
+            kind,
+            description: err.to_string(),
+            log: format!("{:?}", err),
+        }
+    }
+
+    /// Gets a human-friendly explanation of the failure.
+    pub fn message(&self) -> &'static str {
+        self.kind.message()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_output() {
+        use DownloadErrorKind::*;
+
+        // Lines captured from youtube-dl, yt-dlp and ffmpeg, with the kind of
+        // failure each indicates and whether it is worth retrying.
+        let cases = [
+            (
+                "ERROR: unable to write data: [Errno 28] No space left on device",
+                Some(DiskFull),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Private video. Sign in if you've been granted \
+                 access to this video",
+                Some(Private),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Join this channel to get access to members-only \
+                 content like this video, and other exclusive perks.",
+                Some(MembersOnly),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm your age. This video may be \
+                 inappropriate for some users.",
+                Some(AgeRestricted),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. The uploader has not made this \
+                 video available in your country",
+                Some(GeoBlocked),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: The uploader has not made this video available in \
+                 your country.",
+                Some(GeoBlocked),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video contains content \
+                 from SME, who has blocked it in your country on copyright grounds",
+                Some(GeoBlocked),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Premieres in 2 hours",
+                Some(LiveNotStarted),
+                true,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: This live event will begin in 3 days.",
+                Some(LiveNotStarted),
+                true,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable",
+                Some(VideoUnavailable),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video has been removed by \
+                 the uploader",
+                Some(VideoUnavailable),
+                false,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video is no longer \
+                 available because the YouTube account associated with this video has been \
+                 terminated.",
+                Some(VideoUnavailable),
+                false,
+            ),
+            (
+                "ERROR: [youtube:truncated_id] dQw4w9WgX: Incomplete YouTube ID dQw4w9WgX. URL \
+                 https://www.youtube.com/watch?v=dQw4w9WgX looks truncated.",
+                Some(VideoUnavailable),
+                false,
+            ),
+            (
+                "ERROR: Postprocessing: Conversion failed!",
+                Some(Ffmpeg),
+                true,
+            ),
+            (
+                "ERROR: ffprobe and ffmpeg not found. Please install or provide the path using \
+                 --ffmpeg-location",
+                Some(Ffmpeg),
+                true,
+            ),
+            (
+                "video.webm: Invalid data found when processing input",
+                Some(Ffmpeg),
+                true,
+            ),
+            (
+                "ERROR: Unable to download webpage: <urlopen error [Errno -3] Temporary failure \
+                 in name resolution> (caused by URLError(gaierror(-3, 'Temporary failure in name \
+                 resolution')))",
+                Some(Network),
+                true,
+            ),
+            (
+                "ERROR: unable to download video data: HTTP Error 503: Service Unavailable",
+                Some(Network),
+                true,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download API page: HTTP Error 429: Too \
+                 Many Requests",
+                Some(Network),
+                true,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Failed to extract any player response",
+                None,
+                true,
+            ),
+            ("", None, true),
+        ];
+
+        for (output, expected, retryable) in cases {
+            let kind = DownloadErrorKind::classify(output);
+            assert_eq!(kind, expected, "{:?}", output);
+            assert_eq!(
+                kind.unwrap_or(Unknown).is_retryable(),
+                retryable,
+                "{:?}",
+                output
+            );
+        }
+
+        assert!(!MissingBinary.is_retryable());
+    }
+}
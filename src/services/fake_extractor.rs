@@ -0,0 +1,293 @@
+//! A fake extractor that serves placeholder video information and generates
+//! media locally, so that tests can run downloads without network access or
+//! an extractor binary.
+
+#[cfg(windows)]
+use crate::constants::*;
+use crate::services::{
+    ffmpeg_binary_path, run_command, AvailableSubtitle, CancelHandle, ChannelFilter, ChannelRef,
+    Chapter, DownloadError, DownloadPhase, DownloadProgress, DownloadRequest, Extractor, Playlist,
+    PlaylistEntry, ProgressSender, VideoFormat, VideoInfo,
+};
+use crate::types::*;
+use anyhow::Result;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use std::env::current_exe;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs;
+use tokio::process::Command;
+
+/// The length of every fake video.
+const FAKE_VIDEO_DURATION: Duration = Duration::from_secs(30);
+
+/// The number of videos in every fake playlist and channel.
+const FAKE_PLAYLIST_LENGTH: usize = 3;
+
+/// The subtitles written for every fake subtitle track.
+const FAKE_SUBTITLES: &str = "WEBVTT\n\n00:00:00.000 --> 00:00:05.000\nFake subtitles\n";
+
+/// An extractor that needs neither the network nor an extractor binary.
+/// Every video exists, with placeholder information and two chapters, and
+/// its streams are generated with the ffmpeg binary as a test pattern and a
+/// tone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FakeExtractor;
+
+/// Returns the placeholder information of a fake video.
+fn fake_video_info(video_id: &str) -> VideoInfo {
+    let middle = FAKE_VIDEO_DURATION / 2;
+
+    VideoInfo {
+        id: video_id.to_owned(),
+        title: format!("Fake video {}", video_id),
+        uploader: Some("Fake uploader".to_owned()),
+        channel: Some("Fake channel".to_owned()),
+        upload_date: Some("20240101".to_owned()),
+        description: Some("A video served by the fake extractor.".to_owned()),
+        webpage_url: None,
+        thumbnail: Some(format!("fake://thumbnail/{}", video_id)),
+        chapters: Some(vec![
+            Chapter {
+                title: "Beginning".to_owned(),
+                start_time: Duration::ZERO,
+                end_time: middle,
+            },
+            Chapter {
+                title: "End".to_owned(),
+                start_time: middle,
+                end_time: FAKE_VIDEO_DURATION,
+            },
+        ]),
+    }
+}
+
+/// Returns a fake playlist of placeholder videos.
+fn fake_playlist(id: String, title: String, length: usize) -> Playlist {
+    let entries = (1..=length)
+        .map(|index| PlaylistEntry {
+            index,
+            video_id: format!("fakevideo{:02}", index),
+            title: Some(format!("Fake video {}", index)),
+        })
+        .collect();
+
+    Playlist {
+        id,
+        title: Some(title),
+        entries,
+    }
+}
+
+/// Generates a media file at `output_path` from ffmpeg's test sources.
+async fn generate_media(args: &[String], output_path: &Path, cancel: &CancelHandle) -> Result<()> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(ffmpeg_binary_path()?);
+    cmd.arg("-y").args(args).arg(output_path);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = run_command(cmd.current_dir(here), cancel, |_| {}).await?;
+
+    if res.status.success() {
+        Ok(())
+    } else {
+        Err(DownloadError::ffmpeg("failed to generate fake media", res.stderr).into())
+    }
+}
+
+/// Returns the ffmpeg arguments of a test tone input as long as a fake
+/// video.
+fn tone_input_args() -> Vec<String> {
+    vec![
+        "-f".to_owned(),
+        "lavfi".to_owned(),
+        "-i".to_owned(),
+        format!(
+            "sine=frequency=440:duration={}",
+            FAKE_VIDEO_DURATION.as_secs()
+        ),
+    ]
+}
+
+impl Extractor for FakeExtractor {
+    fn fetch_video_info<'a>(
+        &'a self,
+        video_id: &'a str,
+        _cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<VideoInfo>> {
+        async move { Ok(fake_video_info(video_id)) }.boxed_local()
+    }
+
+    fn fetch_thumbnail<'a>(&'a self, url: &'a str) -> LocalBoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            // Each video's thumbnail is a solid colour derived from its URL,
+            // so that thumbnails of different videos differ.
+            let seed = url.bytes().fold(0u32, |seed, byte| {
+                seed.wrapping_mul(31).wrapping_add(byte as u32)
+            });
+            let [red, green, blue, _] = seed.to_le_bytes();
+            let img = RgbImage::from_pixel(320, 180, Rgb([red, green, blue]));
+            let mut bytes = Vec::new();
+            DynamicImage::ImageRgb8(img)
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+            Ok(bytes)
+        }
+        .boxed_local()
+    }
+
+    fn fetch_formats<'a>(
+        &'a self,
+        _video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<VideoFormat>>> {
+        let format =
+            |format_id: &str, ext: &str, height: Option<u32>, vcodec: &str, acodec: &str| {
+                VideoFormat {
+                    format_id: format_id.to_owned(),
+                    ext: ext.to_owned(),
+                    width: height.map(|height| height * 16 / 9),
+                    height,
+                    fps: height.map(|_| 30.0),
+                    vcodec: Some(vcodec.to_owned()),
+                    acodec: Some(acodec.to_owned()),
+                    tbr: None,
+                    filesize: None,
+                    filesize_approx: None,
+                    format_note: None,
+                }
+            };
+        let formats = vec![
+            format("140", "m4a", None, "none", "mp4a.40.2"),
+            format("18", "mp4", Some(360), "avc1.42001E", "mp4a.40.2"),
+            format("137", "mp4", Some(1080), "avc1.640028", "none"),
+        ];
+
+        async move { Ok(formats) }.boxed_local()
+    }
+
+    fn fetch_subtitles<'a>(
+        &'a self,
+        _video_id: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<AvailableSubtitle>>> {
+        let subtitles = [false, true]
+            .into_iter()
+            .map(|automatic| AvailableSubtitle {
+                track: SubtitleTrack {
+                    language: "en".to_owned(),
+                    automatic,
+                },
+                name: "English".to_owned(),
+                formats: vec!["vtt".to_owned()],
+            })
+            .collect();
+
+        async move { Ok(subtitles) }.boxed_local()
+    }
+
+    fn fetch_playlist<'a>(&'a self, playlist_id: &'a str) -> LocalBoxFuture<'a, Result<Playlist>> {
+        let playlist = fake_playlist(
+            playlist_id.to_owned(),
+            format!("Fake playlist {}", playlist_id),
+            FAKE_PLAYLIST_LENGTH,
+        );
+
+        async move { Ok(playlist) }.boxed_local()
+    }
+
+    fn fetch_channel<'a>(
+        &'a self,
+        channel: &'a ChannelRef,
+        filter: &'a ChannelFilter,
+    ) -> LocalBoxFuture<'a, Result<Playlist>> {
+        let length = filter
+            .max_videos
+            .map_or(FAKE_PLAYLIST_LENGTH, |max_videos| {
+                max_videos.min(FAKE_PLAYLIST_LENGTH)
+            });
+        let playlist = fake_playlist(channel.url(), channel.display_name(), length);
+
+        async move { Ok(playlist) }.boxed_local()
+    }
+
+    fn download_audio_stream<'a>(
+        &'a self,
+        _request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Option<PathBuf>>> {
+        async move {
+            let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+
+            let path = directory.join("audio.m4a");
+            let mut args = tone_input_args();
+            args.extend(["-c:a".to_owned(), "aac".to_owned()]);
+            generate_media(&args, &path, cancel).await?;
+            Ok(Some(path))
+        }
+        .boxed_local()
+    }
+
+    fn download_video_file<'a>(
+        &'a self,
+        _request: &'a DownloadRequest,
+        output_path: &'a Path,
+        progress: &'a ProgressSender,
+        cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        async move {
+            let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+
+            let mut args = vec![
+                "-f".to_owned(),
+                "lavfi".to_owned(),
+                "-i".to_owned(),
+                format!(
+                    "testsrc=size=640x360:rate=30:duration={}",
+                    FAKE_VIDEO_DURATION.as_secs()
+                ),
+            ];
+            args.extend(tone_input_args());
+            args.extend([
+                "-pix_fmt".to_owned(),
+                "yuv420p".to_owned(),
+                "-c:a".to_owned(),
+                "aac".to_owned(),
+                "-f".to_owned(),
+                "mp4".to_owned(),
+            ]);
+            generate_media(&args, output_path, cancel).await
+        }
+        .boxed_local()
+    }
+
+    fn download_subtitle_files<'a>(
+        &'a self,
+        request: &'a DownloadRequest,
+        directory: &'a Path,
+        progress: &'a ProgressSender,
+        _cancel: &'a CancelHandle,
+    ) -> LocalBoxFuture<'a, Result<Vec<(SubtitleTrack, PathBuf)>>> {
+        async move {
+            let _ = progress.send(DownloadProgress::new(DownloadPhase::Downloading));
+            let mut files = Vec::new();
+
+            for track in &request.subtitle_tracks {
+                let kind = if track.automatic { "auto" } else { "manual" };
+                let path = directory.join(format!("{}.{}.vtt", kind, track.language));
+                fs::write(&path, FAKE_SUBTITLES).await?;
+                files.push((track.clone(), path));
+            }
+
+            Ok(files)
+        }
+        .boxed_local()
+    }
+}
@@ -1,16 +1,22 @@
 //! Service for fetching the dependency binaries from the web.
 
 use crate::constants::*;
+use crate::services::retry;
+use crate::types::*;
 use anyhow::Result;
+use flate2::read::GzDecoder;
 use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::env::current_exe;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use tempfile::{tempfile, TempDir};
 use tokio::fs::{self, File};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use tokio::process::Command;
 use tokio::task::spawn_blocking;
+use xz2::read::XzDecoder;
 use zip::ZipArchive;
 
 /// An error occurring during a dependency operation.
@@ -30,91 +36,635 @@ impl Display for DepError {
 
 impl std::error::Error for DepError {}
 
-/// Returns the path to the ffmpeg binary.
-fn ffmpeg_binary_path() -> Result<PathBuf> {
+impl DepError {
+    /// Creates a new dependency error.
+    pub fn new(description: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            output: output.into(),
+        }
+    }
+}
+
+/// The record of an installed dependency binary in the dependency manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct DependencyRecord {
+    /// The URL the binary was downloaded from.
+    source_url: String,
+    /// The SHA-256 hash of the download, as verified against the published
+    /// checksums.
+    source_sha256: String,
+    /// The SHA-256 hash of the installed binary, which differs from that of
+    /// the download if the binary was extracted from an archive.
+    binary_sha256: String,
+}
+
+/// The verified dependency binaries, keyed by file name.
+type DependencyManifest = BTreeMap<String, DependencyRecord>;
+
+/// Returns the path to the dependency manifest file.
+fn dependency_manifest_path() -> Result<PathBuf> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let joined = Path::new(here).join(DEPENDENCY_MANIFEST_FILE_NAME);
+    Ok(joined)
+}
+
+/// Loads the dependency manifest from the file.
+fn load_dependency_manifest() -> Result<DependencyManifest> {
+    let manifest_path = dependency_manifest_path()?;
+
+    if manifest_path.exists() {
+        let manifest_bytes = std::fs::read(manifest_path)?;
+        Ok(serde_json::from_slice(&manifest_bytes)?)
+    } else {
+        Ok(DependencyManifest::new())
+    }
+}
+
+/// Records a verified binary in the dependency manifest, replacing any
+/// previous record of it.
+async fn record_dependency(binary_name: &str, record: DependencyRecord) -> Result<()> {
+    let mut manifest = load_dependency_manifest()?;
+    manifest.insert(binary_name.to_owned(), record);
+    let manifest_bytes = serde_json::to_vec(&manifest)?;
+    fs::write(dependency_manifest_path()?, manifest_bytes).await?;
+    Ok(())
+}
+
+/// Formats a SHA-256 hash as lowercase hexadecimal.
+fn hex_digest(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Computes the SHA-256 hash of a file. This blocks, so it should be run on
+/// a blocking thread if the file may be large.
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hex_digest(hasher))
+}
+
+/// Checks that a binary is installed at `path` and matches the hash recorded
+/// for it in the dependency manifest. The binary is hashed on a blocking
+/// thread, since binaries such as ffmpeg are large.
+async fn binary_verified(binary_name: &str, path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let Some(record) = load_dependency_manifest()?.remove(binary_name) else {
+        return Ok(false);
+    };
+    let binary_path = path.to_path_buf();
+    let binary_sha256 = spawn_blocking(move || file_sha256(&binary_path)).await??;
+
+    Ok(binary_sha256 == record.binary_sha256)
+}
+
+/// Returns the last component of a URL, which names the file it serves.
+fn url_file_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+/// Fetches a checksum file in the format of `sha256sum`, and returns the
+/// SHA-256 hash it lists for `file_name`. The download is retried according
+/// to `retry_policy` if it fails transiently.
+async fn fetch_checksum(
+    checksums_url: &str,
+    file_name: &str,
+    retry_policy: &RetryPolicy,
+) -> Result<String> {
+    let checksums = retry(
+        retry_policy,
+        None,
+        |_| {},
+        || async {
+            Ok(reqwest::get(checksums_url)
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        },
+    )
+    .await?;
+
+    checksums
+        .lines()
+        .find_map(|line| {
+            let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            let name = name.strip_prefix("./").unwrap_or(name);
+            (name == file_name).then(|| hash.to_ascii_lowercase())
+        })
+        .ok_or_else(|| {
+            DepError {
+                description: format!("no checksum published for {}", file_name),
+                output: checksums_url.to_owned(),
+            }
+            .into()
+        })
+}
+
+/// Checks that a download's SHA-256 hash is the expected one, so that a
+/// corrupted or tampered download is never installed.
+fn verify_checksum(url: &str, expected: &str, actual: &str) -> Result<()> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(DepError {
+            description: "checksum mismatch, refusing to install".to_owned(),
+            output: format!("{}: expected SHA-256 {}, got {}", url, expected, actual),
+        }
+        .into())
+    }
+}
+
+/// The kind of archive a dependency is distributed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ArchiveKind {
+    /// A zip archive.
+    Zip,
+    /// A tar archive compressed with xz.
+    TarXz,
+    /// A tar archive compressed with gzip.
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Determines the kind of an archive from the extension of its URL or
+    /// file name.
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(Self::TarXz)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+
+    /// Extracts an archive of this kind into `destination`. This blocks, so
+    /// it should be run on a blocking thread.
+    fn extract(self, file: std::fs::File, destination: &Path) -> Result<()> {
+        match self {
+            Self::Zip => ZipArchive::new(file)?.extract(destination)?,
+            Self::TarXz => tar::Archive::new(XzDecoder::new(file)).unpack(destination)?,
+            Self::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(destination)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Searches a directory tree for a file named `file_name`, returning the
+/// match closest to the root. This blocks, so it should be run on a blocking
+/// thread.
+fn find_file(root: &Path, file_name: &str) -> Result<Option<PathBuf>> {
+    let mut directories = vec![root.to_path_buf()];
+
+    while !directories.is_empty() {
+        let mut subdirectories = Vec::new();
+
+        for directory in directories {
+            for entry in std::fs::read_dir(directory)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+
+                if file_type.is_dir() {
+                    subdirectories.push(entry.path());
+                } else if file_type.is_file() && entry.file_name() == file_name {
+                    return Ok(Some(entry.path()));
+                }
+            }
+        }
+
+        directories = subdirectories;
+    }
+
+    Ok(None)
+}
+
+/// Downloads the archive at `url`, verifies it against the checksum listed
+/// for it at `checksums_url`, and extracts the file named `file_name` from it
+/// to `output_path`, wherever in the archive it is. The installed binary is
+/// recorded in the dependency manifest. The downloads are retried according
+/// to `retry_policy` if they fail transiently.
+async fn fetch_from_archive(
+    url: &str,
+    checksums_url: &str,
+    file_name: &str,
+    output_path: &Path,
+    retry_policy: &RetryPolicy,
+) -> Result<()> {
+    let kind = ArchiveKind::from_name(url).ok_or_else(|| DepError {
+        description: "unsupported archive format".to_owned(),
+        output: url.to_owned(),
+    })?;
+    let expected_sha256 = fetch_checksum(checksums_url, url_file_name(url), retry_policy).await?;
+    let (temp_archive_file, source_sha256) =
+        retry(retry_policy, None, |_| {}, || download_to_tempfile(url)).await?;
+    verify_checksum(url, &expected_sha256, &source_sha256)?;
+
+    let temp_file = temp_archive_file.into_std().await;
+    let temp_extract_dir = TempDir::new()?;
+    let temp_extract_path = temp_extract_dir.path().to_path_buf();
+    let search_name = file_name.to_owned();
+    let extracted_path = spawn_blocking(move || {
+        kind.extract(temp_file, &temp_extract_path)?;
+        find_file(&temp_extract_path, &search_name)
+    })
+    .await??
+    .ok_or_else(|| DepError {
+        description: format!("{} not found in archive", file_name),
+        output: url.to_owned(),
+    })?;
+
+    fs::copy(extracted_path, output_path).await?;
+    set_executable(output_path).await?;
+
+    let binary_path = output_path.to_path_buf();
+    let binary_sha256 = spawn_blocking(move || file_sha256(&binary_path)).await??;
+    record_dependency(
+        file_name,
+        DependencyRecord {
+            source_url: url.to_owned(),
+            source_sha256,
+            binary_sha256,
+        },
+    )
+    .await
+}
+
+/// Marks a fetched binary as executable. Only Unix-like systems record this
+/// in a file's permissions.
+async fn set_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+    }
+
+    Ok(())
+}
+
+/// Returns the path the ffmpeg binary is installed to alongside the
+/// application.
+pub fn ffmpeg_install_path() -> Result<PathBuf> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
     let joined = Path::new(here).join(FFMPEG_BINARY_NAME);
     Ok(joined)
 }
 
-/// Checks if the ffmpeg binary exists and is in the current working
-/// directory.
-pub fn ffmpeg_binary_exists() -> Result<bool> {
-    ffmpeg_binary_path().map(|path| path.exists())
+/// Checks if the ffmpeg binary is installed alongside the application and is
+/// the binary recorded in the dependency manifest.
+pub async fn ffmpeg_binary_exists() -> Result<bool> {
+    binary_verified(FFMPEG_BINARY_NAME, &ffmpeg_install_path()?).await
 }
 
-/// Fetches the ffmpeg binary from GitHub and places it in the current working
-/// directory.
-pub async fn fetch_ffmpeg_binary() -> Result<()> {
-    let mut byte_stream = reqwest::get(FFMPEG_BINARY_URL).await?.bytes_stream();
-    let mut temp_zip_file = File::from_std(tempfile()?);
+/// Downloads the file at `url` into an anonymous temporary file, rewound to
+/// its start. The SHA-256 hash of the file is returned along with it.
+async fn download_to_tempfile(url: &str) -> Result<(File, String)> {
+    let mut byte_stream = reqwest::get(url).await?.error_for_status()?.bytes_stream();
+    let mut temp_file = File::from_std(tempfile()?);
+    let mut hasher = Sha256::new();
 
     while let Some(chunk) = byte_stream.next().await {
         let chunk = chunk?;
-        temp_zip_file.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        temp_file.write_all(&chunk).await?;
     }
 
-    temp_zip_file.rewind().await?;
-    let mut archive = ZipArchive::new(temp_zip_file.into_std().await)?;
-    let temp_unzip_dir = TempDir::new()?;
-    let temp_unzip_path = temp_unzip_dir.path().to_path_buf();
-    spawn_blocking(move || archive.extract(temp_unzip_path)).await??;
-
-    let unzipped_ffmpeg_binary_path = temp_unzip_dir
-        .path()
-        .join("ffmpeg-master-latest-win64-gpl")
-        .join("bin")
-        .join(FFMPEG_BINARY_NAME);
-    fs::copy(unzipped_ffmpeg_binary_path, ffmpeg_binary_path()?).await?;
+    temp_file.rewind().await?;
+    Ok((temp_file, hex_digest(hasher)))
+}
 
-    Ok(())
+/// Fetches the ffmpeg binary from GitHub, verifies it against the published
+/// checksums and installs it alongside the application. The download is
+/// retried according to `retry_policy` if it fails transiently.
+pub async fn fetch_ffmpeg_binary(retry_policy: &RetryPolicy) -> Result<()> {
+    fetch_from_archive(
+        FFMPEG_BINARY_URL,
+        FFMPEG_CHECKSUMS_URL,
+        FFMPEG_BINARY_NAME,
+        &ffmpeg_install_path()?,
+        retry_policy,
+    )
+    .await
 }
 
-/// Returns the path to the youtube-dl binary.
-fn youtube_dl_binary_path() -> Result<PathBuf> {
+/// Returns the path the youtube-dl binary is installed to alongside the
+/// application.
+pub fn youtube_dl_install_path() -> Result<PathBuf> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
     let joined = Path::new(here).join(YOUTUBE_DL_BINARY_NAME);
     Ok(joined)
 }
 
-/// Checks if the youtube-dl binary exists and is in the current working
-/// directory.
-pub fn youtube_dl_binary_exists() -> Result<bool> {
-    youtube_dl_binary_path().map(|path| path.exists())
+/// Checks if the youtube-dl binary is installed alongside the application and
+/// is the binary recorded in the dependency manifest.
+pub async fn youtube_dl_binary_exists() -> Result<bool> {
+    binary_verified(YOUTUBE_DL_BINARY_NAME, &youtube_dl_install_path()?).await
 }
 
-/// Fetches the youtube-dl binary from GitHub and places it in the current
-/// working directory.
-pub async fn fetch_youtube_dl_binary() -> Result<()> {
-    let bytes = reqwest::get(YOUTUBE_DL_BINARY_URL).await?.bytes().await?;
-    fs::write(youtube_dl_binary_path()?, bytes).await?;
-    Ok(())
+/// Downloads a binary that is published as a single file from `url`,
+/// verifies it against `expected_sha256` and installs it to `path`. The
+/// download is retried according to `retry_policy` if it fails transiently.
+async fn install_single_file(
+    url: &str,
+    expected_sha256: &str,
+    file_name: &str,
+    path: &Path,
+    retry_policy: &RetryPolicy,
+) -> Result<()> {
+    let bytes = retry(
+        retry_policy,
+        None,
+        |_| {},
+        || async { Ok(reqwest::get(url).await?.error_for_status()?.bytes().await?) },
+    )
+    .await?;
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    let sha256 = hex_digest(hasher);
+    verify_checksum(url, expected_sha256, &sha256)?;
+
+    fs::write(path, bytes).await?;
+    set_executable(path).await?;
+    record_dependency(
+        file_name,
+        DependencyRecord {
+            source_url: url.to_owned(),
+            source_sha256: sha256.clone(),
+            binary_sha256: sha256,
+        },
+    )
+    .await
+}
+
+/// Fetches a binary that is published as a single file from `url`, verifies
+/// it against the checksums published at `checksums_url` and installs it to
+/// `path`. The download is retried according to `retry_policy` if it fails
+/// transiently.
+async fn fetch_single_file(
+    url: &str,
+    checksums_url: &str,
+    file_name: &str,
+    path: &Path,
+    retry_policy: &RetryPolicy,
+) -> Result<()> {
+    let expected_sha256 = fetch_checksum(checksums_url, url_file_name(url), retry_policy).await?;
+    install_single_file(url, &expected_sha256, file_name, path, retry_policy).await
+}
+
+/// Updates a binary that is published as a single file at `url`, installed
+/// to `path`. The binary is fetched again, verified like a new installation,
+/// if the checksum published for it at `checksums_url` differs from the one
+/// recorded when it was installed, or if the installed binary no longer
+/// matches its record. Binaries are never trusted to update themselves, as
+/// their updates could not be verified. Returns whether the binary was
+/// updated.
+async fn update_single_file(
+    url: &str,
+    checksums_url: &str,
+    file_name: &str,
+    path: &Path,
+    retry_policy: &RetryPolicy,
+) -> Result<bool> {
+    let expected_sha256 = fetch_checksum(checksums_url, url_file_name(url), retry_policy).await?;
+    let installed_sha256 = load_dependency_manifest()?
+        .remove(file_name)
+        .map(|record| record.source_sha256);
+
+    if installed_sha256.as_deref() == Some(expected_sha256.as_str())
+        && binary_verified(file_name, path).await?
+    {
+        return Ok(false);
+    }
+
+    install_single_file(url, &expected_sha256, file_name, path, retry_policy).await?;
+    Ok(true)
+}
+
+/// Fetches the youtube-dl binary from GitHub, verifies it against the
+/// published checksums and installs it alongside the application. The
+/// download is retried according to `retry_policy` if it fails transiently.
+pub async fn fetch_youtube_dl_binary(retry_policy: &RetryPolicy) -> Result<()> {
+    fetch_single_file(
+        YOUTUBE_DL_BINARY_URL,
+        YOUTUBE_DL_CHECKSUMS_URL,
+        YOUTUBE_DL_BINARY_NAME,
+        &youtube_dl_install_path()?,
+        retry_policy,
+    )
+    .await
 }
 
-/// Updates the youtube-dl binary.
-pub async fn update_youtube_dl_binary() -> Result<()> {
+/// Updates the youtube-dl binary installed alongside the application to the
+/// published release, retrying according to `retry_policy` if the update
+/// fails transiently. Returns whether the binary was updated.
+pub async fn update_youtube_dl_binary(retry_policy: &RetryPolicy) -> Result<bool> {
+    update_single_file(
+        YOUTUBE_DL_BINARY_URL,
+        YOUTUBE_DL_CHECKSUMS_URL,
+        YOUTUBE_DL_BINARY_NAME,
+        &youtube_dl_install_path()?,
+        retry_policy,
+    )
+    .await
+}
+
+/// Returns the path the yt-dlp binary is installed to alongside the
+/// application.
+pub fn yt_dlp_install_path() -> Result<PathBuf> {
     let current = current_exe()?;
     let here = current.parent().unwrap_or(Path::new("."));
+    let joined = Path::new(here).join(YT_DLP_BINARY_NAME);
+    Ok(joined)
+}
 
-    let mut cmd = Command::new(YOUTUBE_DL_BINARY_NAME);
-    cmd.arg("-U");
+/// Checks if the yt-dlp binary is installed alongside the application and is
+/// the binary recorded in the dependency manifest.
+pub async fn yt_dlp_binary_exists() -> Result<bool> {
+    binary_verified(YT_DLP_BINARY_NAME, &yt_dlp_install_path()?).await
+}
 
-    #[cfg(windows)]
-    {
-        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+/// Fetches the latest yt-dlp release from GitHub, verifies it against the
+/// published checksums and installs it alongside the application. The
+/// download is retried according to `retry_policy` if it fails transiently.
+pub async fn fetch_yt_dlp_binary(retry_policy: &RetryPolicy) -> Result<()> {
+    fetch_single_file(
+        YT_DLP_BINARY_URL,
+        YT_DLP_CHECKSUMS_URL,
+        YT_DLP_BINARY_NAME,
+        &yt_dlp_install_path()?,
+        retry_policy,
+    )
+    .await
+}
+
+/// Updates the yt-dlp binary installed alongside the application to the
+/// latest release, retrying according to `retry_policy` if the update fails
+/// transiently. Returns whether the binary was updated.
+pub async fn update_yt_dlp_binary(retry_policy: &RetryPolicy) -> Result<bool> {
+    update_single_file(
+        YT_DLP_BINARY_URL,
+        YT_DLP_CHECKSUMS_URL,
+        YT_DLP_BINARY_NAME,
+        &yt_dlp_install_path()?,
+        retry_policy,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use xz2::write::XzEncoder;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    /// The contents of the binary to find in each archive.
+    const BINARY: &[u8] = b"binary";
+
+    /// The contents of a file with the binary's name nested deeper than the
+    /// binary itself.
+    const DECOY: &[u8] = b"decoy";
+
+    /// Writes a tar archive of the given files to `writer`.
+    fn write_tar<W: Write>(writer: W, files: &[(&str, &[u8])]) -> W {
+        let mut builder = tar::Builder::new(writer);
+
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *contents).unwrap();
+        }
+
+        builder.into_inner().unwrap()
     }
 
-    let res = cmd.current_dir(here).output().await?;
+    /// Creates an archive named `name` in `dir` containing the given files.
+    fn create_archive(dir: &Path, name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join(name);
+        let file = std::fs::File::create(&path).unwrap();
 
-    if res.status.success() {
-        Ok(())
-    } else {
-        Err(DepError {
-            description: "failed to update youtube-dl binary".to_owned(),
-            output: String::from_utf8_lossy(&res.stderr).into_owned(),
+        match ArchiveKind::from_name(name).unwrap() {
+            ArchiveKind::Zip => {
+                let mut zip = ZipWriter::new(file);
+
+                for (path, contents) in files {
+                    zip.start_file(*path, SimpleFileOptions::default()).unwrap();
+                    zip.write_all(contents).unwrap();
+                }
+
+                zip.finish().unwrap();
+            }
+            ArchiveKind::TarXz => {
+                write_tar(XzEncoder::new(file, 6), files).finish().unwrap();
+            }
+            ArchiveKind::TarGz => {
+                write_tar(GzEncoder::new(file, Compression::default()), files)
+                    .finish()
+                    .unwrap();
+            }
         }
-        .into())
+
+        path
+    }
+
+    #[test]
+    fn archive_kinds() {
+        for (name, kind) in [
+            ("ffmpeg-master-latest-win64-gpl.zip", Some(ArchiveKind::Zip)),
+            (
+                "ffmpeg-release-amd64-static.tar.xz",
+                Some(ArchiveKind::TarXz),
+            ),
+            ("ffmpeg.TXZ", Some(ArchiveKind::TarXz)),
+            ("ffmpeg-6.1-macos.tar.gz", Some(ArchiveKind::TarGz)),
+            ("ffmpeg.tgz", Some(ArchiveKind::TarGz)),
+            ("ffmpeg.exe", None),
+            ("ffmpeg.tar", None),
+        ] {
+            assert_eq!(ArchiveKind::from_name(name), kind, "{}", name);
+        }
+    }
+
+    #[test]
+    fn extract_archives() {
+        let dir = TempDir::new().unwrap();
+
+        for (name, binary_path, decoy_path) in [
+            (
+                "nested.zip",
+                "pkg/bin/ffmpeg",
+                "pkg/doc/examples/bin/ffmpeg",
+            ),
+            ("nested.tar.xz", "pkg/ffmpeg", "pkg/manpages/ffmpeg"),
+            ("flat.tar.gz", "ffmpeg", "licenses/ffmpeg"),
+        ] {
+            // The decoy is added first, so that it is not found merely by
+            // being the first entry.
+            let archive_path = create_archive(
+                dir.path(),
+                name,
+                &[
+                    (decoy_path, DECOY),
+                    ("pkg/README.txt", b"readme"),
+                    (binary_path, BINARY),
+                ],
+            );
+            let extract_dir = TempDir::new().unwrap();
+            ArchiveKind::from_name(name)
+                .unwrap()
+                .extract(
+                    std::fs::File::open(&archive_path).unwrap(),
+                    extract_dir.path(),
+                )
+                .unwrap();
+
+            let found = find_file(extract_dir.path(), "ffmpeg").unwrap().unwrap();
+            assert_eq!(found, extract_dir.path().join(binary_path), "{}", name);
+            assert_eq!(std::fs::read(&found).unwrap(), BINARY, "{}", name);
+            assert_eq!(find_file(extract_dir.path(), "ffprobe").unwrap(), None);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn executable_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("ffmpeg");
+        std::fs::write(&path, BINARY).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        set_executable(&path).await.unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
     }
 }
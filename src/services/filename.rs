@@ -0,0 +1,253 @@
+//! Output filename templates.
+
+use crate::services::VideoInfo;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// The names of the fields that can be used in a filename template.
+pub const TEMPLATE_FIELDS: [&str; 7] = [
+    "title",
+    "id",
+    "uploader",
+    "channel",
+    "upload_date",
+    "playlist_index",
+    "ext",
+];
+
+/// The maximum length of a single path component in bytes, which is the
+/// limit on most filesystems.
+const MAX_COMPONENT_LENGTH: usize = 255;
+
+/// Names which cannot be used for files on Windows, regardless of extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// An error in a filename template.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TemplateError {
+    /// A description of the error.
+    description: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl TemplateError {
+    /// Creates a new template error from a description.
+    fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+        }
+    }
+}
+
+/// The values substituted into a filename template.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateValues<'a> {
+    /// Information about the video being downloaded.
+    pub info: &'a VideoInfo,
+    /// The 1-based index of the video within its playlist, if any.
+    pub playlist_index: Option<usize>,
+    /// The extension of the output file.
+    pub extension: &'a str,
+}
+
+impl TemplateValues<'_> {
+    /// Returns the value of a template field, or `None` if there is no such
+    /// field.
+    fn field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "title" => self.info.title.clone(),
+            "id" => self.info.id.clone(),
+            "uploader" => self.info.artist().unwrap_or("Unknown").to_owned(),
+            "channel" => self
+                .info
+                .channel
+                .as_deref()
+                .or(self.info.artist())
+                .unwrap_or("Unknown")
+                .to_owned(),
+            "upload_date" => self
+                .info
+                .formatted_upload_date()
+                .unwrap_or_else(|| "Unknown".to_owned()),
+            "playlist_index" => self
+                .playlist_index
+                .map(|index| format!("{:02}", index))
+                .unwrap_or_default(),
+            "ext" => self.extension.to_owned(),
+            _ => return None,
+        })
+    }
+}
+
+/// A piece of a parsed filename template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart<'a> {
+    /// Literal text.
+    Literal(&'a str),
+    /// A field to substitute.
+    Field(&'a str),
+}
+
+/// Splits a single path component of a template into literal text and
+/// fields.
+fn parse_component(component: &str) -> Result<Vec<TemplatePart<'_>>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut rest = component;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(TemplateError::new("unexpected '}'"));
+        }
+
+        let Some(len) = rest[start + 1..].find('}') else {
+            return Err(TemplateError::new("unclosed '{'"));
+        };
+        let name = &rest[start + 1..start + 1 + len];
+
+        if !TEMPLATE_FIELDS.contains(&name) {
+            return Err(TemplateError::new(format!("unknown field '{{{}}}'", name)));
+        }
+
+        if start > 0 {
+            parts.push(TemplatePart::Literal(&rest[..start]));
+        }
+
+        parts.push(TemplatePart::Field(name));
+        rest = &rest[start + len + 2..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest));
+    }
+
+    Ok(parts)
+}
+
+/// Replaces characters that are illegal in filenames on common filesystems,
+/// while preserving all other characters, including non-ASCII ones.
+fn sanitize_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Makes a path component valid on common filesystems. Leading and trailing
+/// whitespace and trailing dots are removed, reserved names are altered, and
+/// overly long components are truncated, preserving their extension.
+fn finish_component(component: &str) -> String {
+    let mut component = component.trim().trim_end_matches('.').to_owned();
+
+    if component.is_empty() || component == "." || component == ".." {
+        return "_".to_owned();
+    }
+
+    let stem = component.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem))
+    {
+        component.insert(stem.len(), '_');
+    }
+
+    if component.len() > MAX_COMPONENT_LENGTH {
+        let extension = component
+            .rfind('.')
+            .map(|index| component[index..].to_owned())
+            .filter(|extension| extension.len() < MAX_COMPONENT_LENGTH / 2)
+            .unwrap_or_default();
+        let mut end = MAX_COMPONENT_LENGTH - extension.len();
+
+        while !component.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        component = format!("{}{}", component[..end].trim_end(), extension);
+    }
+
+    component
+}
+
+/// Turns arbitrary text, such as a chapter title, into a valid file name.
+pub fn sanitize_file_name(name: &str) -> String {
+    finish_component(&sanitize_text(name))
+}
+
+/// Checks that a filename template is valid.
+pub fn validate_template(template: &str) -> Result<(), TemplateError> {
+    if template.trim().is_empty() {
+        return Err(TemplateError::new("the template is empty"));
+    }
+
+    if template.trim_end().ends_with(['/', '\\']) {
+        return Err(TemplateError::new("the template must end with a file name"));
+    }
+
+    for component in template.split(['/', '\\']) {
+        parse_component(component)?;
+    }
+
+    Ok(())
+}
+
+/// Renders a filename template into a relative output path. Each `/` in the
+/// template separates a directory, and each field is substituted with its
+/// value, sanitised so that it cannot introduce further directories.
+///
+/// If the video was selected from a playlist and the template does not
+/// include `{playlist_index}`, the index is prefixed to the filename so that
+/// videos sort in playlist order. The extension is appended if the template
+/// does not end with it.
+pub fn render_template(
+    template: &str,
+    values: &TemplateValues<'_>,
+) -> Result<PathBuf, TemplateError> {
+    validate_template(template)?;
+
+    let components = template
+        .split(['/', '\\'])
+        .filter(|component| !component.trim().is_empty())
+        .collect::<Vec<_>>();
+    let mut path = PathBuf::new();
+
+    for (i, component) in components.iter().enumerate() {
+        let mut rendered = String::new();
+
+        for part in parse_component(component)? {
+            match part {
+                TemplatePart::Literal(text) => rendered.push_str(&sanitize_text(text)),
+                TemplatePart::Field(name) => {
+                    rendered.push_str(&sanitize_text(&values.field(name).unwrap_or_default()))
+                }
+            }
+        }
+
+        let is_file_name = i == components.len() - 1;
+        if is_file_name && !template.contains("{playlist_index}") {
+            if let Some(index) = values.playlist_index {
+                rendered = format!("{:02} - {}", index, rendered);
+            }
+        }
+
+        if is_file_name && !rendered.ends_with(&format!(".{}", values.extension)) {
+            rendered = format!("{}.{}", rendered, values.extension);
+        }
+
+        path.push(finish_component(&rendered));
+    }
+
+    Ok(path)
+}
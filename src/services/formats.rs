@@ -0,0 +1,158 @@
+//! Probing of the streams available for a video.
+
+#[cfg(windows)]
+use crate::constants::*;
+use crate::services::{CommandLineExtractor, DownloadError};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::env::current_exe;
+use std::path::Path;
+use tokio::process::Command;
+
+/// A stream available for a video, as reported by youtube-dl.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoFormat {
+    /// youtube-dl's identifier for the format.
+    pub format_id: String,
+    /// The container file extension.
+    pub ext: String,
+    /// The video width in pixels.
+    pub width: Option<u32>,
+    /// The video height in pixels.
+    pub height: Option<u32>,
+    /// The video frame rate.
+    pub fps: Option<f64>,
+    /// The video codec, or `"none"` for audio-only streams.
+    pub vcodec: Option<String>,
+    /// The audio codec, or `"none"` for video-only streams.
+    pub acodec: Option<String>,
+    /// The total bitrate in kilobits per second.
+    pub tbr: Option<f64>,
+    /// The exact file size in bytes.
+    pub filesize: Option<u64>,
+    /// The approximate file size in bytes, when the exact size is unknown.
+    pub filesize_approx: Option<f64>,
+    /// Additional information about the format, such as the quality label.
+    pub format_note: Option<String>,
+}
+
+/// Returns the codec if one is present, treating youtube-dl's `"none"` as
+/// absent.
+fn present_codec(codec: &Option<String>) -> Option<&str> {
+    codec.as_deref().filter(|codec| *codec != "none")
+}
+
+/// Formats a byte count in human-readable binary units.
+fn format_size(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+impl VideoFormat {
+    /// Returns the video codec, if the format contains video.
+    pub fn video_codec(&self) -> Option<&str> {
+        present_codec(&self.vcodec)
+    }
+
+    /// Returns the audio codec, if the format contains audio.
+    pub fn audio_codec(&self) -> Option<&str> {
+        present_codec(&self.acodec)
+    }
+
+    /// Returns the file size in bytes, exact or approximate, if known.
+    pub fn size(&self) -> Option<f64> {
+        self.filesize
+            .map(|filesize| filesize as f64)
+            .or(self.filesize_approx)
+    }
+
+    /// Returns the youtube-dl format selector that downloads this format. If
+    /// the format contains no audio, the best audio stream is merged in.
+    pub fn format_selector(&self) -> String {
+        if self.video_codec().is_some() && self.audio_codec().is_none() {
+            format!("{}+bestaudio", self.format_id)
+        } else {
+            self.format_id.clone()
+        }
+    }
+
+    /// Describes the format in a human-readable way, e.g.
+    /// `1920x1080 60fps av01 + opus, 2500kbps, 210.3MiB (mp4)`.
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => parts.push(format!("{}x{}", width, height)),
+            (None, Some(height)) => parts.push(format!("{}p", height)),
+            _ => {}
+        }
+
+        if let Some(fps) = self.fps.filter(|_| self.video_codec().is_some()) {
+            parts.push(format!("{}fps", fps.round()));
+        }
+
+        match (self.video_codec(), self.audio_codec()) {
+            (Some(video), Some(audio)) => parts.push(format!("{} + {}", video, audio)),
+            (Some(video), None) => parts.push(format!("{} (video only)", video)),
+            (None, Some(audio)) => parts.push(format!("{} (audio only)", audio)),
+            (None, None) => {}
+        }
+
+        let mut description = parts.join(" ");
+
+        if let Some(tbr) = self.tbr {
+            description.push_str(&format!(", {:.0}kbps", tbr));
+        }
+
+        if let Some(size) = self.size() {
+            description.push_str(&format!(", {}", format_size(size)));
+        }
+
+        description.push_str(&format!(" ({})", self.ext));
+        description
+    }
+}
+
+/// The subset of youtube-dl's `-J` output describing available formats.
+#[derive(Debug, Clone, Deserialize)]
+struct FormatsInfo {
+    /// The available formats.
+    #[serde(default)]
+    formats: Vec<VideoFormat>,
+}
+
+/// Lists the streams available for a video using a youtube-dl compatible
+/// extractor, ordered from worst to best as the extractor reports them.
+pub async fn fetch_formats(cli: &CommandLineExtractor, video_id: &str) -> Result<Vec<VideoFormat>> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("-J").arg(video_id);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = cmd.current_dir(here).kill_on_drop(true).output().await?;
+
+    if res.status.success() {
+        let info = serde_json::from_slice::<FormatsInfo>(&res.stdout)?;
+        Ok(info.formats)
+    } else {
+        Err(DownloadError::new(
+            "failed to fetch video formats",
+            String::from_utf8_lossy(&res.stderr),
+        )
+        .into())
+    }
+}
@@ -0,0 +1,200 @@
+//! Video information and the metadata tags derived from it.
+
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// (De)serialization of durations as fractional seconds, the form youtube-dl
+/// gives times in.
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+
+    /// Serializes a duration as fractional seconds.
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        duration.as_secs_f64().serialize(serializer)
+    }
+
+    /// Deserializes a duration from fractional seconds.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A chapter marker of a video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Chapter {
+    /// The title of the chapter, which may be empty.
+    #[serde(default)]
+    pub title: String,
+    /// The time the chapter starts at.
+    #[serde(with = "seconds")]
+    pub start_time: Duration,
+    /// The time the chapter ends at.
+    #[serde(with = "seconds")]
+    pub end_time: Duration,
+}
+
+impl Chapter {
+    /// Returns the title of the chapter, or a placeholder naming its 1-based
+    /// index if it has none.
+    pub fn display_title(&self, index: usize) -> String {
+        match self.title.trim() {
+            "" => format!("Chapter {}", index),
+            title => title.to_owned(),
+        }
+    }
+
+    /// Returns the time range of the video the chapter covers.
+    pub fn clip_range(&self, mode: ClipMode) -> ClipRange {
+        ClipRange {
+            start: Some(self.start_time),
+            end: Some(self.end_time),
+            mode,
+        }
+    }
+}
+
+/// The subset of youtube-dl's info JSON describing a video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VideoInfo {
+    /// The ID of the video.
+    pub id: String,
+    /// The title of the video.
+    pub title: String,
+    /// The name of the video's uploader.
+    pub uploader: Option<String>,
+    /// The name of the channel the video was published on.
+    pub channel: Option<String>,
+    /// The upload date of the video in `YYYYMMDD` format.
+    pub upload_date: Option<String>,
+    /// The description of the video.
+    pub description: Option<String>,
+    /// The URL of the video's web page.
+    pub webpage_url: Option<String>,
+    /// The URL of the video's thumbnail.
+    pub thumbnail: Option<String>,
+    /// The chapter markers of the video, if it has any.
+    #[serde(default)]
+    pub chapters: Option<Vec<Chapter>>,
+}
+
+impl VideoInfo {
+    /// Returns the name to credit as the artist of the video.
+    pub fn artist(&self) -> Option<&str> {
+        self.uploader.as_deref().or(self.channel.as_deref())
+    }
+
+    /// Returns the URL the video can be watched at.
+    pub fn source_url(&self) -> String {
+        self.webpage_url
+            .clone()
+            .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", self.id))
+    }
+
+    /// Returns the upload date in `YYYY-MM-DD` format, if it is known.
+    pub fn formatted_upload_date(&self) -> Option<String> {
+        let date = self.upload_date.as_deref()?;
+
+        (date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()))
+            .then(|| format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8]))
+    }
+
+    /// Returns the metadata tags to write into a downloaded file, as pairs of
+    /// ffmpeg metadata keys and values.
+    pub fn metadata_tags(&self) -> Vec<(&'static str, String)> {
+        let mut tags = vec![("title", self.title.clone())];
+
+        if let Some(artist) = self.artist() {
+            tags.push(("artist", artist.to_owned()));
+        }
+
+        if let Some(date) = self.formatted_upload_date() {
+            tags.push(("date", date));
+        }
+
+        if let Some(description) = &self.description {
+            tags.push(("description", description.clone()));
+        }
+
+        tags.push(("comment", self.source_url()));
+        tags.push(("video_id", self.id.clone()));
+        tags
+    }
+
+    /// Returns the chapters that overlap the time range `clip`, with their
+    /// times made relative to the start of the range and limited to its end.
+    pub fn chapters_within(&self, clip: &ClipRange) -> Vec<Chapter> {
+        let start = clip.start.unwrap_or_default();
+
+        self.chapters
+            .iter()
+            .flatten()
+            .filter_map(|chapter| {
+                let end_time = match clip.end {
+                    Some(end) => chapter.end_time.min(end),
+                    None => chapter.end_time,
+                };
+
+                (end_time > start && end_time > chapter.start_time).then(|| Chapter {
+                    title: chapter.title.clone(),
+                    start_time: chapter.start_time.saturating_sub(start),
+                    end_time: end_time - start,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Escapes a value for an ffmpeg metadata file, in which `=`, `;`, `#`, `\`
+/// and line breaks are special.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Renders chapters as an ffmpeg metadata file, from which ffmpeg writes
+/// chapter markers into a container.
+pub fn chapter_metadata(chapters: &[Chapter]) -> String {
+    let mut metadata = ";FFMETADATA1\n".to_owned();
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_time.as_millis(),
+            chapter.end_time.as_millis(),
+            escape_ffmetadata(&chapter.display_title(index + 1))
+        ));
+    }
+
+    metadata
+}
+
+/// Returns the ffmpeg output arguments that write a video's metadata tags.
+/// MPEG-4 containers only store non-standard keys, such as the video ID, if
+/// asked to.
+pub fn metadata_args(info: &VideoInfo, extension: &str) -> Vec<String> {
+    let mut args = Vec::new();
+
+    for (key, value) in info.metadata_tags() {
+        args.push("-metadata".to_owned());
+        args.push(format!("{}={}", key, value));
+    }
+
+    if matches!(extension, "mp4" | "m4a") {
+        args.push("-movflags".to_owned());
+        args.push("use_metadata_tags".to_owned());
+    }
+
+    args
+}
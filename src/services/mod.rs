@@ -1,11 +1,51 @@
 //! Application services.
 
+mod archive;
+mod batch;
+mod binaries;
+mod cancel;
+mod channel;
+mod collision;
 mod config;
 mod download;
+mod extractor;
+mod failure;
+#[cfg(test)]
+mod fake_extractor;
 mod fetch_deps;
+mod filename;
+mod formats;
+mod metadata;
 mod parse_url;
+mod playlist;
+mod progress;
+mod queue;
+mod retry;
+mod staging;
+mod subtitles;
+mod timestamp;
 
+pub use archive::*;
+pub use batch::*;
+pub use binaries::*;
+pub use cancel::*;
+pub use channel::*;
+pub use collision::*;
 pub use config::*;
 pub use download::*;
+pub use extractor::*;
+pub use failure::*;
+#[cfg(test)]
+pub use fake_extractor::*;
 pub use fetch_deps::*;
+pub use filename::*;
+pub use formats::*;
+pub use metadata::*;
 pub use parse_url::*;
+pub use playlist::*;
+pub use progress::*;
+pub use queue::*;
+pub use retry::*;
+pub use staging::*;
+pub use subtitles::*;
+pub use timestamp::*;
@@ -1,36 +1,431 @@
 //! Utilities for parsing and validating URLs.
 
+use crate::services::parse_timestamp;
+use percent_encoding::percent_decode_str;
+use std::time::Duration;
 use url::{Host, Url};
 
 /// Validates that a video ID is in the expected format.
-fn valid_video_id(id: &str) -> bool {
+pub fn valid_video_id(id: &str) -> bool {
     id.len() == 11
         && id
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
 
-/// Parses a YouTube video URL, returning the video ID. If the URL is invalid,
-/// `None` is returned.
-pub fn parse_video_url(video_url: &str) -> Option<String> {
-    let video_url = Url::parse(video_url).ok()?;
-
-    match video_url.host() {
-        Some(Host::Domain("youtube.com")) | Some(Host::Domain("www.youtube.com")) => {
-            if video_url.path() == "/watch" {
-                let video_id = video_url.query_pairs().find(|(key, _)| key == "v")?.1;
-                valid_video_id(&video_id).then_some(video_id.into_owned())
-            } else {
-                None
+/// Validates that a playlist ID is in the expected format. Playlist IDs vary
+/// in length depending on the kind of playlist, so only the character set and
+/// a sensible length range are checked.
+fn valid_playlist_id(id: &str) -> bool {
+    (2..=64).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Validates that a channel ID is in the expected format.
+fn valid_channel_id(id: &str) -> bool {
+    id.len() == 24
+        && id.starts_with("UC")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Validates that a channel handle, custom name, or legacy username is in the
+/// expected format.
+fn valid_channel_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 100
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// A reference to a YouTube channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChannelRef {
+    /// A channel handle, e.g. `youtube.com/@name`. The `@` is not included.
+    Handle(String),
+    /// A channel ID, e.g. `youtube.com/channel/UC...`.
+    Id(String),
+    /// A custom channel name, e.g. `youtube.com/c/name`.
+    Custom(String),
+    /// A legacy username, e.g. `youtube.com/user/name`.
+    User(String),
+}
+
+impl ChannelRef {
+    /// Returns the canonical URL of the channel.
+    pub fn url(&self) -> String {
+        match self {
+            Self::Handle(handle) => format!("https://www.youtube.com/@{}", handle),
+            Self::Id(id) => format!("https://www.youtube.com/channel/{}", id),
+            Self::Custom(name) => format!("https://www.youtube.com/c/{}", name),
+            Self::User(name) => format!("https://www.youtube.com/user/{}", name),
+        }
+    }
+
+    /// Returns a short human-readable name for the channel.
+    pub fn display_name(&self) -> String {
+        match self {
+            Self::Handle(handle) => format!("@{}", handle),
+            Self::Id(id) | Self::Custom(id) | Self::User(id) => id.clone(),
+        }
+    }
+}
+
+/// A reference to a YouTube video, playlist or channel, parsed from a URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum YoutubeRef {
+    /// A single video.
+    Video {
+        /// The ID of the video.
+        id: String,
+        /// The ID of the playlist the video was linked from, if any.
+        playlist_id: Option<String>,
+        /// The time the link starts playback at, if any.
+        start_time: Option<Duration>,
+        /// The time the link stops playback at, if any. Only embed links
+        /// carry an end time.
+        end_time: Option<Duration>,
+    },
+    /// A playlist.
+    Playlist(String),
+    /// A channel.
+    Channel(ChannelRef),
+}
+
+/// Hosts that serve YouTube pages, all of which share the same URL paths.
+const YOUTUBE_HOSTS: [&str; 6] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+
+/// Hosts of short video links, whose path is the video ID.
+const SHORT_LINK_HOSTS: [&str; 2] = ["youtu.be", "www.youtu.be"];
+
+/// Path segments that are followed by a video ID, e.g. `/shorts/<id>`.
+const VIDEO_PATH_PREFIXES: [&str; 4] = ["shorts", "embed", "live", "v"];
+
+/// Parses the path segments of a channel URL in any of the handle, channel
+/// ID, custom name, or legacy username forms. Trailing tab segments such as
+/// `/videos` are ignored.
+fn parse_channel_path(segments: &[&str]) -> Option<ChannelRef> {
+    let (first, rest) = segments.split_first()?;
+
+    if let Some(handle) = first.strip_prefix('@') {
+        let handle = decode_path_segment(handle)?;
+        return valid_channel_name(&handle).then_some(ChannelRef::Handle(handle));
+    }
+
+    let name = decode_path_segment(rest.first()?)?;
+
+    match *first {
+        "channel" => valid_channel_id(&name).then_some(ChannelRef::Id(name)),
+        "c" => valid_channel_name(&name).then_some(ChannelRef::Custom(name)),
+        "user" => valid_channel_name(&name).then_some(ChannelRef::User(name)),
+        _ => None,
+    }
+}
+
+/// Parses a YouTube URL or bare video ID into a reference to the video,
+/// playlist or channel it refers to. The scheme may be omitted. A video URL
+/// with a `list` parameter refers to the video, with the playlist attached.
+/// If the text does not refer to anything on YouTube, `None` is returned.
+pub fn parse_youtube_url(text: &str) -> Option<YoutubeRef> {
+    let text = text.trim();
+
+    if valid_video_id(text) {
+        return Some(YoutubeRef::Video {
+            id: text.to_owned(),
+            playlist_id: None,
+            start_time: None,
+            end_time: None,
+        });
+    }
+
+    let url = if text.contains("://") {
+        Url::parse(text)
+    } else {
+        Url::parse(&format!("https://{}", text))
+    }
+    .ok()?;
+
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return None;
+    }
+
+    let host = match url.host() {
+        Some(Host::Domain(host)) => host,
+        _ => return None,
+    };
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.into_owned())
+    };
+    let segments = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let playlist_id = query("list").filter(|id| valid_playlist_id(id));
+
+    let video_id = if SHORT_LINK_HOSTS.contains(&host) {
+        match segments.as_slice() {
+            [id] => Some((*id).to_owned()),
+            _ => None,
+        }
+    } else if YOUTUBE_HOSTS.contains(&host) {
+        match segments.as_slice() {
+            ["watch"] => query("v"),
+            ["playlist"] | ["embed", "videoseries"] => {
+                return playlist_id.map(YoutubeRef::Playlist)
             }
+            [prefix, id, ..] if VIDEO_PATH_PREFIXES.contains(prefix) => Some((*id).to_owned()),
+            _ => return parse_channel_path(&segments).map(YoutubeRef::Channel),
         }
-        Some(Host::Domain("youtu.be")) | Some(Host::Domain("www.youtu.be")) => {
-            let video_id = video_url
-                .path()
-                .strip_prefix('/')
-                .unwrap_or(video_url.path());
-            valid_video_id(video_id).then_some(video_id.to_owned())
+    } else {
+        return None;
+    };
+
+    match video_id.filter(|id| valid_video_id(id)) {
+        Some(id) => {
+            let start_time = query("t")
+                .or_else(|| query("start"))
+                .or_else(|| {
+                    url.fragment()
+                        .and_then(|fragment| fragment.strip_prefix("t="))
+                        .map(str::to_owned)
+                })
+                .and_then(|time| parse_timestamp(&time));
+            let end_time = query("end").and_then(|time| parse_timestamp(&time));
+
+            Some(YoutubeRef::Video {
+                id,
+                playlist_id,
+                start_time,
+                end_time,
+            })
         }
+        None => playlist_id.map(YoutubeRef::Playlist),
+    }
+}
+
+/// Parses a YouTube video URL or bare video ID, returning the video ID. If
+/// the text does not refer to a video, `None` is returned.
+pub fn parse_video_url(video_url: &str) -> Option<String> {
+    match parse_youtube_url(video_url)? {
+        YoutubeRef::Video { id, .. } => Some(id),
+        _ => None,
+    }
+}
+
+/// Parses a YouTube playlist URL, returning the playlist ID. Both dedicated
+/// playlist URLs and video URLs with a `list` parameter are accepted. If the
+/// URL does not refer to a playlist, `None` is returned.
+pub fn parse_playlist_url(playlist_url: &str) -> Option<String> {
+    match parse_youtube_url(playlist_url)? {
+        YoutubeRef::Video { playlist_id, .. } => playlist_id,
+        YoutubeRef::Playlist(id) => Some(id),
+        YoutubeRef::Channel(_) => None,
+    }
+}
+
+/// Parses a YouTube channel URL in any of the handle, channel ID, custom name,
+/// or legacy username forms. If the URL does not refer to a channel, `None`
+/// is returned.
+pub fn parse_channel_url(channel_url: &str) -> Option<ChannelRef> {
+    match parse_youtube_url(channel_url)? {
+        YoutubeRef::Channel(channel) => Some(channel),
         _ => None,
     }
 }
+
+/// Decodes a percent-encoded URL path segment, returning `None` if it does
+/// not decode to valid UTF-8.
+fn decode_path_segment(segment: &str) -> Option<String> {
+    percent_decode_str(segment)
+        .decode_utf8()
+        .ok()
+        .map(|decoded| decoded.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A video ID that is valid in form.
+    const ID: &str = "dQw4w9WgXcQ";
+
+    /// Returns a reference to the video with ID `ID`.
+    fn video(start_secs: Option<u64>, playlist_id: Option<&str>) -> Option<YoutubeRef> {
+        Some(YoutubeRef::Video {
+            id: ID.to_owned(),
+            playlist_id: playlist_id.map(ToOwned::to_owned),
+            start_time: start_secs.map(Duration::from_secs),
+            end_time: None,
+        })
+    }
+
+    /// Returns a reference to a playlist.
+    fn playlist(id: &str) -> Option<YoutubeRef> {
+        Some(YoutubeRef::Playlist(id.to_owned()))
+    }
+
+    /// Returns a reference to a channel.
+    fn channel(channel: ChannelRef) -> Option<YoutubeRef> {
+        Some(YoutubeRef::Channel(channel))
+    }
+
+    #[test]
+    fn youtube_urls() {
+        let cases = [
+            // Watch pages on each host.
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                video(None, None),
+            ),
+            ("https://youtube.com/watch?v=dQw4w9WgXcQ", video(None, None)),
+            (
+                "http://m.youtube.com/watch?v=dQw4w9WgXcQ",
+                video(None, None),
+            ),
+            (
+                "https://music.youtube.com/watch?v=dQw4w9WgXcQ&feature=share",
+                video(None, None),
+            ),
+            ("https://youtu.be/dQw4w9WgXcQ", video(None, None)),
+            // Other video paths.
+            (
+                "https://www.youtube.com/shorts/dQw4w9WgXcQ",
+                video(None, None),
+            ),
+            (
+                "https://www.youtube.com/embed/dQw4w9WgXcQ",
+                video(None, None),
+            ),
+            (
+                "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ",
+                video(None, None),
+            ),
+            (
+                "https://www.youtube.com/live/dQw4w9WgXcQ?si=abc",
+                video(None, None),
+            ),
+            ("https://www.youtube.com/v/dQw4w9WgXcQ", video(None, None)),
+            // URLs without a scheme and bare IDs.
+            ("www.youtube.com/watch?v=dQw4w9WgXcQ", video(None, None)),
+            ("m.youtube.com/shorts/dQw4w9WgXcQ", video(None, None)),
+            ("youtu.be/dQw4w9WgXcQ", video(None, None)),
+            ("dQw4w9WgXcQ", video(None, None)),
+            ("  dQw4w9WgXcQ ", video(None, None)),
+            // Start times.
+            ("https://youtu.be/dQw4w9WgXcQ?t=42", video(Some(42), None)),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s",
+                video(Some(90), None),
+            ),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=1h1s",
+                video(Some(3601), None),
+            ),
+            (
+                "https://www.youtube.com/embed/dQw4w9WgXcQ?start=10",
+                video(Some(10), None),
+            ),
+            // Playlists.
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLabc123",
+                video(None, Some("PLabc123")),
+            ),
+            (
+                "https://www.youtube.com/playlist?list=PLabc123",
+                playlist("PLabc123"),
+            ),
+            (
+                "https://www.youtube.com/watch?list=PLabc123",
+                playlist("PLabc123"),
+            ),
+            (
+                "https://www.youtube.com/embed/videoseries?list=PLabc123",
+                playlist("PLabc123"),
+            ),
+            // Channels.
+            (
+                "https://www.youtube.com/@someone",
+                channel(ChannelRef::Handle("someone".to_owned())),
+            ),
+            (
+                "https://m.youtube.com/@someone/videos",
+                channel(ChannelRef::Handle("someone".to_owned())),
+            ),
+            (
+                "https://www.youtube.com/channel/UCXuqSBlHAE6Xw-yeJA0Tunw",
+                channel(ChannelRef::Id("UCXuqSBlHAE6Xw-yeJA0Tunw".to_owned())),
+            ),
+            (
+                "youtube.com/c/name",
+                channel(ChannelRef::Custom("name".to_owned())),
+            ),
+            (
+                "https://www.youtube.com/user/name",
+                channel(ChannelRef::User("name".to_owned())),
+            ),
+            (
+                "https://www.youtube.com/c/caf%C3%A9",
+                channel(ChannelRef::Custom("café".to_owned())),
+            ),
+            // Invalid references.
+            ("", None),
+            ("not a url", None),
+            ("dQw4w9WgXc", None),
+            ("dQw4w9WgXcQQ", None),
+            ("https://www.youtube.com/watch?v=short", None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQQ", None),
+            ("https://www.youtube.com/shorts/", None),
+            ("https://vimeo.com/dQw4w9WgXcQ", None),
+            ("https://notyoutube.com/watch?v=dQw4w9WgXcQ", None),
+            ("ftp://youtube.com/watch?v=dQw4w9WgXcQ", None),
+            ("https://www.youtube.com/", None),
+            ("https://www.youtube.com/feed/subscriptions", None),
+            ("https://www.youtube.com/c/a+b", None),
+            ("https://www.youtube.com/c/a%2Bb", None),
+            ("https://www.youtube.com/c/a&b", None),
+            ("https://www.youtube.com/c/a%26b", None),
+            ("https://www.youtube.com/@a%26b", None),
+            ("https://www.youtube.com/c/%FF", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_youtube_url(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn path_segments() {
+        let cases = [
+            ("name", Some("name")),
+            ("caf%C3%A9", Some("café")),
+            ("a+b", Some("a+b")),
+            ("a%2Bb", Some("a+b")),
+            ("a%26b&c", Some("a&b&c")),
+            ("a%20b", Some("a b")),
+            ("a%3Db%3F", Some("a=b?")),
+            ("%FF", None),
+        ];
+
+        for (segment, expected) in cases {
+            assert_eq!(
+                decode_path_segment(segment).as_deref(),
+                expected,
+                "{:?}",
+                segment
+            );
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Expansion of YouTube playlists into their entries.
+
+#[cfg(windows)]
+use crate::constants::*;
+use crate::services::{CommandLineExtractor, DownloadError};
+use anyhow::Result;
+use serde::Deserialize;
+use std::env::current_exe;
+use std::path::Path;
+use tokio::process::Command;
+
+/// A single video in a playlist.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlaylistEntry {
+    /// The 1-based position of the video in the playlist.
+    pub index: usize,
+    /// The ID of the video.
+    pub video_id: String,
+    /// The title of the video, if known.
+    pub title: Option<String>,
+}
+
+/// A YouTube playlist and its entries.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Playlist {
+    /// The ID of the playlist.
+    pub id: String,
+    /// The title of the playlist, if known.
+    pub title: Option<String>,
+    /// The videos in the playlist, in playlist order.
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// A playlist entry as reported by youtube-dl's flat-playlist JSON output.
+#[derive(Debug, Clone, Deserialize)]
+struct FlatPlaylistEntry {
+    /// The ID of the video.
+    id: String,
+    /// The title of the video.
+    title: Option<String>,
+}
+
+/// A playlist as reported by youtube-dl's flat-playlist JSON output.
+#[derive(Debug, Clone, Deserialize)]
+struct FlatPlaylist {
+    /// The title of the playlist.
+    title: Option<String>,
+    /// The videos in the playlist. Entries that could not be resolved are
+    /// reported as `null`.
+    #[serde(default)]
+    entries: Vec<Option<FlatPlaylistEntry>>,
+}
+
+/// Fetches the entries of a playlist using a youtube-dl compatible
+/// extractor, without resolving each individual video.
+pub async fn fetch_playlist(cli: &CommandLineExtractor, playlist_id: &str) -> Result<Playlist> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("--flat-playlist")
+        .arg("--dump-single-json")
+        .arg(format!(
+            "https://www.youtube.com/playlist?list={}",
+            playlist_id
+        ));
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = cmd.current_dir(here).kill_on_drop(true).output().await?;
+
+    if res.status.success() {
+        let flat_playlist = serde_json::from_slice::<FlatPlaylist>(&res.stdout)?;
+        let entries = flat_playlist
+            .entries
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                entry.map(|entry| PlaylistEntry {
+                    index: i + 1,
+                    video_id: entry.id,
+                    title: entry.title,
+                })
+            })
+            .collect();
+
+        Ok(Playlist {
+            id: playlist_id.to_owned(),
+            title: flat_playlist.title,
+            entries,
+        })
+    } else {
+        Err(DownloadError::new(
+            "failed to fetch playlist entries",
+            String::from_utf8_lossy(&res.stderr),
+        )
+        .into())
+    }
+}
@@ -0,0 +1,390 @@
+//! Parsing of progress output from the youtube-dl and ffmpeg binaries.
+
+use crate::services::{parse_timestamp, RetryStatus};
+use crate::types::*;
+use std::fmt::Display;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The phase a download operation is in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DownloadPhase {
+    /// Fetching video information before the download begins.
+    #[default]
+    Preparing,
+    /// Downloading content from YouTube.
+    Downloading,
+    /// Cutting the requested time range from downloaded content with ffmpeg.
+    Clipping,
+    /// Converting downloaded content with ffmpeg.
+    Converting,
+    /// Writing metadata tags into the downloaded file.
+    Tagging,
+    /// Splitting the downloaded file into one file per chapter.
+    Splitting,
+}
+
+impl DownloadPhase {
+    /// Gets a human-readable name of the phase.
+    pub const fn phase_name(self) -> &'static str {
+        match self {
+            Self::Preparing => "Preparing",
+            Self::Downloading => "Downloading",
+            Self::Clipping => "Clipping",
+            Self::Converting => "Converting",
+            Self::Tagging => "Tagging",
+            Self::Splitting => "Splitting",
+        }
+    }
+}
+
+/// A progress update from a running download operation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    /// The phase the download is currently in.
+    pub phase: DownloadPhase,
+    /// The completion percentage of the current phase, if known.
+    pub percent: Option<f64>,
+    /// The current transfer or processing speed, if known.
+    pub speed: Option<String>,
+    /// The estimated time remaining in the current phase, if known.
+    pub eta: Option<Duration>,
+    /// The pending retry of the current phase, if it failed transiently.
+    pub retry: Option<RetryStatus>,
+}
+
+impl DownloadProgress {
+    /// Creates a progress update with nothing known but the phase.
+    pub fn new(phase: DownloadPhase) -> Self {
+        Self {
+            phase,
+            ..Self::default()
+        }
+    }
+}
+
+/// Formats a duration as `MM:SS`, or `HH:MM:SS` if it is an hour or longer.
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl Display for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}...", self.phase.phase_name())?;
+
+        if let Some(retry) = self.retry {
+            return write!(
+                f,
+                " retry {}/{} in {}s",
+                retry.attempt,
+                retry.max_attempts,
+                retry.remaining.as_secs_f64().ceil()
+            );
+        }
+
+        if let Some(percent) = self.percent {
+            write!(f, " {:.1}%", percent)?;
+        }
+
+        if let Some(speed) = &self.speed {
+            write!(f, " at {}", speed)?;
+        }
+
+        if let Some(eta) = self.eta {
+            write!(f, ", {} remaining", format_eta(eta))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The sending half of a download progress event stream.
+pub type ProgressSender = UnboundedSender<DownloadProgress>;
+
+/// Returns the whitespace-delimited token following `key` in `line`.
+fn token_after<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(key)? + key.len();
+    line[start..].split_whitespace().next()
+}
+
+/// Parses a youtube-dl progress line, e.g.
+/// `[download]  42.3% of 120.00MiB at  3.00MiB/s ETA 00:31`. Lines reporting
+/// that youtube-dl has started merging or post-processing with ffmpeg are
+/// reported as the start of the conversion phase.
+pub fn parse_youtube_dl_progress(line: &str) -> Option<DownloadProgress> {
+    let line = line.trim();
+
+    if line.starts_with("[ffmpeg]") || line.starts_with("[Merger]") {
+        return Some(DownloadProgress::new(DownloadPhase::Converting));
+    }
+
+    let rest = line.strip_prefix("[download]")?.trim_start();
+    let (percent, rest) = rest.split_once('%')?;
+    let percent = percent.trim().parse::<f64>().ok()?;
+
+    let speed = token_after(rest, " at ")
+        .filter(|speed| !speed.starts_with("Unknown"))
+        .map(ToOwned::to_owned);
+    let eta = token_after(rest, " ETA ").and_then(parse_timestamp);
+
+    Some(DownloadProgress {
+        phase: DownloadPhase::Downloading,
+        percent: Some(percent.clamp(0.0, 100.0)),
+        speed,
+        eta,
+        retry: None,
+    })
+}
+
+/// A stateful parser for ffmpeg's stderr output. The total input duration is
+/// read from the header so that subsequent `time=` lines can be converted to
+/// a completion percentage.
+#[derive(Debug, Clone)]
+pub struct FfmpegProgressParser {
+    /// The phase the progress updates are reported in.
+    phase: DownloadPhase,
+    /// The range of the input being written, which determines the duration
+    /// of the output.
+    clip: ClipRange,
+    /// The total duration of the output, once known.
+    duration: Option<Duration>,
+}
+
+impl FfmpegProgressParser {
+    /// Creates a new parser for an ffmpeg invocation that writes `clip` of
+    /// its input, reporting progress in `phase`.
+    pub fn new(phase: DownloadPhase, clip: &ClipRange) -> Self {
+        Self {
+            phase,
+            clip: *clip,
+            duration: None,
+        }
+    }
+
+    /// Feeds a line of ffmpeg output to the parser, returning a progress update
+    /// if the line reports one.
+    pub fn parse_line(&mut self, line: &str) -> Option<DownloadProgress> {
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("Duration:") {
+            let duration = rest.split(',').next().and_then(parse_timestamp);
+
+            if let Some(duration) = duration {
+                let end = self.clip.end.map_or(duration, |end| end.min(duration));
+                self.duration = Some(end.saturating_sub(self.clip.start.unwrap_or_default()));
+            }
+
+            return None;
+        }
+
+        let time = token_after(line, "time=").and_then(parse_timestamp)?;
+        let speed = token_after(line, "speed=")
+            .filter(|speed| *speed != "N/A")
+            .map(ToOwned::to_owned);
+        let speed_factor = speed
+            .as_deref()
+            .and_then(|speed| speed.strip_suffix('x'))
+            .and_then(|speed| speed.parse::<f64>().ok())
+            .filter(|speed| *speed > 0.0);
+
+        let (percent, eta) = match self.duration.filter(|duration| !duration.is_zero()) {
+            Some(duration) => {
+                let fraction = (time.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0);
+                let remaining = duration.saturating_sub(time).as_secs_f64();
+                let eta = speed_factor.map(|factor| Duration::from_secs_f64(remaining / factor));
+                (Some(fraction * 100.0), eta)
+            }
+            None => (None, None),
+        };
+
+        Some(DownloadProgress {
+            phase: self.phase,
+            percent,
+            speed,
+            eta,
+            retry: None,
+        })
+    }
+}
+
+/// Reads from `reader` until end of stream, calling `on_line` with each line.
+/// Lines may be terminated by either `\n` or `\r`, since both binaries use
+/// carriage returns to redraw progress in place. The full output is returned.
+pub async fn read_lines<R, F>(mut reader: R, mut on_line: F) -> std::io::Result<String>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(&str),
+{
+    let mut output = Vec::new();
+    let mut line_start = 0;
+    let mut buf = [0; 4096];
+
+    loop {
+        let n = reader.read(&mut buf).await?;
+
+        if n == 0 {
+            break;
+        }
+
+        output.extend_from_slice(&buf[..n]);
+
+        while let Some(offset) = output[line_start..]
+            .iter()
+            .position(|b| *b == b'\n' || *b == b'\r')
+        {
+            let line_end = line_start + offset;
+            on_line(&String::from_utf8_lossy(&output[line_start..line_end]));
+            line_start = line_end + 1;
+        }
+    }
+
+    if line_start < output.len() {
+        on_line(&String::from_utf8_lossy(&output[line_start..]));
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Progress lines captured from youtube-dl downloads.
+    const YOUTUBE_DL_LOG: &str = "\
+[youtube] dQw4w9WgXcQ: Downloading webpage
+[download] Destination: Rick Astley - Never Gonna Give You Up-dQw4w9WgXcQ.f137.mp4
+[download]   0.0% of ~  1.20GiB at Unknown speed ETA Unknown ETA
+[download]  42.3% of 120.00MiB at  3.00MiB/s ETA 00:31
+[download]  99.9% of ~ 85.43MiB at 10.51MiB/s ETA 01:02:03
+[download] 100% of 120.00MiB in 00:40
+[Merger] Merging formats into \"Rick Astley - Never Gonna Give You Up-dQw4w9WgXcQ.mp4\"
+[ffmpeg] Destination: Rick Astley - Never Gonna Give You Up-dQw4w9WgXcQ.mp3";
+
+    /// Output captured from an ffmpeg conversion.
+    const FFMPEG_LOG: &str = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'audio.m4a':
+  Duration: 00:03:20.00, start: 0.000000, bitrate: 128 kb/s
+size=N/A time=N/A bitrate=N/A speed=N/A
+size=    1024kB time=00:01:40.00 bitrate= 134.2kbits/s speed=10x
+size=    2048kB time=00:03:20.00 bitrate= 134.2kbits/s speed=N/A";
+
+    /// Builds an expected progress update.
+    fn progress(
+        phase: DownloadPhase,
+        percent: Option<f64>,
+        speed: Option<&str>,
+        eta: Option<u64>,
+    ) -> DownloadProgress {
+        DownloadProgress {
+            phase,
+            percent,
+            speed: speed.map(ToOwned::to_owned),
+            eta: eta.map(Duration::from_secs),
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn youtube_dl_log() {
+        let updates = YOUTUBE_DL_LOG
+            .lines()
+            .filter_map(parse_youtube_dl_progress)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            updates,
+            vec![
+                progress(DownloadPhase::Downloading, Some(0.0), None, None),
+                progress(
+                    DownloadPhase::Downloading,
+                    Some(42.3),
+                    Some("3.00MiB/s"),
+                    Some(31)
+                ),
+                progress(
+                    DownloadPhase::Downloading,
+                    Some(99.9),
+                    Some("10.51MiB/s"),
+                    Some(3723)
+                ),
+                progress(DownloadPhase::Downloading, Some(100.0), None, None),
+                DownloadProgress::new(DownloadPhase::Converting),
+                DownloadProgress::new(DownloadPhase::Converting),
+            ]
+        );
+    }
+
+    #[test]
+    fn youtube_dl_non_progress_lines() {
+        for line in [
+            "",
+            "[download] Destination: video.mp4",
+            "[download] video.mp4 has already been downloaded",
+            "[youtube] dQw4w9WgXcQ: Downloading webpage",
+            "ERROR: Private video",
+        ] {
+            assert_eq!(parse_youtube_dl_progress(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn ffmpeg_log() {
+        let mut parser =
+            FfmpegProgressParser::new(DownloadPhase::Converting, &ClipRange::default());
+        let updates = FFMPEG_LOG
+            .lines()
+            .filter_map(|line| parser.parse_line(line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            updates,
+            vec![
+                progress(DownloadPhase::Converting, Some(50.0), Some("10x"), Some(10)),
+                progress(DownloadPhase::Converting, Some(100.0), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn ffmpeg_unknown_duration() {
+        let mut parser =
+            FfmpegProgressParser::new(DownloadPhase::Converting, &ClipRange::default());
+
+        assert_eq!(parser.parse_line("  Duration: N/A, bitrate: N/A"), None);
+        assert_eq!(
+            parser.parse_line("size=  1kB time=00:00:15.00 bitrate= 1kbits/s speed=2x"),
+            Some(progress(DownloadPhase::Converting, None, Some("2x"), None))
+        );
+    }
+
+    #[test]
+    fn ffmpeg_clipped_duration() {
+        let clip = ClipRange {
+            start: Some(Duration::from_secs(60)),
+            end: Some(Duration::from_secs(90)),
+            ..ClipRange::default()
+        };
+        let mut parser = FfmpegProgressParser::new(DownloadPhase::Clipping, &clip);
+
+        assert_eq!(
+            parser.parse_line("  Duration: 00:10:00.00, start: 0.000000, bitrate: 128 kb/s"),
+            None
+        );
+        assert_eq!(
+            parser.parse_line("size=  1kB time=00:00:15.00 bitrate= 1kbits/s speed=3x"),
+            Some(progress(
+                DownloadPhase::Clipping,
+                Some(50.0),
+                Some("3x"),
+                Some(5)
+            ))
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! A persistent queue of download jobs, run with a concurrency limit.
+
+use crate::constants::*;
+use crate::services::{
+    download, remove_stale_staging_dirs, CancelHandle, Cancelled, DownloadArchive, DownloadFailure,
+    DownloadOutcome, DownloadPhase, DownloadProgress, DownloadRequest, FileCollision,
+};
+use crate::types::*;
+use anyhow::Result;
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env::current_exe;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tokio::fs;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::{watch, Notify};
+
+/// Returns the path to the queue file.
+fn queue_file_path() -> Result<PathBuf> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let joined = Path::new(here).join(QUEUE_FILE_NAME);
+    Ok(joined)
+}
+
+/// The unique identifier of a job in the download queue.
+pub type JobId = u64;
+
+/// The state of a job in the download queue.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    /// The job is waiting to be run.
+    Pending,
+    /// The job is running, with the most recent progress update.
+    Running(DownloadProgress),
+    /// The job completed successfully with the given outcome.
+    Completed(DownloadOutcome),
+    /// The job failed.
+    Failed(DownloadFailure),
+    /// The job is waiting for the user to decide what to do about the
+    /// existing file at the given path.
+    Conflict(PathBuf),
+    /// The job was cancelled by the user.
+    Cancelled,
+}
+
+impl JobState {
+    /// Returns whether a job in this state can be retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Failed(failure) => failure.kind.is_retryable(),
+            Self::Cancelled => true,
+            Self::Pending | Self::Running(_) | Self::Completed(_) | Self::Conflict(_) => false,
+        }
+    }
+}
+
+/// A job in the download queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueJob {
+    /// The job's identifier.
+    pub id: JobId,
+    /// The download to perform.
+    pub request: DownloadRequest,
+    /// The current state of the job.
+    pub state: JobState,
+    /// Whether the job was running when the application last closed, and
+    /// will resume from its partial files when it next runs.
+    pub interrupted: bool,
+}
+
+/// A queue job as persisted to the queue file. Only pending, running and
+/// failed jobs are persisted; jobs that were running when the application
+/// closed are restored as pending and marked as interrupted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct PersistedJob {
+    /// The download to perform.
+    #[serde(flatten)]
+    request: DownloadRequest,
+    /// The failure, if the job failed.
+    #[serde(default)]
+    failure: Option<DownloadFailure>,
+    /// Whether the job was running when the queue was persisted.
+    #[serde(default)]
+    interrupted: bool,
+    /// The error message, if the job failed, as persisted before failures
+    /// were classified.
+    #[serde(default, skip_serializing)]
+    error: Option<String>,
+}
+
+/// The state of the download queue.
+#[derive(Debug, Default)]
+pub struct QueueState {
+    /// All jobs in the queue, in the order they were added.
+    jobs: Vec<QueueJob>,
+    /// Cancellation handles for running jobs.
+    cancel_handles: HashMap<JobId, CancelHandle>,
+    /// The identifier to give the next job.
+    next_id: JobId,
+    /// The maximum number of jobs to run at once.
+    max_concurrent: usize,
+}
+
+impl QueueState {
+    /// Returns all jobs in the queue, in the order they were added.
+    pub fn jobs(&self) -> &[QueueJob] {
+        &self.jobs
+    }
+
+    /// Returns a mutable reference to the job with the given ID.
+    fn job_mut(&mut self, id: JobId) -> Option<&mut QueueJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Adds a job to the end of the queue.
+    fn push(&mut self, request: DownloadRequest, state: JobState, interrupted: bool) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(QueueJob {
+            id,
+            request,
+            state,
+            interrupted,
+        });
+        id
+    }
+
+    /// Returns the number of running jobs.
+    fn running_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Running(_)))
+            .count()
+    }
+
+    /// Marks the next pending job as running and returns it, along with a
+    /// handle to cancel it, if the concurrency limit allows.
+    fn start_next(&mut self) -> Option<(QueueJob, CancelHandle)> {
+        if self.running_count() >= self.max_concurrent {
+            return None;
+        }
+
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.state == JobState::Pending)?;
+        job.state = JobState::Running(DownloadProgress::new(DownloadPhase::Preparing));
+        job.interrupted = false;
+        let job = job.clone();
+
+        let cancel = CancelHandle::new();
+        self.cancel_handles.insert(job.id, cancel.clone());
+
+        Some((job, cancel))
+    }
+
+    /// Returns the jobs that should be persisted to the queue file.
+    fn persisted_jobs(&self) -> Vec<PersistedJob> {
+        self.jobs
+            .iter()
+            .filter_map(|job| {
+                let (failure, interrupted) = match &job.state {
+                    JobState::Pending | JobState::Conflict(_) => (None, job.interrupted),
+                    JobState::Running(_) => (None, true),
+                    JobState::Failed(failure) => (Some(failure.clone()), false),
+                    JobState::Completed(_) | JobState::Cancelled => return None,
+                };
+
+                Some(PersistedJob {
+                    request: job.request.clone(),
+                    failure,
+                    interrupted,
+                    error: None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A handle to the download queue. Clones of a handle all refer to the same
+/// queue.
+#[derive(Debug, Clone)]
+pub struct DownloadQueue {
+    /// The queue state, along with the means to notify subscribers of changes.
+    state: Rc<watch::Sender<QueueState>>,
+    /// Wakes the queue runner when jobs may need to be started.
+    wake: Rc<Notify>,
+    /// The archive of completed downloads.
+    archive: DownloadArchive,
+}
+
+impl PartialEq for DownloadQueue {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl DownloadQueue {
+    /// Loads the persisted queue from the file, running at most
+    /// `max_concurrent` jobs at once. Jobs consult and update `archive`.
+    pub async fn load(max_concurrent: usize, archive: DownloadArchive) -> Result<Self> {
+        let queue_path = queue_file_path()?;
+        let mut state = QueueState {
+            max_concurrent: max_concurrent.max(1),
+            ..QueueState::default()
+        };
+
+        if queue_path.exists() {
+            let queue_bytes = fs::read(queue_path).await?;
+            let persisted_jobs = serde_json::from_slice::<Vec<PersistedJob>>(&queue_bytes)?;
+
+            for job in persisted_jobs {
+                let failure = job
+                    .failure
+                    .or_else(|| job.error.map(DownloadFailure::unknown));
+                let job_state = match failure {
+                    Some(failure) => JobState::Failed(failure),
+                    None => JobState::Pending,
+                };
+                state.push(job.request, job_state, job.interrupted);
+            }
+        }
+
+        // Staging directories are only kept for jobs that may still resume.
+        let staged_requests = state
+            .jobs
+            .iter()
+            .map(|job| &job.request)
+            .collect::<Vec<_>>();
+        let _ = remove_stale_staging_dirs(&staged_requests).await;
+
+        Ok(Self {
+            state: Rc::new(watch::Sender::new(state)),
+            wake: Rc::new(Notify::new()),
+            archive,
+        })
+    }
+
+    /// Saves the pending and failed jobs to the file.
+    async fn save(&self) -> Result<()> {
+        let queue_path = queue_file_path()?;
+        let persisted_jobs = self.state.borrow().persisted_jobs();
+        let queue_bytes = serde_json::to_vec(&persisted_jobs)?;
+        fs::write(queue_path, queue_bytes).await?;
+        Ok(())
+    }
+
+    /// Subscribes to changes in the queue state.
+    pub fn subscribe(&self) -> watch::Receiver<QueueState> {
+        self.state.subscribe()
+    }
+
+    /// Returns a snapshot of all jobs in the queue.
+    pub fn jobs(&self) -> Vec<QueueJob> {
+        self.state.borrow().jobs.clone()
+    }
+
+    /// Modifies the queue state, notifying subscribers and waking the runner.
+    fn modify<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut QueueState) -> T,
+    {
+        let mut value = None;
+        self.state.send_modify(|state| value = Some(f(state)));
+        self.wake.notify_one();
+        value.unwrap()
+    }
+
+    /// Adds a download job to the end of the queue.
+    pub fn enqueue(&self, request: DownloadRequest) -> JobId {
+        self.modify(|state| state.push(request, JobState::Pending, false))
+    }
+
+    /// Cancels a job. Pending jobs are marked cancelled immediately, while
+    /// running jobs are stopped and marked cancelled once they have shut down.
+    pub fn cancel(&self, id: JobId) {
+        self.modify(|state| {
+            if let Some(cancel) = state.cancel_handles.get(&id) {
+                cancel.cancel();
+            } else if let Some(job) = state.job_mut(id) {
+                if job.state == JobState::Pending {
+                    job.state = JobState::Cancelled;
+                }
+            }
+        });
+    }
+
+    /// Returns a cancelled job, or a job that failed for a reason that
+    /// retrying could overcome, to the pending state.
+    pub fn retry(&self, id: JobId) {
+        self.modify(|state| {
+            if let Some(job) = state.job_mut(id) {
+                if job.state.is_retryable() {
+                    job.state = JobState::Pending;
+                }
+            }
+        });
+    }
+
+    /// Resolves a job's conflict with an existing file. The job is either
+    /// marked as skipped, or returned to the pending state to run again with
+    /// the given collision policy.
+    pub fn resolve_conflict(&self, id: JobId, resolution: Option<CollisionPolicy>) {
+        self.modify(|state| {
+            if let Some(job) = state.job_mut(id) {
+                let JobState::Conflict(path) = &job.state else {
+                    return;
+                };
+
+                job.state = match resolution {
+                    Some(policy) => {
+                        job.request.collision_policy = policy;
+                        JobState::Pending
+                    }
+                    None => JobState::Completed(DownloadOutcome::skipped(path.clone())),
+                };
+            }
+        });
+    }
+
+    /// Removes a job from the queue, unless it is running.
+    pub fn remove(&self, id: JobId) {
+        self.modify(|state| {
+            state
+                .jobs
+                .retain(|job| job.id != id || matches!(job.state, JobState::Running(_)));
+        });
+    }
+
+    /// Removes all completed and cancelled jobs from the queue.
+    pub fn clear_finished(&self) {
+        self.modify(|state| {
+            state
+                .jobs
+                .retain(|job| !matches!(job.state, JobState::Completed(_) | JobState::Cancelled));
+        });
+    }
+
+    /// Sets the maximum number of jobs to run at once.
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        self.modify(|state| state.max_concurrent = max_concurrent.max(1));
+    }
+
+    /// Runs a single job to completion, recording its progress and outcome.
+    async fn run_job(&self, job: QueueJob, cancel: CancelHandle) {
+        let (progress_tx, mut progress_rx) = unbounded_channel();
+        let download_task = async {
+            let progress = progress_tx;
+            download(&job.request, &self.archive, &progress, &cancel).await
+        };
+        let progress_task = async {
+            while let Some(update) = progress_rx.recv().await {
+                self.state.send_modify(|state| {
+                    if let Some(job) = state.job_mut(job.id) {
+                        job.state = JobState::Running(update);
+                    }
+                });
+            }
+        };
+        let (res, ()) = tokio::join!(download_task, progress_task);
+
+        let job_state = match res {
+            Ok(outcome) => JobState::Completed(outcome),
+            Err(err) if err.is::<Cancelled>() => JobState::Cancelled,
+            Err(err) => match err.downcast::<FileCollision>() {
+                Ok(collision) => JobState::Conflict(collision.path),
+                Err(err) => JobState::Failed(DownloadFailure::from_error(&err)),
+            },
+        };
+
+        self.modify(|state| {
+            state.cancel_handles.remove(&job.id);
+
+            if let Some(job) = state.job_mut(job.id) {
+                job.state = job_state;
+            }
+        });
+    }
+
+    /// Runs the queue forever, starting pending jobs as the concurrency limit
+    /// allows and persisting the queue whenever it changes.
+    pub async fn run(&self) {
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            loop {
+                let mut next = None;
+                self.state.send_if_modified(|state| {
+                    next = state.start_next();
+                    next.is_some()
+                });
+
+                match next {
+                    Some((job, cancel)) => running.push(self.run_job(job, cancel)),
+                    None => break,
+                }
+            }
+
+            let _ = self.save().await;
+
+            tokio::select! {
+                Some(()) = running.next(), if !running.is_empty() => {}
+                () = self.wake.notified() => {}
+            }
+        }
+    }
+}
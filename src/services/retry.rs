@@ -0,0 +1,91 @@
+//! Retrying of operations that fail transiently.
+
+use crate::services::{CancelHandle, Cancelled, DownloadFailure};
+use crate::types::*;
+use anyhow::Result;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// A pending retry of a failed operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryStatus {
+    /// The attempt that will be made, counting from 1.
+    pub attempt: u32,
+    /// The maximum number of attempts.
+    pub max_attempts: u32,
+    /// The time remaining until the attempt is made.
+    pub remaining: Duration,
+}
+
+/// Returns whether an error is transient, such that performing the operation
+/// that produced it again could succeed.
+fn is_transient(err: &anyhow::Error) -> bool {
+    !err.is::<Cancelled>() && DownloadFailure::from_error(err).kind.is_transient()
+}
+
+/// Returns the delay before the given retry, with the policy's jitter
+/// applied.
+fn retry_delay(policy: &RetryPolicy, retry: u32) -> Duration {
+    let delay = policy.backoff_delay(retry);
+    let jitter = f64::from(policy.jitter_percent.min(100)) / 100.0;
+
+    if jitter == 0.0 {
+        return delay;
+    }
+
+    delay.mul_f64(rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter))
+}
+
+/// Runs an operation, performing it again according to `policy` for as long
+/// as it fails transiently. While waiting to retry, `on_wait` is called once a
+/// second with the time remaining. If cancellation is requested through
+/// `cancel` during a wait, a `Cancelled` error is returned.
+pub async fn retry<T, F, Fut, W>(
+    policy: &RetryPolicy,
+    cancel: Option<&CancelHandle>,
+    mut on_wait: W,
+    mut operation: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    W: FnMut(RetryStatus),
+{
+    let mut attempt = 1;
+
+    loop {
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        if attempt >= policy.max_attempts || !is_transient(&err) {
+            return Err(err);
+        }
+
+        let mut remaining = retry_delay(policy, attempt);
+        attempt += 1;
+
+        while !remaining.is_zero() {
+            on_wait(RetryStatus {
+                attempt,
+                max_attempts: policy.max_attempts,
+                remaining,
+            });
+
+            let step = remaining.min(Duration::from_secs(1));
+
+            match cancel {
+                Some(cancel) => tokio::select! {
+                    () = sleep(step) => {}
+                    () = cancel.cancelled() => return Err(Cancelled.into()),
+                },
+                None => sleep(step).await,
+            }
+
+            remaining -= step;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Staging directories in which downloads are assembled before being moved to
+//! their output paths. Staging directories are stable across application
+//! restarts, so that interrupted downloads can be resumed, and are locked
+//! while a download uses them.
+
+use crate::constants::*;
+use crate::services::{DownloadError, DownloadRequest};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::env::current_exe;
+use std::ffi::OsString;
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Returns the path to the directory containing all staging directories.
+fn staging_root_path() -> Result<PathBuf> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+    let joined = Path::new(here).join(STAGING_DIR_NAME);
+    Ok(joined)
+}
+
+/// Hashes text with the 64-bit FNV-1a algorithm. Unlike the standard library's
+/// hashers, the result is guaranteed not to change between releases, so it
+/// can be used in persisted names.
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The name of the file each staging directory is locked through.
+const LOCK_FILE_NAME: &str = ".lock";
+
+/// Returns the name of the staging directory for a download. Downloads of
+/// the same content in the same format, clip range and output path share a
+/// staging directory, so that an interrupted download can be resumed. The
+/// rest of the request may contain characters that are not valid in file
+/// names, so it is hashed.
+fn staging_dir_name(request: &DownloadRequest) -> String {
+    let key = request.archive_key();
+    let clip_mode = if request.clip.is_clipped() {
+        request.clip.mode.mode_name()
+    } else {
+        ""
+    };
+    let identity = [
+        key.format.as_str(),
+        clip_mode,
+        &request.output_directory.to_string_lossy(),
+        &request.filename_template,
+    ]
+    .join("\n");
+
+    format!(
+        "{}-{}-{:016x}",
+        key.video_id,
+        key.content_type.type_name().to_lowercase(),
+        stable_hash(&identity)
+    )
+}
+
+/// Opens the lock file of a staging directory and tries to lock it
+/// exclusively. Returns `None` if another download holds the lock, whether
+/// in this or another instance of the application.
+fn try_lock_staging_dir(path: &Path) -> Result<Option<File>> {
+    let lock = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.join(LOCK_FILE_NAME))?;
+
+    match lock.try_lock() {
+        Ok(()) => Ok(Some(lock)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(err)) => Err(err.into()),
+    }
+}
+
+/// The staging directory of a running download. The directory is locked for
+/// as long as this is held, so that it is not removed or written to by
+/// another download.
+#[derive(Debug)]
+pub struct StagingDir {
+    /// The path of the directory.
+    path: PathBuf,
+    /// The locked lock file within the directory.
+    lock: File,
+}
+
+impl StagingDir {
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Removes the directory along with everything in it.
+    pub async fn remove(self) -> Result<()> {
+        // Open files cannot be removed on Windows, so the lock is released
+        // first.
+        drop(self.lock);
+
+        match fs::remove_dir_all(&self.path).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Creates and locks the staging directory for a download, keeping any
+/// partial files left by an earlier attempt at the download. Fails if the
+/// same download is already running.
+pub async fn create_staging_dir(request: &DownloadRequest) -> Result<StagingDir> {
+    let path = staging_root_path()?.join(staging_dir_name(request));
+    fs::create_dir_all(&path).await?;
+
+    match try_lock_staging_dir(&path)? {
+        Some(lock) => Ok(StagingDir { path, lock }),
+        None => Err(DownloadError::new(
+            "the same download is already running",
+            path.display().to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Removes all staging directories except those of the given downloads and
+/// those locked by running downloads.
+pub async fn remove_stale_staging_dirs(keep: &[&DownloadRequest]) -> Result<()> {
+    let root = staging_root_path()?;
+
+    if !root.exists() {
+        return Ok(());
+    }
+
+    let keep = keep
+        .iter()
+        .map(|request| staging_dir_name(request))
+        .collect::<HashSet<_>>();
+    let mut entries = fs::read_dir(root).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        if keep.contains(entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
+
+        let path = entry.path();
+
+        if let Ok(Some(lock)) = try_lock_staging_dir(&path) {
+            drop(lock);
+            let _ = fs::remove_dir_all(path).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the path a staged file is copied to alongside `output_path` when
+/// it cannot be moved there directly, such as `.Title.mp4.part` alongside
+/// `Title.mp4`.
+pub fn staged_copy_path(output_path: &Path) -> PathBuf {
+    let mut copy_name = OsString::from(".");
+    copy_name.push(output_path.file_name().unwrap_or_default());
+    copy_name.push(".part");
+    output_path.with_file_name(copy_name)
+}
+
+/// A partial copy of a staged file, which is removed when dropped unless the
+/// copy was moved into place, so that it is not left behind if the move
+/// fails or is interrupted.
+struct PartialCopy<'a> {
+    /// The path of the copy.
+    path: &'a Path,
+    /// Whether the copy was moved into place.
+    moved: bool,
+}
+
+impl Drop for PartialCopy<'_> {
+    fn drop(&mut self) {
+        if !self.moved {
+            let _ = std::fs::remove_file(self.path);
+        }
+    }
+}
+
+/// Moves a file from a staging directory to its output path, atomically
+/// replacing any file already there. If the two are on different file
+/// systems, the file is first copied alongside the output path and then
+/// moved into place, and the original removed.
+pub async fn move_staged_file(staged_path: &Path, output_path: &Path) -> Result<()> {
+    if fs::rename(staged_path, output_path).await.is_ok() {
+        return Ok(());
+    }
+
+    let copy_path = staged_copy_path(output_path);
+    let mut copy = PartialCopy {
+        path: &copy_path,
+        moved: false,
+    };
+    fs::copy(staged_path, &copy_path).await?;
+    fs::rename(&copy_path, output_path).await?;
+    copy.moved = true;
+
+    fs::remove_file(staged_path).await?;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Listing of the subtitle and caption tracks available for a video.
+
+#[cfg(windows)]
+use crate::constants::*;
+use crate::services::{CommandLineExtractor, DownloadError};
+use crate::types::*;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env::current_exe;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Pseudo-languages youtube-dl lists among subtitles that are not subtitle
+/// tracks.
+const IGNORED_SUBTITLE_LANGUAGES: [&str; 1] = ["live_chat"];
+
+/// A file in which a subtitle track is available, as reported by youtube-dl.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct SubtitleFile {
+    /// The file format.
+    ext: Option<String>,
+    /// The name of the track's language.
+    name: Option<String>,
+}
+
+/// The subset of youtube-dl's `-J` output describing available subtitles.
+#[derive(Debug, Clone, Deserialize)]
+struct SubtitlesInfo {
+    /// The subtitle tracks uploaded with the video, by language.
+    #[serde(default)]
+    subtitles: BTreeMap<String, Vec<SubtitleFile>>,
+    /// The automatically generated caption tracks, by language.
+    #[serde(default)]
+    automatic_captions: BTreeMap<String, Vec<SubtitleFile>>,
+}
+
+/// A subtitle track available for a video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct AvailableSubtitle {
+    /// The track.
+    pub track: SubtitleTrack,
+    /// The name of the track's language, or its code if the name is unknown.
+    pub name: String,
+    /// The formats the track is available in.
+    pub formats: Vec<String>,
+}
+
+impl AvailableSubtitle {
+    /// Describes the track in a human-readable way, e.g.
+    /// `English (en, auto-generated)`.
+    pub fn description(&self) -> String {
+        let kind = if self.track.automatic {
+            ", auto-generated"
+        } else {
+            ""
+        };
+
+        format!("{} ({}{})", self.name, self.track.language, kind)
+    }
+}
+
+/// Collects the tracks of one kind from youtube-dl's map of languages to
+/// files.
+fn available_tracks(
+    files: BTreeMap<String, Vec<SubtitleFile>>,
+    automatic: bool,
+) -> impl Iterator<Item = AvailableSubtitle> {
+    files
+        .into_iter()
+        .filter(|(language, files)| {
+            !files.is_empty() && !IGNORED_SUBTITLE_LANGUAGES.contains(&language.as_str())
+        })
+        .map(move |(language, files)| AvailableSubtitle {
+            name: files
+                .iter()
+                .find_map(|file| file.name.clone())
+                .unwrap_or_else(|| language.clone()),
+            formats: files.into_iter().filter_map(|file| file.ext).collect(),
+            track: SubtitleTrack {
+                language,
+                automatic,
+            },
+        })
+}
+
+/// Lists the subtitle tracks available for a video using a youtube-dl
+/// compatible extractor. Tracks uploaded with the video are listed first,
+/// followed by automatically generated captions, each ordered by language
+/// code.
+pub async fn fetch_subtitles(
+    cli: &CommandLineExtractor,
+    video_id: &str,
+) -> Result<Vec<AvailableSubtitle>> {
+    let current = current_exe()?;
+    let here = current.parent().unwrap_or(Path::new("."));
+
+    let mut cmd = Command::new(cli.binary_path()?);
+    cmd.arg("-J").arg(video_id);
+
+    #[cfg(windows)]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW_FLAG);
+    }
+
+    let res = cmd.current_dir(here).kill_on_drop(true).output().await?;
+
+    if res.status.success() {
+        let info = serde_json::from_slice::<SubtitlesInfo>(&res.stdout)?;
+        Ok(available_tracks(info.subtitles, false)
+            .chain(available_tracks(info.automatic_captions, true))
+            .collect())
+    } else {
+        Err(DownloadError::new(
+            "failed to fetch video subtitles",
+            String::from_utf8_lossy(&res.stderr),
+        )
+        .into())
+    }
+}
@@ -0,0 +1,140 @@
+//! Parsing and formatting of timestamps and time ranges within videos.
+
+use crate::types::*;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Parses a timestamp in any of the forms YouTube uses, such as `90`, `90s`,
+/// `1m30s` or `1h2m3s`, or in clock form such as `1:30` or `1:02:03`. The
+/// seconds may be fractional. If the text is not a timestamp, `None` is
+/// returned.
+pub fn parse_timestamp(text: &str) -> Option<Duration> {
+    let text = text.trim().to_ascii_lowercase();
+
+    if text.is_empty() {
+        return None;
+    }
+
+    let secs = if text.contains(':') {
+        let parts = text.split(':').collect::<Vec<_>>();
+        let (secs, rest) = parts.split_last()?;
+
+        if rest.len() > 2 {
+            return None;
+        }
+
+        let minutes = rest.iter().try_fold(0u64, |total, part| {
+            part.parse::<u64>().ok().map(|value| total * 60 + value)
+        })?;
+
+        minutes as f64 * 60.0 + secs.parse::<f64>().ok()?
+    } else {
+        let mut total = 0.0;
+        let mut number = String::new();
+
+        for c in text.chars() {
+            let unit = match c {
+                '0'..='9' | '.' => {
+                    number.push(c);
+                    continue;
+                }
+                'h' => 3600.0,
+                'm' => 60.0,
+                's' => 1.0,
+                _ => return None,
+            };
+
+            total += number.parse::<f64>().ok()? * unit;
+            number.clear();
+        }
+
+        if !number.is_empty() {
+            total += number.parse::<f64>().ok()?;
+        }
+
+        total
+    };
+
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Formats a timestamp in clock form, as `M:SS`, or `H:MM:SS` if it is an
+/// hour or later. Fractions of a second are included only if present.
+pub fn format_timestamp(time: Duration) -> String {
+    let secs = time.as_secs();
+    let millis = time.subsec_millis();
+    let clock = if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    };
+
+    if millis == 0 {
+        clock
+    } else {
+        format!("{}.{:03}", clock, millis)
+    }
+}
+
+/// An error in a clip range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClipRangeError {
+    /// A description of the error.
+    description: String,
+}
+
+impl ClipRangeError {
+    /// Creates a new clip range error.
+    fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+        }
+    }
+}
+
+impl Display for ClipRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl std::error::Error for ClipRangeError {}
+
+/// Validates that a clip range ends after it starts.
+pub fn validate_clip_range(clip: &ClipRange) -> Result<(), ClipRangeError> {
+    match (clip.start, clip.end) {
+        (_, Some(end)) if end.is_zero() => {
+            Err(ClipRangeError::new("the end time must be after 0:00"))
+        }
+        (Some(start), Some(end)) if end <= start => Err(ClipRangeError::new(
+            "the end time must be after the start time",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parses a clip range from start and end timestamps, either of which may be
+/// empty to keep the video from its beginning or to its end.
+pub fn parse_clip_range(
+    start: &str,
+    end: &str,
+    mode: ClipMode,
+) -> Result<ClipRange, ClipRangeError> {
+    let parse = |text: &str, name: &str| {
+        if text.trim().is_empty() {
+            Ok(None)
+        } else {
+            parse_timestamp(text).map(Some).ok_or_else(|| {
+                ClipRangeError::new(format!("invalid {} time: {}", name, text.trim()))
+            })
+        }
+    };
+    let clip = ClipRange {
+        start: parse(start, "start")?,
+        end: parse(end, "end")?,
+        mode,
+    };
+
+    validate_clip_range(&clip)?;
+    Ok(clip)
+}
@@ -1,6 +1,8 @@
 //! Types used in multiple places across the application.
 
+use crate::constants::*;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// The download content type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -11,4 +13,566 @@ pub enum ContentType {
     Audio,
     /// The entire video, with audio.
     Video,
+    /// Subtitle and caption tracks of the video.
+    Subtitles,
+}
+
+impl ContentType {
+    /// Gets a human-readable name of the content type.
+    pub const fn type_name(self) -> &'static str {
+        match self {
+            Self::Thumbnail => "Thumbnail",
+            Self::Audio => "Audio",
+            Self::Video => "Video",
+            Self::Subtitles => "Subtitles",
+        }
+    }
+}
+
+/// A preferred video codec.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum VideoCodec {
+    /// No codec preference.
+    #[default]
+    Any,
+    /// The AV1 codec.
+    Av1,
+    /// The VP9 codec.
+    Vp9,
+    /// The H.264 (AVC) codec.
+    H264,
+}
+
+impl VideoCodec {
+    /// All video codec preferences, in display order.
+    pub const ALL: [Self; 4] = [Self::Any, Self::Av1, Self::Vp9, Self::H264];
+
+    /// Gets a human-readable name of the codec.
+    pub const fn codec_name(self) -> &'static str {
+        match self {
+            Self::Any => "Any",
+            Self::Av1 => "AV1",
+            Self::Vp9 => "VP9",
+            Self::H264 => "H.264",
+        }
+    }
+
+    /// Returns youtube-dl format filters matching the codec. A codec may be
+    /// reported under more than one name, so each filter is an alternative.
+    fn format_filters(self) -> &'static [&'static str] {
+        match self {
+            Self::Any => &[""],
+            Self::Av1 => &["[vcodec^=av01]"],
+            Self::Vp9 => &["[vcodec^=vp9]", "[vcodec^=vp09]"],
+            Self::H264 => &["[vcodec^=avc1]"],
+        }
+    }
+}
+
+/// The preferred video quality.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FormatPreference {
+    /// The maximum video height in pixels, or `None` for the best available.
+    pub max_height: Option<u32>,
+    /// The preferred video codec. Other codecs are used if no stream in the
+    /// preferred codec is available.
+    pub codec: VideoCodec,
+}
+
+impl FormatPreference {
+    /// Builds a youtube-dl format selector for the preference. The best
+    /// matching video and audio streams are merged, falling back to the best
+    /// progressive stream, and finally to anything at all.
+    pub fn format_selector(&self) -> String {
+        let height = self
+            .max_height
+            .map(|max_height| format!("[height<={}]", max_height))
+            .unwrap_or_default();
+        let mut alternatives = Vec::new();
+
+        if self.codec != VideoCodec::Any {
+            for codec in self.codec.format_filters() {
+                alternatives.push(format!("bestvideo{}{}+bestaudio", height, codec));
+            }
+        }
+
+        alternatives.push(format!("bestvideo{}+bestaudio", height));
+        alternatives.push(format!("best{}", height));
+        alternatives.push("best".to_owned());
+        alternatives.join("/")
+    }
+}
+
+/// An audio output format.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum AudioFormat {
+    /// MP3 audio.
+    #[default]
+    Mp3,
+    /// AAC audio in an MPEG-4 container.
+    M4a,
+    /// Opus audio in an Ogg container.
+    Opus,
+    /// Lossless FLAC audio.
+    Flac,
+    /// Uncompressed PCM audio.
+    Wav,
+    /// Vorbis audio in an Ogg container.
+    Ogg,
+}
+
+impl AudioFormat {
+    /// All audio formats, in display order.
+    pub const ALL: [Self; 6] = [
+        Self::Mp3,
+        Self::M4a,
+        Self::Opus,
+        Self::Flac,
+        Self::Wav,
+        Self::Ogg,
+    ];
+
+    /// The bitrates in kilobits per second offered for lossy formats.
+    pub const BITRATES: [u32; 6] = [96, 128, 160, 192, 256, 320];
+
+    /// Gets a human-readable name of the format.
+    pub const fn format_name(self) -> &'static str {
+        match self {
+            Self::Mp3 => "MP3",
+            Self::M4a => "M4A (AAC)",
+            Self::Opus => "Opus",
+            Self::Flac => "FLAC",
+            Self::Wav => "WAV",
+            Self::Ogg => "Ogg Vorbis",
+        }
+    }
+
+    /// Gets the file extension of the format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::M4a => "m4a",
+            Self::Opus => "opus",
+            Self::Flac => "flac",
+            Self::Wav => "wav",
+            Self::Ogg => "ogg",
+        }
+    }
+
+    /// Gets the name of the ffmpeg encoder producing the format.
+    pub const fn encoder(self) -> &'static str {
+        match self {
+            Self::Mp3 => "libmp3lame",
+            Self::M4a => "aac",
+            Self::Opus => "libopus",
+            Self::Flac => "flac",
+            Self::Wav => "pcm_s16le",
+            Self::Ogg => "libvorbis",
+        }
+    }
+
+    /// Gets the name ffmpeg reports for audio streams that can be copied into
+    /// the format without re-encoding.
+    pub const fn codec_name(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::M4a => "aac",
+            Self::Opus => "opus",
+            Self::Flac => "flac",
+            Self::Wav => "pcm_s16le",
+            Self::Ogg => "vorbis",
+        }
+    }
+
+    /// Returns whether the format is lossless, in which case quality settings
+    /// do not apply.
+    pub const fn is_lossless(self) -> bool {
+        matches!(self, Self::Flac | Self::Wav)
+    }
+
+    /// Returns whether the format's container can hold a cover image.
+    pub const fn supports_cover_art(self) -> bool {
+        matches!(self, Self::Mp3 | Self::M4a | Self::Flac)
+    }
+
+    /// Returns the range of variable bitrate quality levels supported by the
+    /// format's encoder, if it supports any. For MP3, lower levels are better;
+    /// for Vorbis, higher levels are better.
+    pub const fn vbr_levels(self) -> Option<(u8, u8)> {
+        match self {
+            Self::Mp3 => Some((0, 9)),
+            Self::Ogg => Some((0, 10)),
+            _ => None,
+        }
+    }
+}
+
+/// The quality of encoded audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioQuality {
+    /// A constant bitrate in kilobits per second.
+    Bitrate(u32),
+    /// A variable bitrate quality level, as understood by the encoder.
+    Vbr(u8),
+}
+
+impl Default for AudioQuality {
+    fn default() -> Self {
+        Self::Bitrate(DEFAULT_AUDIO_BITRATE)
+    }
+}
+
+impl AudioQuality {
+    /// Gets a human-readable description of the quality for a format.
+    pub fn quality_name(self, format: AudioFormat) -> String {
+        match (self, format) {
+            (Self::Bitrate(bitrate), _) => format!("{} kbps", bitrate),
+            (Self::Vbr(level), AudioFormat::Mp3) => format!("VBR V{}", level),
+            (Self::Vbr(level), _) => format!("VBR q{}", level),
+        }
+    }
+}
+
+/// The preferred audio output format and quality.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AudioPreference {
+    /// The output format.
+    pub format: AudioFormat,
+    /// The encoding quality. This is ignored for lossless formats, and when
+    /// the source audio is copied without re-encoding.
+    pub quality: AudioQuality,
+}
+
+impl AudioPreference {
+    /// Returns the ffmpeg arguments that encode audio according to the
+    /// preference. A variable bitrate quality is only used if the format's
+    /// encoder supports it, falling back to the default bitrate otherwise.
+    pub fn encoder_args(&self) -> Vec<String> {
+        let mut args = vec!["-c:a".to_owned(), self.format.encoder().to_owned()];
+
+        if self.format.is_lossless() {
+            return args;
+        }
+
+        match (self.quality, self.format.vbr_levels()) {
+            (AudioQuality::Vbr(level), Some((min, max))) => {
+                args.push("-q:a".to_owned());
+                args.push(level.clamp(min, max).to_string());
+            }
+            (AudioQuality::Bitrate(bitrate), _) => {
+                args.push("-b:a".to_owned());
+                args.push(format!("{}k", bitrate));
+            }
+            (AudioQuality::Vbr(_), None) => {
+                args.push("-b:a".to_owned());
+                args.push(format!("{}k", DEFAULT_AUDIO_BITRATE));
+            }
+        }
+
+        args
+    }
+}
+
+/// How to handle an output file that already exists.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum CollisionPolicy {
+    /// Replace the existing file.
+    Overwrite,
+    /// Skip the download if the existing file is of the same video, and
+    /// otherwise save to a new name.
+    #[default]
+    SkipIfSame,
+    /// Save to a new name, such as `Title (2).mp4`.
+    Rename,
+    /// Ask what to do.
+    Prompt,
+}
+
+impl CollisionPolicy {
+    /// All collision policies, in display order.
+    pub const ALL: [Self; 4] = [
+        Self::Overwrite,
+        Self::SkipIfSame,
+        Self::Rename,
+        Self::Prompt,
+    ];
+
+    /// Gets a human-readable name of the policy.
+    pub const fn policy_name(self) -> &'static str {
+        match self {
+            Self::Overwrite => "Overwrite",
+            Self::SkipIfSame => "Skip if same video",
+            Self::Rename => "Keep both",
+            Self::Prompt => "Ask",
+        }
+    }
+}
+
+/// How operations that fail transiently are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// The maximum number of times an operation is attempted, including the
+    /// first attempt.
+    pub max_attempts: u32,
+    /// The delay in seconds before the first retry.
+    pub initial_delay_secs: u64,
+    /// The factor by which the delay grows with each subsequent retry.
+    pub backoff_factor: u32,
+    /// The maximum delay in seconds before any retry.
+    pub max_delay_secs: u64,
+    /// The maximum random variation of each delay, as a percentage of the
+    /// delay.
+    pub jitter_percent: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            initial_delay_secs: DEFAULT_RETRY_INITIAL_DELAY_SECS,
+            backoff_factor: DEFAULT_RETRY_BACKOFF_FACTOR,
+            max_delay_secs: DEFAULT_RETRY_MAX_DELAY_SECS,
+            jitter_percent: DEFAULT_RETRY_JITTER_PERCENT,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the given retry, counting from 1, without
+    /// jitter applied.
+    pub fn backoff_delay(&self, retry: u32) -> Duration {
+        let growth = u64::from(self.backoff_factor).saturating_pow(retry.saturating_sub(1));
+        let delay_secs = self
+            .initial_delay_secs
+            .saturating_mul(growth)
+            .min(self.max_delay_secs);
+        Duration::from_secs(delay_secs)
+    }
+}
+
+/// How a time range is cut from a download.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum ClipMode {
+    /// Copy the streams without re-encoding. This is fast, but video can
+    /// only be cut at keyframes, so the range may begin a few seconds early.
+    #[default]
+    Fast,
+    /// Re-encode the range so that it begins and ends exactly at the
+    /// requested times.
+    Accurate,
+}
+
+impl ClipMode {
+    /// All clip modes, in display order.
+    pub const ALL: [Self; 2] = [Self::Fast, Self::Accurate];
+
+    /// Gets a human-readable name of the mode.
+    pub const fn mode_name(self) -> &'static str {
+        match self {
+            Self::Fast => "Fast (cut at keyframes)",
+            Self::Accurate => "Accurate (re-encode)",
+        }
+    }
+
+    /// Returns the ffmpeg arguments that encode a video clip in this mode.
+    pub fn encoder_args(self) -> Vec<String> {
+        match self {
+            Self::Fast => vec!["-c".to_owned(), "copy".to_owned()],
+            Self::Accurate => vec![
+                "-c:v".to_owned(),
+                "libx264".to_owned(),
+                "-preset".to_owned(),
+                "veryfast".to_owned(),
+                "-crf".to_owned(),
+                CLIP_VIDEO_CRF.to_string(),
+                "-c:a".to_owned(),
+                "aac".to_owned(),
+                "-b:a".to_owned(),
+                format!("{}k", DEFAULT_AUDIO_BITRATE),
+            ],
+        }
+    }
+}
+
+/// Formats a time compactly for use in file names, e.g. `1h2m3s`.
+fn compact_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    let millis = time.subsec_millis();
+    let mut text = String::new();
+
+    if secs >= 3600 {
+        text.push_str(&format!("{}h", secs / 3600));
+    }
+
+    if secs >= 60 {
+        text.push_str(&format!("{}m", (secs / 60) % 60));
+    }
+
+    if millis == 0 {
+        text.push_str(&format!("{}s", secs % 60));
+    } else {
+        text.push_str(&format!("{}.{:03}s", secs % 60, millis));
+    }
+
+    text
+}
+
+/// A time range of a video to keep. A range with neither a start nor an end
+/// keeps the whole video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipRange {
+    /// The time to begin at, or `None` for the beginning of the video.
+    pub start: Option<Duration>,
+    /// The time to end at, or `None` for the end of the video.
+    pub end: Option<Duration>,
+    /// How the range is cut.
+    pub mode: ClipMode,
+}
+
+impl ClipRange {
+    /// Returns whether the range excludes any part of the video.
+    pub fn is_clipped(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    /// Returns a short file-name-safe description of the range, such as
+    /// `1m30s-2m0s`, or `None` if the range keeps the whole video.
+    pub fn label(&self) -> Option<String> {
+        self.is_clipped().then(|| {
+            format!(
+                "{}-{}",
+                compact_time(self.start.unwrap_or_default()),
+                self.end.map(compact_time).as_deref().unwrap_or("end")
+            )
+        })
+    }
+
+    /// Returns the ffmpeg arguments that seek the input to the start of the
+    /// range. These must precede the input file.
+    pub fn input_args(&self) -> Vec<String> {
+        match self.start {
+            Some(start) => vec!["-ss".to_owned(), format!("{:.3}", start.as_secs_f64())],
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the ffmpeg arguments that stop the output at the end of the
+    /// range. Input seeking resets timestamps to zero, so the length of the
+    /// range is given rather than its end.
+    pub fn output_args(&self) -> Vec<String> {
+        match self.end {
+            Some(end) => {
+                let length = end.saturating_sub(self.start.unwrap_or_default());
+                vec!["-t".to_owned(), format!("{:.3}", length.as_secs_f64())]
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// A subtitle file format.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum SubtitleFormat {
+    /// SubRip.
+    #[default]
+    Srt,
+    /// WebVTT, the format YouTube serves subtitles in.
+    Vtt,
+}
+
+impl SubtitleFormat {
+    /// All subtitle formats, in display order.
+    pub const ALL: [Self; 2] = [Self::Srt, Self::Vtt];
+
+    /// Gets a human-readable name of the format.
+    pub const fn format_name(self) -> &'static str {
+        match self {
+            Self::Srt => "SRT",
+            Self::Vtt => "WebVTT",
+        }
+    }
+
+    /// Gets the file extension of the format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+        }
+    }
+}
+
+/// A subtitle track of a video.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SubtitleTrack {
+    /// The language code of the track, as reported by youtube-dl.
+    pub language: String,
+    /// Whether the track is generated automatically, rather than uploaded
+    /// with the video.
+    pub automatic: bool,
+}
+
+impl SubtitleTrack {
+    /// Returns a short name for the track, such as `en` or `en.auto`, used
+    /// in file names.
+    pub fn file_label(&self) -> String {
+        if self.automatic {
+            format!("{}.auto", self.language)
+        } else {
+            self.language.clone()
+        }
+    }
+}
+
+/// How downloaded subtitles are saved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubtitlePreference {
+    /// The format subtitle files are saved in.
+    pub format: SubtitleFormat,
+    /// Whether subtitles downloaded with a video are embedded into it as
+    /// soft subtitle tracks, rather than saved alongside it.
+    pub embed: bool,
+}
+
+/// The program used to extract video information and download streams.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum ExtractorKind {
+    /// youtube-dl.
+    #[default]
+    YoutubeDl,
+    /// yt-dlp, a more actively maintained fork of youtube-dl.
+    YtDlp,
+    /// A fake extractor that serves placeholder information and generates
+    /// media locally, for testing downloads without network access.
+    #[cfg(test)]
+    Fake,
+}
+
+impl ExtractorKind {
+    /// All extractors offered for selection, in display order.
+    pub const ALL: [Self; 2] = [Self::YoutubeDl, Self::YtDlp];
+
+    /// Gets a human-readable name of the extractor.
+    pub const fn extractor_name(self) -> &'static str {
+        match self {
+            Self::YoutubeDl => "youtube-dl",
+            Self::YtDlp => "yt-dlp",
+            #[cfg(test)]
+            Self::Fake => "Fake",
+        }
+    }
 }